@@ -0,0 +1,206 @@
+use std::fmt::Write;
+
+use strum::IntoEnumIterator;
+
+use crate::classes::{HashAlgorithm, OutputFormat, GIT_VERSION_SHORT, VERSION};
+use crate::json::JsonString;
+
+// Reports which CPU extensions the hashing backends can use. Acceleration is chosen when the binary is
+// built (the `asm` and `no-accel` cargo features) and by runtime CPU detection within each crate
+
+/// true if built with the `no-accel` feature, forcing the portable software backends
+const NO_ACCEL: bool = cfg!(feature = "no-accel");
+
+/// true if built with the `asm` feature, using assembly backends for SHA-1, SHA-2 and MD5
+const ASM: bool = cfg!(feature = "asm");
+
+/// Cargo features, and whether this binary was built with them
+fn build_features() -> [(&'static str, bool); 10] {
+    [
+        ("asm", ASM),
+        ("no-accel", NO_ACCEL),
+        ("parallel", cfg!(feature = "parallel")),
+        ("progress", cfg!(feature = "progress")),
+        ("glob", cfg!(feature = "glob")),
+        ("tui", cfg!(feature = "tui")),
+        ("clipboard", cfg!(feature = "clipboard")),
+        ("notify", cfg!(feature = "notify")),
+        ("webhook", cfg!(feature = "webhook")),
+        ("sha3-finalists", cfg!(feature = "sha3-finalists")),
+    ]
+}
+
+/// CPU features relevant to the hashers, and whether this machine has them
+fn cpu_features() -> Vec<(&'static str, bool)> {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        vec![
+            ("sha-ni", is_x86_feature_detected!("sha")),
+            ("sse4.1", is_x86_feature_detected!("sse4.1")),
+            ("avx2", is_x86_feature_detected!("avx2")),
+            ("avx512f", is_x86_feature_detected!("avx512f")),
+            ("pclmulqdq", is_x86_feature_detected!("pclmulqdq")),
+        ]
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        vec![
+            ("neon", std::arch::is_aarch64_feature_detected!("neon")),
+            ("sha2", std::arch::is_aarch64_feature_detected!("sha2")),
+            ("sha3", std::arch::is_aarch64_feature_detected!("sha3")),
+            ("crc", std::arch::is_aarch64_feature_detected!("crc")),
+        ]
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        Vec::new()
+    }
+}
+
+/// true if this CPU feature was detected
+fn has(features: &[(&str, bool)], name: &str) -> bool {
+    features
+        .iter()
+        .any(|&(feature, found)| feature == name && found)
+}
+
+/// The backend each family of hashers will use on this machine
+fn backends(features: &[(&str, bool)]) -> Vec<(&'static str, &'static str)> {
+    let sha = if NO_ACCEL {
+        "portable"
+    } else if has(features, "sha-ni") || has(features, "sha2") {
+        "SHA extensions"
+    } else if ASM {
+        "assembly"
+    } else {
+        "portable"
+    };
+
+    let md5 = if ASM && !NO_ACCEL {
+        "assembly"
+    } else {
+        "portable"
+    };
+
+    let blake3 = if NO_ACCEL {
+        "portable"
+    } else if has(features, "avx512f") {
+        "AVX-512"
+    } else if has(features, "avx2") {
+        "AVX2"
+    } else if has(features, "sse4.1") {
+        "SSE4.1"
+    } else if has(features, "neon") {
+        "NEON"
+    } else {
+        "portable"
+    };
+
+    // crc32fast picks its own backend at runtime, and has no switch to turn it off
+    let crc32 = if has(features, "pclmulqdq") || has(features, "crc") {
+        "carry-less multiply"
+    } else {
+        "portable"
+    };
+
+    // the BLAKE2 SIMD crates also pick their own backend at runtime
+    let blake2_parallel = if has(features, "avx2") {
+        "AVX2"
+    } else if has(features, "sse4.1") {
+        "SSE4.1"
+    } else {
+        "portable"
+    };
+
+    vec![
+        ("SHA-1, SHA-2", sha),
+        ("MD5", md5),
+        ("BLAKE3", blake3),
+        ("CRC32", crc32),
+        ("BLAKE2bp, BLAKE2sp", blake2_parallel),
+        (
+            "SHA-3, ParallelHash, KMAC, BLAKE2, WHIRLPOOL, ED2K, TTH, fingerprints, other CRCs",
+            "portable",
+        ),
+    ]
+}
+
+/// Print the CPU features found, the build features, and the resulting backend for each hasher
+pub fn show_features_report() {
+    let features = cpu_features();
+    let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+
+    println!("CPU features ({}):", std::env::consts::ARCH);
+    if features.is_empty() {
+        println!("    none detected for this architecture");
+    }
+    for (name, found) in &features {
+        println!("    {name:<12} {}", if *found { "yes" } else { "no" });
+    }
+
+    println!("Build features:");
+    for (name, enabled) in build_features() {
+        println!("    {name:<12} {}", on_off(enabled));
+    }
+
+    println!("Backends:");
+    for (hashers, backend) in backends(&features) {
+        println!("    {hashers:<26} {backend}");
+    }
+}
+
+/// Print the version and git commit. As JSON, also the build features, algorithms and CPU acceleration
+pub fn show_version(format: OutputFormat) {
+    let version = VERSION.unwrap_or("?");
+    let enabled: Vec<&str> = build_features()
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+
+    if format == OutputFormat::Text {
+        println!("hash_rust {version} ({GIT_VERSION_SHORT})");
+        println!("features: {}", enabled.join(", "));
+        return;
+    }
+
+    let features = cpu_features();
+    let list = |items: Vec<String>| items.join(",");
+    let mut json = String::new();
+    let _ = write!(
+        json,
+        r#"{{"version":{},"git":{},"arch":{},"features":[{}],"algorithms":[{}],"cpu":{{{}}},"backends":{{{}}}}}"#,
+        JsonString(version),
+        JsonString(GIT_VERSION_SHORT),
+        JsonString(std::env::consts::ARCH),
+        list(
+            enabled
+                .iter()
+                .map(|name| JsonString(name).to_string())
+                .collect()
+        ),
+        list(
+            HashAlgorithm::iter()
+                .map(|algorithm| JsonString(&format!("{algorithm:?}")).to_string())
+                .collect()
+        ),
+        list(
+            features
+                .iter()
+                .map(|(name, found)| format!("{}:{found}", JsonString(name)))
+                .collect()
+        ),
+        list(
+            backends(&features)
+                .iter()
+                .map(|(hashers, backend)| format!(
+                    "{}:{}",
+                    JsonString(hashers),
+                    JsonString(backend)
+                ))
+                .collect()
+        ),
+    );
+    println!("{json}");
+}
@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use crate::call_hasher;
+use crate::classes::{BasicHash, ConfigSettings};
+use crate::hasher::ReaderSource;
+use crate::stats::{self, FileTiming};
+
+/// Separator between the archive path and the path of an entry inside it
+pub const ARCHIVE_SEPARATOR: &str = "!/";
+
+/// Signatures a zip archive starts with: a local file header, or the end of central directory record of an
+/// archive with no entries
+const ZIP_SIGNATURES: [&[u8; 4]; 2] = [b"PK\x03\x04", b"PK\x05\x06"];
+
+/// Whether the file starts like a zip archive. Other files are hashed as they are with --zip
+pub fn is_zip(path: &str) -> bool {
+    let mut signature = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut signature))
+        .is_ok_and(|()| ZIP_SIGNATURES.contains(&&signature))
+}
+
+/// Hash every file entry inside a zip archive. Returns the display path `archive.zip!/inner/path`, hash and timing of each entry
+pub fn hash_zip_entries(
+    config: &ConfigSettings,
+    archive_path: &str,
+) -> anyhow::Result<Vec<(String, anyhow::Result<BasicHash>, FileTiming)>> {
+    let file = File::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+    let mut results = Vec::with_capacity(archive.len());
+
+    tracing::debug!("'{archive_path}' has {} zip entries", archive.len());
+
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            continue; // directories have no content to hash
+        }
+
+        let display_path = format!("{archive_path}{ARCHIVE_SEPARATOR}{}", entry.name());
+        let source = ReaderSource {
+            reader: entry,
+            buffer_size: config.io.buffer_size,
+        };
+        let (hash, timing) =
+            stats::timed(|| call_hasher(config.algorithm, config.encoding, source));
+        results.push((display_path, hash, timing));
+    }
+
+    Ok(results)
+}
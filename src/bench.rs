@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+use strum::IntoEnumIterator;
+
+use crate::classes::{ConfigSettings, HashAlgorithm};
+use crate::hasher::FileSource;
+use crate::{call_hasher, default_encoding};
+
+/// Measure the throughput of every algorithm on in-memory data, and on the supplied file if there is one
+pub fn run_benchmark(config: &ConfigSettings) -> anyhow::Result<()> {
+    let size = usize::try_from(config.bench_size)?;
+
+    // a repeating pattern rather than zeros, just in case anything short-circuits on them
+    let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+
+    let file = config.supplied_path.as_deref();
+    if let Some(path) = file {
+        // read once first, so every algorithm sees the same warm cache
+        call_hasher(
+            HashAlgorithm::CRC32,
+            default_encoding(HashAlgorithm::CRC32),
+            FileSource {
+                path,
+                io: &config.io,
+            },
+        )?;
+    }
+
+    println!(
+        "Benchmark on {} MB in memory{}",
+        size / (1024 * 1024),
+        file.map(|path| format!(" and file '{path}'"))
+            .unwrap_or_default()
+    );
+    println!(
+        "{:<14} {:>12} {:>12}",
+        "Algorithm", "Memory MB/s", "File MB/s"
+    );
+
+    for algo in HashAlgorithm::iter() {
+        let encoding = default_encoding(algo);
+
+        let start = Instant::now();
+        call_hasher(algo, encoding, data.as_slice())?;
+        let memory_rate = megabytes_per_sec(size as u64, start.elapsed());
+
+        let file_rate = match file {
+            Some(path) => {
+                let file_size = std::fs::metadata(path)?.len();
+                let source = FileSource {
+                    path,
+                    io: &config.io,
+                };
+                let start = Instant::now();
+                call_hasher(algo, encoding, source)?;
+                format!("{:.1}", megabytes_per_sec(file_size, start.elapsed()))
+            }
+            None => "-".to_string(),
+        };
+
+        println!(
+            "{:<14} {memory_rate:>12.1} {file_rate:>12}",
+            format!("{algo:?}")
+        );
+    }
+
+    Ok(())
+}
+
+/// Throughput in MB/s, where a MB is 1024 * 1024 bytes
+fn megabytes_per_sec(bytes: u64, elapsed: Duration) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64().max(f64::EPSILON)
+}
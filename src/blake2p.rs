@@ -0,0 +1,76 @@
+use digest::{FixedOutput, HashMarker, Output, OutputSizeUser, Reset, Update};
+use generic_array::typenum::{U32, U64};
+
+// The parallel BLAKE2 variants, a tree of 4 BLAKE2b or 8 BLAKE2s leaves fed in turn, joined by a root node.
+// They give different digests from plain BLAKE2. The RustCrypto blake2 crate doesn't have them, so the
+// SIMD crates are wrapped in the same way as Crc32 and Blake3
+
+#[derive(Clone, Default)]
+pub struct Blake2bp(blake2b_simd::blake2bp::State);
+
+// Indicate that the Blake2bp struct is a Digest algorithm (a hash function)
+impl HashMarker for Blake2bp {}
+
+// Indicate that the Blake2bp struct has a fixed output size of 64 bytes
+impl OutputSizeUser for Blake2bp {
+    type OutputSize = U64;
+}
+
+// Update the hash with the provided data
+impl Update for Blake2bp {
+    #[inline]
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+// Finalize the hash and write it into the provided buffer
+impl FixedOutput for Blake2bp {
+    #[inline]
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(self.0.finalize().as_bytes());
+    }
+}
+
+// Reset the hash to its initial state
+impl Reset for Blake2bp {
+    #[inline]
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Blake2sp(blake2s_simd::blake2sp::State);
+
+// Indicate that the Blake2sp struct is a Digest algorithm (a hash function)
+impl HashMarker for Blake2sp {}
+
+// Indicate that the Blake2sp struct has a fixed output size of 32 bytes
+impl OutputSizeUser for Blake2sp {
+    type OutputSize = U32;
+}
+
+// Update the hash with the provided data
+impl Update for Blake2sp {
+    #[inline]
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+// Finalize the hash and write it into the provided buffer
+impl FixedOutput for Blake2sp {
+    #[inline]
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(self.0.finalize().as_bytes());
+    }
+}
+
+// Reset the hash to its initial state
+impl Reset for Blake2sp {
+    #[inline]
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,82 @@
+#[cfg(feature = "parallel")]
+use std::sync::OnceLock;
+
+use digest::{FixedOutput, HashMarker, Output, OutputSizeUser, Reset, Update};
+use generic_array::typenum::U32;
+#[cfg(feature = "parallel")]
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+#[cfg(feature = "parallel")]
+use crate::classes::{BasicHash, OutputEncoding};
+#[cfg(feature = "parallel")]
+use crate::hasher::encode_hash;
+
+// The blake3 crate implements a newer version of the digest traits than the rest of the hashers,
+// so wrap it in the same way as Crc32
+
+/// Pool used to hash within a single file, shared by every file in the run
+#[cfg(feature = "parallel")]
+static FILE_POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+#[derive(Clone, Default)]
+pub struct Blake3(blake3::Hasher);
+
+// Indicate that the Blake3 struct is a Digest algorithm (a hash function)
+impl HashMarker for Blake3 {}
+
+// Indicate that the Blake3 struct has a fixed output size of 32 bytes
+impl OutputSizeUser for Blake3 {
+    type OutputSize = U32;
+}
+
+// Update the hash with the provided data
+impl Update for Blake3 {
+    #[inline]
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+// Finalize the hash and write it into the provided buffer
+impl FixedOutput for Blake3 {
+    #[inline]
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(self.0.finalize().as_bytes());
+    }
+}
+
+// Reset the hash to its initial state
+impl Reset for Blake3 {
+    #[inline]
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// Hash a single file using several threads, so one huge file can use every core
+#[cfg(feature = "parallel")]
+pub fn hash_file_multithreaded(
+    path: &str,
+    threads: usize,
+    encoding: OutputEncoding,
+) -> anyhow::Result<BasicHash> {
+    let pool = file_pool(threads)?;
+    let mut hasher = blake3::Hasher::new();
+
+    // maps the file, and hashes it in parallel within the pool. Small files are just read normally
+    pool.install(|| hasher.update_mmap_rayon(path))?;
+    crate::stats::add_bytes(hasher.count());
+
+    Ok(encode_hash(hasher.finalize().as_bytes(), encoding))
+}
+
+/// Get the pool for intra-file hashing, building it on first use
+#[cfg(feature = "parallel")]
+fn file_pool(threads: usize) -> anyhow::Result<&'static ThreadPool> {
+    if let Some(pool) = FILE_POOL.get() {
+        return Ok(pool);
+    }
+
+    let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+    Ok(FILE_POOL.get_or_init(|| pool))
+}
@@ -0,0 +1,96 @@
+// Brace expansion for patterns, eg `photos/**/*.{jpg,png}`. Unix shells expand braces before hash_rust sees
+// them, but Windows shells don't, and the glob crate doesn't know them, so the pattern is expanded here into
+// one pattern for each alternative. As in bash, braces without a comma are left as they are
+
+/// Most patterns the braces can expand to, as each group multiplies them
+const MAX_PATTERNS: usize = 1024;
+
+/// The patterns a pattern expands to, in the order of the alternatives. Without braces, just the pattern
+pub fn expand(pattern: &str) -> anyhow::Result<Vec<String>> {
+    let mut patterns = Vec::new();
+    if expand_into(pattern, &mut patterns) {
+        Ok(patterns)
+    } else {
+        Err(anyhow::anyhow!(
+            "The braces in '{pattern}' make more than {MAX_PATTERNS} patterns"
+        ))
+    }
+}
+
+/// Add the patterns this one expands to, unless there would be too many
+fn expand_into(pattern: &str, patterns: &mut Vec<String>) -> bool {
+    let Some((open, close, commas)) = first_group(pattern) else {
+        patterns.push(pattern.to_string());
+        return patterns.len() <= MAX_PATTERNS;
+    };
+
+    let (head, tail) = (&pattern[..open], &pattern[close + 1..]);
+    let mut start = open + 1;
+    for end in commas.into_iter().chain([close]) {
+        if !expand_into(&format!("{head}{}{tail}", &pattern[start..end]), patterns) {
+            return false;
+        }
+        start = end + 1;
+    }
+    true
+}
+
+/// The first `{` with a matching `}` and a comma between them: the positions of the braces, and of the commas
+/// that aren't inside a nested pair
+fn first_group(pattern: &str) -> Option<(usize, usize, Vec<usize>)> {
+    let bytes = pattern.as_bytes();
+    let mut position = 0;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'[' => position = class_end(bytes, position),
+            b'{' => {
+                if let Some((close, commas)) = group_end(bytes, position) {
+                    if !commas.is_empty() {
+                        return Some((position, close, commas));
+                    }
+                }
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+    None
+}
+
+/// The matching `}` for the `{` at `open`, and the commas at its own level
+fn group_end(bytes: &[u8], open: usize) -> Option<(usize, Vec<usize>)> {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut position = open + 1;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'[' => position = class_end(bytes, position),
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Some((position, commas)),
+            b'}' => depth -= 1,
+            b',' if depth == 0 => commas.push(position),
+            _ => {}
+        }
+        position += 1;
+    }
+    None
+}
+
+/// The `]` closing a character class started at `open`, where braces and commas are just characters. A `]`
+/// straight after the `[` or `[!` is one of them. If the class isn't closed, the `[` stands alone
+fn class_end(bytes: &[u8], open: usize) -> usize {
+    let mut position = open + 1;
+    if bytes.get(position) == Some(&b'!') {
+        position += 1;
+    }
+    if bytes.get(position) == Some(&b']') {
+        position += 1;
+    }
+    match bytes[position.min(bytes.len())..]
+        .iter()
+        .position(|&byte| byte == b']')
+    {
+        Some(offset) => position + offset,
+        None => open,
+    }
+}
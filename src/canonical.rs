@@ -0,0 +1,71 @@
+use std::path::{Path, MAIN_SEPARATOR};
+
+use crate::classes::ConfigSettings;
+use crate::output::{self, HeldLine};
+use crate::{paths, progress};
+
+// The manifest for --format canonical, which is the same byte for byte whenever the same files have the same
+// contents, however and wherever they were scanned, so manifests can be signed and compared with diff. The
+// lines are `hash  path` as sha256sum writes them, so --check reads them, under a `#` header
+
+/// Version of the manifest layout, given in its header
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Print the held results as a canonical manifest: a header, then a line for each file sorted by path
+pub fn print_manifest(config: &ConfigSettings, held: Vec<HeldLine>) {
+    // with --relative-to the paths have already been made relative to it
+    let root = config
+        .relative_to
+        .is_none()
+        .then(|| paths::scan_root(config));
+    let mut entries: Vec<(String, String)> = held
+        .into_iter()
+        .map(|line| (manifest_path(&line.path, root.as_deref()), line.hash))
+        .collect();
+    entries.sort();
+
+    let mut lines = vec![
+        format!("# hash_rust manifest {MANIFEST_VERSION}"),
+        format!("# algorithm {}", config.algorithm.name()),
+        format!(
+            "# encoding {}",
+            format!("{:?}", config.encoding).to_lowercase()
+        ),
+    ];
+    lines.extend(
+        entries
+            .into_iter()
+            .map(|(path, hash)| manifest_line(&path, &hash)),
+    );
+    progress::suspend(|| lines.into_iter().for_each(output::print_line));
+}
+
+/// A path as it appears in the manifest: relative to the root if there is one, with `/` between components
+pub fn manifest_path(path: &str, root: Option<&Path>) -> String {
+    let relative = match (root, paths::absolute(path)) {
+        (Some(root), Ok(full_path)) => match paths::absolute(root) {
+            Ok(root) => paths::relative_to(&full_path, &root)
+                .to_string_lossy()
+                .into_owned(),
+            Err(_) => path.to_string(),
+        },
+        _ => path.to_string(),
+    };
+
+    if MAIN_SEPARATOR == '/' {
+        relative
+    } else {
+        relative.replace(MAIN_SEPARATOR, "/")
+    }
+}
+
+/// A line of the manifest. Like sha256sum, names with a backslash or newline are escaped, and the line marked
+/// with a backslash
+pub fn manifest_line(path: &str, hash: &str) -> String {
+    if path.contains(['\\', '\n']) {
+        let escaped = path.replace('\\', "\\\\").replace('\n', "\\n");
+        format!("\\{hash}  {escaped}")
+    } else {
+        format!("{hash}  {path}")
+    }
+}
@@ -0,0 +1,426 @@
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::classes::{
+    BasicHash, ConfigSettings, HashAlgorithm, HashFamily, OutputEncoding, Verbosity,
+};
+use crate::observer::{self, Event};
+use crate::signing::{self, PublicKey};
+use crate::{
+    color, decode_hash, default_encoding, hash_file, limits, logfile, output, paths, progress,
+    stats,
+};
+
+/// One line of a checksum file: a file and the hash it is expected to have
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChecksumEntry {
+    pub path: String,
+    pub expected: String,
+    /// the algorithm named on the line, for BSD style `SHA256 (path) = hash` lines
+    pub algorithm: Option<HashAlgorithm>,
+}
+
+impl AsRef<Path> for ChecksumEntry {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.path)
+    }
+}
+
+/// The usable lines of a checksum file, and how many lines couldn't be understood
+pub struct ChecksumFile {
+    pub entries: Vec<ChecksumEntry>,
+    pub malformed: usize,
+}
+
+/// Read the checksum file given on the command line, or stdin if there isn't one. With a public key, the file
+/// must have a good signature from it
+pub fn load(
+    config: &ConfigSettings,
+    public_key: Option<&PublicKey>,
+) -> anyhow::Result<ChecksumFile> {
+    let path = config.supplied_path.as_deref();
+    let options = config.check.unwrap_or_default();
+    let bytes = read_bytes(path)?;
+    if let Some(key) = public_key {
+        let name = path.unwrap_or("-");
+        let trusted_comment = signing::verify_manifest(&bytes, key)
+            .map_err(|e| anyhow::anyhow!("Refusing to check '{name}': {e}"))?;
+        tracing::info!("Signature verified, trusted comment: {trusted_comment}");
+    }
+    let mut checksums = parse_checksums(config, path.unwrap_or("-"), &decode_text(&bytes));
+
+    if options.ignore_missing {
+        // a stat per line, which for a large manifest on network storage is worth spreading across threads
+        #[cfg(feature = "parallel")]
+        let exists: Vec<bool> = checksums
+            .entries
+            .par_iter()
+            .map(|entry| Path::new(&entry.path).exists())
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let exists: Vec<bool> = checksums
+            .entries
+            .iter()
+            .map(|entry| Path::new(&entry.path).exists())
+            .collect();
+
+        let mut exists = exists.into_iter();
+        checksums.entries.retain(|_| exists.next().unwrap_or(false));
+    }
+
+    if checksums.malformed > 0 {
+        tracing::warn!("{} line(s) are improperly formatted", checksums.malformed);
+    }
+    Ok(checksums)
+}
+
+/// The lines of a checksum file. Blank lines and `#` comments are ignored. As with sha256sum, a line is
+/// improperly formatted unless its hash has the length and encoding of the algorithm it will be checked with.
+/// Relative paths are taken from the --base-dir directory if given, otherwise the current directory
+pub fn parse_checksums(config: &ConfigSettings, name: &str, text: &str) -> ChecksumFile {
+    let options = config.check.unwrap_or_default();
+    let mut checksums = ChecksumFile {
+        entries: Vec::new(),
+        malformed: 0,
+    };
+    for (line_number, line) in text.lines().enumerate() {
+        // lines() leaves a lone CR behind, eg from a file edited on both Windows and Unix
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = parse_line(line).filter(|entry| {
+            line_format(config, entry)
+                .is_ok_and(|(algorithm, encoding)| hash_fits(&entry.expected, algorithm, encoding))
+        });
+        match entry {
+            Some(mut entry) => {
+                entry.path = resolve(&entry.path, config.base_dir.as_deref());
+                checksums.entries.push(entry);
+            }
+            None => {
+                checksums.malformed += 1;
+                if options.warn {
+                    tracing::warn!(
+                        "{name}: {}: improperly formatted checksum line",
+                        line_number + 1
+                    );
+                }
+            }
+        }
+    }
+    checksums
+}
+
+/// The algorithm a line is checked with, and the encoding its hash is written in. The algorithm is the one
+/// named on a BSD style line, or worked out from the length of the hash when no -a was given
+fn line_format(
+    config: &ConfigSettings,
+    entry: &ChecksumEntry,
+) -> anyhow::Result<(HashAlgorithm, OutputEncoding)> {
+    let options = config.check.unwrap_or_default();
+    let algorithm = if entry.algorithm.is_some() || options.detect_algorithm {
+        detect_algorithm(entry, options.prefer)?
+    } else {
+        config.algorithm
+    };
+    let encoding = match algorithm {
+        // CRC32 is always written as a number
+        HashAlgorithm::CRC32 => OutputEncoding::U32,
+        _ if algorithm == config.algorithm && config.encoding != OutputEncoding::Unspecified => {
+            config.encoding
+        }
+        _ => default_encoding(algorithm),
+    };
+    Ok((algorithm, encoding))
+}
+
+/// Could the text be a hash made by the algorithm, written in the encoding?
+pub fn hash_fits(text: &str, algorithm: HashAlgorithm, encoding: OutputEncoding) -> bool {
+    let bits = algorithm.digest_bits();
+    match encoding {
+        OutputEncoding::U32 => {
+            text.bytes().all(|b| b.is_ascii_digit())
+                && text
+                    .parse::<u32>()
+                    .is_ok_and(|number| bits >= 32 || number >> bits == 0)
+        }
+        _ => decode_hash(&BasicHash(text.to_string()), encoding, algorithm)
+            .is_some_and(|bytes| bytes.len() == bits.div_ceil(8)),
+    }
+}
+
+/// A path from a checksum file, with a relative path taken from `base_dir`. Absolute paths are left alone
+pub fn resolve(path: &str, base_dir: Option<&Path>) -> String {
+    match base_dir {
+        Some(base) if Path::new(path).is_relative() => {
+            base.join(path).to_string_lossy().into_owned()
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Read a checksum file as text, or stdin if no file is given or it is `-`
+pub fn read_text(path: Option<&str>) -> anyhow::Result<String> {
+    Ok(decode_text(&read_bytes(path)?))
+}
+
+/// Read a checksum file as it is, or stdin if no file is given or it is `-`
+fn read_bytes(path: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    match path {
+        None | Some("-") => {
+            let mut bytes = Vec::new();
+            io::stdin().lock().read_to_end(&mut bytes)?;
+            Ok(bytes)
+        }
+        Some(path) => Ok(std::fs::read(path)?),
+    }
+}
+
+/// Checksum files written on Windows are often UTF-16, or UTF-8 with a byte order mark.
+/// UTF-16 is recognised by its byte order mark, or by the zero bytes in ASCII text when there isn't one
+pub fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [first, 0, ..] if *first != 0 => utf16(bytes, u16::from_le_bytes),
+        [0, second, ..] if *second != 0 => utf16(bytes, u16::from_be_bytes),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Parse a line written by hash_rust or sha256sum (`hash path`, `hash  path` or `hash *path`),
+/// or a BSD style line as written by `sha256sum --tag` (`SHA256 (path) = hash`)
+pub fn parse_line(line: &str) -> Option<ChecksumEntry> {
+    // sha256sum starts a line with a backslash when the path has been escaped
+    let (line, escaped) = match line.strip_prefix('\\') {
+        Some(rest) => (rest, true),
+        None => (line, false),
+    };
+
+    let mut entry = parse_bsd_line(line).or_else(|| parse_gnu_line(line))?;
+    if escaped {
+        entry.path = unescape(&entry.path);
+    }
+    Some(entry)
+}
+
+/// `ALGO (path) = hash`
+fn parse_bsd_line(line: &str) -> Option<ChecksumEntry> {
+    let (tag, rest) = line.split_once(" (")?;
+    let (path, expected) = rest.rsplit_once(") = ")?;
+    if path.is_empty() || !is_hash(expected) {
+        return None;
+    }
+
+    Some(ChecksumEntry {
+        path: path.to_string(),
+        expected: expected.to_string(),
+        algorithm: Some(parse_tag(tag)?),
+    })
+}
+
+/// `hash path`, where the path may be marked with `*` for binary mode, or a space for text mode
+fn parse_gnu_line(line: &str) -> Option<ChecksumEntry> {
+    let (expected, rest) = line.split_once(' ')?;
+    let path = rest
+        .strip_prefix('*')
+        .or_else(|| rest.strip_prefix(' '))
+        .unwrap_or(rest);
+    if path.is_empty() || !is_hash(expected) {
+        return None;
+    }
+
+    Some(ChecksumEntry {
+        path: path.to_string(),
+        expected: expected.to_string(),
+        algorithm: None,
+    })
+}
+
+/// Could this be a hash in any of the output encodings?
+fn is_hash(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '='))
+}
+
+/// The algorithm named by a BSD style tag, eg `SHA256` or `BLAKE2b`
+fn parse_tag(tag: &str) -> Option<HashAlgorithm> {
+    match tag.to_ascii_uppercase().as_str() {
+        "SHA224" => Some(HashAlgorithm::SHA2_224),
+        "SHA256" => Some(HashAlgorithm::SHA2_256),
+        "SHA384" => Some(HashAlgorithm::SHA2_384),
+        "SHA512" => Some(HashAlgorithm::SHA2_512),
+        "BLAKE2B" => Some(HashAlgorithm::Blake2B512),
+        "BLAKE2S" => Some(HashAlgorithm::Blake2S256),
+        _ => HashAlgorithm::from_str(tag).ok(),
+    }
+}
+
+/// The name of an algorithm as written on a BSD style line, the reverse of `parse_tag`
+pub fn bsd_tag(algorithm: HashAlgorithm) -> &'static str {
+    match algorithm {
+        HashAlgorithm::SHA2_224 => "SHA224",
+        HashAlgorithm::SHA2_256 => "SHA256",
+        HashAlgorithm::SHA2_384 => "SHA384",
+        HashAlgorithm::SHA2_512 => "SHA512",
+        HashAlgorithm::SHA3_256 => "SHA3-256",
+        HashAlgorithm::Blake2B512 => "BLAKE2b",
+        HashAlgorithm::Blake2S256 => "BLAKE2s",
+        other => other.name(),
+    }
+}
+
+/// The algorithm for a line of a checksum file, from the name on a BSD style line or the length of the hash
+pub fn detect_algorithm(
+    entry: &ChecksumEntry,
+    prefer: HashFamily,
+) -> anyhow::Result<HashAlgorithm> {
+    if let Some(algorithm) = entry.algorithm {
+        return Ok(algorithm);
+    }
+
+    let hash = entry.expected.as_str();
+    if hash.len() == 10 && hash.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(HashAlgorithm::CRC32); // written as a 32-bit number
+    }
+    if !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(anyhow::anyhow!(
+            "Can't tell the algorithm as the hash isn't hex, use -a"
+        ));
+    }
+
+    let algorithm = match (hash.len(), prefer) {
+        (32, _) => HashAlgorithm::MD5,
+        (40, _) => HashAlgorithm::SHA1,
+        (56, _) => HashAlgorithm::SHA2_224,
+        (64, HashFamily::SHA2) => HashAlgorithm::SHA2_256,
+        (64, HashFamily::SHA3) => HashAlgorithm::SHA3_256,
+        (64, HashFamily::Blake2) => HashAlgorithm::Blake2S256,
+        (64, HashFamily::Blake3) => HashAlgorithm::Blake3,
+        (96, HashFamily::SHA3) => HashAlgorithm::SHA3_384,
+        (96, _) => HashAlgorithm::SHA2_384,
+        (128, HashFamily::SHA3) => HashAlgorithm::SHA3_512,
+        (128, HashFamily::Blake2) => HashAlgorithm::Blake2B512,
+        (128, _) => HashAlgorithm::SHA2_512,
+        (length, _) => {
+            return Err(anyhow::anyhow!(
+                "Can't tell the algorithm from a hash of {length} hex digits, use -a"
+            ))
+        }
+    };
+    Ok(algorithm)
+}
+
+/// Undo sha256sum's escaping of backslashes and newlines in paths
+fn unescape(path: &str) -> String {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Hash one file from the checksum file, and print whether it matches
+pub fn verify(config: &ConfigSettings, entry: &ChecksumEntry) {
+    let _span = tracing::debug_span!("file", path = entry.path).entered();
+    // held until this file is finished with, and closed
+    let _permit = limits::open_permit();
+
+    let options = config.check.unwrap_or_default();
+    let pathstr = entry.path.as_str();
+    observer::notify(Event::FileStarted { path: pathstr });
+
+    // each line can name its own algorithm, or have it worked out from the length of the hash
+    let format = line_format(config, entry);
+    let (result, timing) = stats::timed(|| {
+        format.and_then(|(algorithm, encoding)| {
+            let actual = hash_file(
+                algorithm,
+                encoding,
+                &config.io,
+                // only BLAKE3 can spread a file across threads
+                config
+                    .file_threads
+                    .filter(|_| algorithm == HashAlgorithm::Blake3),
+                pathstr,
+            )?;
+            Ok((actual, encoding))
+        })
+    });
+
+    progress::suspend(|| match result {
+        Ok((actual, encoding)) => {
+            let matched = hashes_match(&entry.expected, &actual, encoding);
+            let status = if matched { "OK" } else { "FAILED" };
+            if matched {
+                stats::file_hashed(pathstr, &actual, &timing);
+            } else {
+                progress::file_failed();
+                stats::file_mismatched(pathstr, format_args!("expected {}", entry.expected));
+            }
+            logfile::record(format_args!("{status} {actual} {timing} {pathstr}"));
+            if options.status || (matched && config.verbosity == Verbosity::Quiet) {
+                return;
+            }
+
+            let shown_path = paths::display_path(config, pathstr);
+            output::print_result(
+                &shown_path,
+                &actual,
+                &timing,
+                format_args!(
+                    "{}: {}",
+                    color::path(&shown_path),
+                    color::status(status, matched)
+                ),
+            );
+        }
+        Err(e) => {
+            progress::file_failed();
+            stats::file_failed(pathstr, &e);
+            logfile::record(format_args!("ERROR {pathstr}: {e:#}"));
+            if !options.status {
+                tracing::error!(path = pathstr, error = %format!("{e:#}"), "Could not check the file");
+            }
+        }
+    });
+
+    progress::file_done();
+}
+
+/// Hex digits are compared ignoring case, other encodings exactly
+fn hashes_match(expected: &str, actual: &BasicHash, encoding: OutputEncoding) -> bool {
+    match encoding {
+        OutputEncoding::Hex | OutputEncoding::Unspecified => {
+            expected.eq_ignore_ascii_case(&actual.0)
+        }
+        _ => expected == actual.0,
+    }
+}
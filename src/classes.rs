@@ -1,20 +1,68 @@
 use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 use git_version::git_version;
-use strum::EnumString;
+use strum::{EnumIter, EnumMessage, EnumString};
+
+use crate::crc::CrcSpec;
 
 pub const DEFAULT_HASH: HashAlgorithm = HashAlgorithm::SHA3_256;
+pub const DEFAULT_MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+pub const DEFAULT_BUFFER_SIZE: usize = 4096 * 8;
+pub const DEFAULT_BENCH_SIZE: u64 = 256 * 1024 * 1024;
 pub const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 // pub const GIT_VERSION: &str = git_version!(args = ["--abbrev=40", "--always", "--dirty=+"]);
 pub const GIT_VERSION_SHORT: &str = git_version!(args = ["--abbrev=14", "--always", "--dirty=+"]);
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString, EnumIter, EnumMessage)]
 #[strum(ascii_case_insensitive)]
 pub enum HashAlgorithm {
     #[strum(serialize = "CRC32", serialize = "CRC-32")]
     CRC32,
+    /// any CRC, described by --crc-spec
+    #[strum(serialize = "CRC")]
+    Crc,
+    #[strum(
+        serialize = "CRC16-CCITT",
+        serialize = "CRC16_CCITT",
+        serialize = "CRC-16/CCITT-FALSE"
+    )]
+    Crc16Ccitt,
+    #[strum(
+        serialize = "CRC16-MODBUS",
+        serialize = "CRC16_MODBUS",
+        serialize = "CRC-16/MODBUS"
+    )]
+    Crc16Modbus,
+    #[strum(
+        serialize = "CRC16-XMODEM",
+        serialize = "CRC16_XMODEM",
+        serialize = "CRC-16/XMODEM"
+    )]
+    Crc16Xmodem,
+    #[strum(
+        serialize = "CRC16-USB",
+        serialize = "CRC16_USB",
+        serialize = "CRC-16/USB"
+    )]
+    Crc16Usb,
+    #[strum(
+        serialize = "CRC8-SMBUS",
+        serialize = "CRC8_SMBUS",
+        serialize = "CRC-8/SMBUS"
+    )]
+    Crc8Smbus,
+    #[strum(
+        serialize = "CRC8-MAXIM",
+        serialize = "CRC8_MAXIM",
+        serialize = "CRC-8/MAXIM-DOW"
+    )]
+    Crc8Maxim,
     #[strum(serialize = "MD5", serialize = "MD-5")]
     MD5,
+    #[strum(serialize = "ED2K", serialize = "EDONKEY")]
+    Ed2k,
     #[strum(serialize = "SHA1", serialize = "SHA-1")]
     SHA1,
     #[strum(
@@ -43,6 +91,112 @@ pub enum HashAlgorithm {
     Blake2B512,
     #[strum(serialize = "BLAKE2S-256", serialize = "BLAKE2S_256")]
     Blake2S256,
+    #[strum(serialize = "BLAKE2BP", serialize = "BLAKE2BP-512")]
+    Blake2bp,
+    #[strum(serialize = "BLAKE2SP", serialize = "BLAKE2SP-256")]
+    Blake2sp,
+    #[strum(serialize = "BLAKE3")]
+    Blake3,
+    #[strum(serialize = "TTH", serialize = "TIGER-TREE")]
+    Tth,
+    #[strum(serialize = "PARALLELHASH128", serialize = "PARALLELHASH-128")]
+    ParallelHash128,
+    #[strum(serialize = "PARALLELHASH256", serialize = "PARALLELHASH-256")]
+    ParallelHash256,
+    #[strum(serialize = "KMAC128", serialize = "KMAC-128")]
+    Kmac128,
+    #[strum(serialize = "KMAC256", serialize = "KMAC-256")]
+    Kmac256,
+    // fast fingerprints, not cryptographic
+    #[strum(serialize = "RAPIDHASH", serialize = "RAPIDHASH3")]
+    Rapidhash,
+    #[strum(serialize = "T1HA2", serialize = "T1HA2-64")]
+    T1ha2,
+    #[strum(serialize = "METROHASH64", serialize = "METROHASH-64")]
+    MetroHash64,
+    #[strum(serialize = "CITYHASH128", serialize = "CITYHASH-128")]
+    CityHash128,
+    #[strum(serialize = "FARMHASH64", serialize = "FARM_FINGERPRINT")]
+    FarmHash64,
+    // SHA-3 finalists, for manifests from the competition era
+    #[cfg(feature = "sha3-finalists")]
+    #[strum(serialize = "GROESTL-256", serialize = "GROESTL_256")]
+    Groestl256,
+    #[cfg(feature = "sha3-finalists")]
+    #[strum(serialize = "GROESTL-512", serialize = "GROESTL_512")]
+    Groestl512,
+    #[cfg(feature = "sha3-finalists")]
+    #[strum(serialize = "JH-256", serialize = "JH_256")]
+    Jh256,
+    #[cfg(feature = "sha3-finalists")]
+    #[strum(serialize = "SKEIN-512-256", serialize = "SKEIN_512_256")]
+    Skein512_256,
+}
+
+impl HashAlgorithm {
+    /// The name shown to users, the first name accepted by -a
+    pub fn name(self) -> &'static str {
+        self.aliases()[0]
+    }
+
+    /// Every name accepted by -a for this algorithm
+    pub fn aliases(self) -> &'static [&'static str] {
+        self.get_serializations()
+    }
+
+    /// False for checksums and fast fingerprints, which anyone can deliberately make match
+    pub fn is_cryptographic(self) -> bool {
+        !matches!(
+            self,
+            Self::CRC32
+                | Self::Crc
+                | Self::Crc16Ccitt
+                | Self::Crc16Modbus
+                | Self::Crc16Xmodem
+                | Self::Crc16Usb
+                | Self::Crc8Smbus
+                | Self::Crc8Maxim
+                | Self::Rapidhash
+                | Self::T1ha2
+                | Self::MetroHash64
+                | Self::CityHash128
+                | Self::FarmHash64
+        )
+    }
+
+    /// Size of the digest in bits
+    pub fn digest_bits(self) -> usize {
+        match self {
+            Self::CRC32 => 32,
+            Self::Crc => crate::crc::spec().width as usize,
+            Self::Crc8Smbus | Self::Crc8Maxim => 8,
+            Self::Crc16Ccitt | Self::Crc16Modbus | Self::Crc16Xmodem | Self::Crc16Usb => 16,
+            Self::Rapidhash | Self::T1ha2 | Self::MetroHash64 | Self::FarmHash64 => 64,
+            Self::MD5 | Self::Ed2k | Self::CityHash128 => 128,
+            Self::SHA1 => 160,
+            Self::Tth => 192,
+            Self::SHA2_224 => 224,
+            Self::SHA2_256
+            | Self::SHA3_256
+            | Self::Blake2S256
+            | Self::Blake2sp
+            | Self::Blake3
+            | Self::ParallelHash128
+            | Self::Kmac128 => 256,
+            Self::SHA2_384 | Self::SHA3_384 => 384,
+            Self::SHA2_512
+            | Self::SHA3_512
+            | Self::Whirlpool
+            | Self::Blake2B512
+            | Self::Blake2bp
+            | Self::ParallelHash256
+            | Self::Kmac256 => 512,
+            #[cfg(feature = "sha3-finalists")]
+            Self::Groestl256 | Self::Jh256 | Self::Skein512_256 => 256,
+            #[cfg(feature = "sha3-finalists")]
+            Self::Groestl512 => 512,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
@@ -55,6 +209,158 @@ pub enum OutputEncoding {
     Unspecified,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// How much to report on stderr, each level including everything below it
+pub enum Verbosity {
+    /// errors only
+    Quiet,
+    /// errors and warnings
+    Normal,
+    /// also details of each file
+    Verbose,
+    /// also the configuration and internal decisions
+    Debug,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// Format of the diagnostic messages on stderr
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// Format of reports printed to stdout, eg by --version
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// Format of the results printed when hashing
+pub enum ResultFormat {
+    Text,
+    /// a manifest that is byte for byte the same for the same files, wherever they were scanned
+    Canonical,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// Checksum file formats that --convert reads and writes
+pub enum ChecksumFormat {
+    /// `hash  path`, as sha256sum writes
+    Gnu,
+    /// `SHA256 (path) = hash`, as `shasum --tag` writes
+    Bsd,
+    /// CSV with sizes, as hashdeep writes
+    Hashdeep,
+    Json,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// When to color the output
+pub enum ColorMode {
+    /// only on a terminal, and only if `NO_COLOR` is not set
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// When to draw the progress bar
+pub enum ProgressMode {
+    /// only when stderr is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// What a ** walk does with symbolic links, and on Windows junctions and other reparse points
+pub enum LinkMode {
+    /// go through them, as the glob crate does, unless that would lead round in a loop
+    #[default]
+    Follow,
+    /// leave them out
+    Skip,
+    /// leave them out, with a warning for each
+    Report,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// Preferred algorithms when --check can't tell them apart by the length of the hash
+pub enum HashFamily {
+    SHA2,
+    #[default]
+    SHA3,
+    Blake2,
+    Blake3,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// How results are sorted with --sort
+pub enum SortOrder {
+    Path,
+    /// largest first
+    Size,
+    /// identical hashes end up next to each other
+    Hash,
+    /// slowest first
+    Time,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+/// What the script written by --emit-script does with each duplicate
+pub enum ScriptKind {
+    /// replace it with a hard link to the first copy
+    Hardlink,
+    /// replace it with a copy-on-write clone of the first copy
+    Reflink,
+    Delete,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Process exit codes, so scripts can tell a mismatch from an unreadable file
+pub enum ExitStatus {
+    /// everything hashed, and every verification passed
+    Success = 0,
+    /// a hash did not match the expected value
+    Mismatch = 1,
+    /// some files could not be read or hashed
+    FileErrors = 2,
+    /// the command line was invalid
+    Usage = 3,
+    /// the run could not start or continue, eg stdin or the log file could not be read or written
+    Fatal = 4,
+}
+
+impl From<ExitStatus> for std::process::ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        Self::from(status as u8)
+    }
+}
+
+/// An error in the command line arguments, as opposed to a failure while running
+#[derive(Debug)]
+pub struct UsageError(pub anyhow::Error);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// Basic hash string. This is a wrapper around a String
 pub struct BasicHash(pub String);
@@ -66,26 +372,154 @@ impl fmt::Display for BasicHash {
     }
 }
 
+#[derive(Debug, Copy, Clone)]
+/// Options controlling how files are read
+pub struct IoOptions {
+    /// size of each read, and of the largest file read in one go
+    pub buffer_size: usize,
+    /// memory map files at least this many bytes long, if set
+    pub mmap_threshold: Option<u64>,
+    /// read through io_uring with several reads in flight (Linux only)
+    pub io_uring: bool,
+    /// bypass the OS cache with unbuffered, aligned reads
+    pub direct_io: bool,
+    /// hint sequential access, and drop the file from the page cache once hashed
+    pub drop_cache: bool,
+    /// most files read at once, independent of the number of hashing threads
+    pub io_threads: Option<usize>,
+    /// most files read at once from each device
+    pub device_readers: Option<usize>,
+    /// total read bandwidth across all threads, in bytes per second
+    pub throttle: Option<u64>,
+    /// files bigger than the buffer, up to this size, are read into memory in one go
+    pub max_in_memory: Option<u64>,
+    /// most bytes held by whole-file reads at once, across all threads
+    pub memory_budget: Option<usize>,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+/// Options for --check, matching the ones sha256sum takes
+pub struct CheckOptions {
+    /// print nothing, the exit code says whether everything matched
+    pub status: bool,
+    /// fail if any line of the checksum file is improperly formatted
+    pub strict: bool,
+    /// warn about each improperly formatted line
+    pub warn: bool,
+    /// skip files that don't exist, instead of reporting them
+    pub ignore_missing: bool,
+    /// no algorithm was given, so it is worked out from the length of the hashes
+    pub detect_algorithm: bool,
+    /// which algorithm to pick when the length of a hash fits more than one
+    pub prefer: HashFamily,
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Options for --convert
+pub struct ConvertOptions {
+    /// format to write
+    pub to: ChecksumFormat,
+    /// only keep hashes of this algorithm, and assume it for lines that don't name one
+    pub algorithm: Option<HashAlgorithm>,
+    /// which algorithm to pick when the length of a hash fits more than one
+    pub prefer: HashFamily,
+}
+
+impl Default for IoOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            mmap_threshold: None,
+            io_uring: false,
+            direct_io: false,
+            drop_cache: false,
+            io_threads: None,
+            device_readers: None,
+            throttle: None,
+            max_in_memory: None,
+            memory_budget: None,
+        }
+    }
+}
+
 #[allow(clippy::struct_excessive_bools)]
 #[readonly::make]
 #[derive(Debug)]
 /// Configuration settings
 pub struct ConfigSettings {
-    pub debug_mode: bool,
+    pub verbosity: Verbosity,
     pub exclude_fn: bool,
     pub single_thread: bool,
     pub case_sensitive: bool,
     pub algorithm: HashAlgorithm,
     pub encoding: OutputEncoding,
     pub limit_num: Option<usize>,
+    pub limit_bytes: Option<u64>,
+    pub extra_encodings: Vec<OutputEncoding>,
+    pub crc_spec: Option<CrcSpec>,
+    pub block_size: Option<usize>,
+    pub key: Option<Vec<u8>>,
+    pub customization: Option<Vec<u8>>,
+    pub sample: Option<usize>,
+    pub seed: Option<u64>,
     pub supplied_path: Option<String>,
+    pub jobs: Option<usize>,
+    pub max_open: Option<usize>,
+    pub zip: bool,
+    pub combine: bool,
+    pub check: Option<CheckOptions>,
+    pub convert: Option<ConvertOptions>,
+    pub copy_to: Option<String>,
+    pub io: IoOptions,
+    pub file_threads: Option<usize>,
+    pub bench: bool,
+    pub bench_size: u64,
+    pub stats: bool,
+    pub timing: bool,
+    pub show_size: bool,
+    pub show_mtime: bool,
+    pub with_metadata: bool,
+    pub clipboard: bool,
+    pub qr: bool,
+    pub notify: bool,
+    pub post_results: Option<String>,
+    pub sign: bool,
+    pub secret_key: Option<String>,
+    pub verify_signature: bool,
+    pub public_key: Option<String>,
+    pub progress: ProgressMode,
+    pub prescan: bool,
+    pub log_file: Option<String>,
+    pub log_format: LogFormat,
+    pub color: ColorMode,
+    pub fail_fast: bool,
+    pub timeout: Option<Duration>,
+    pub journal: Option<String>,
+    pub newer_than: Option<SystemTime>,
+    pub older_than: Option<SystemTime>,
+    pub skip_empty: bool,
+    pub only_empty: bool,
+    pub include_pseudo_fs: bool,
+    pub links: LinkMode,
+    pub events: Option<String>,
+    pub per_dir_sums: Option<String>,
+    pub relative_to: Option<PathBuf>,
+    pub base_dir: Option<PathBuf>,
+    pub absolute: bool,
+    pub ordered: bool,
+    pub hdd_mode: bool,
+    pub sort: Option<SortOrder>,
+    pub group: bool,
+    pub emit_script: Option<ScriptKind>,
+    pub result_format: ResultFormat,
+    pub tui: bool,
 }
 
 impl ConfigSettings {
     #[allow(clippy::fn_params_excessive_bools)]
     /// Create a new `ConfigSettings` object
     pub fn new(
-        debug_mode: bool,
+        verbosity: Verbosity,
         exclude_fn: bool,
         single_thread: bool,
         case_sensitive: bool,
@@ -94,20 +528,295 @@ impl ConfigSettings {
         limit_num: Option<usize>,
     ) -> Self {
         Self {
-            debug_mode,
+            verbosity,
             exclude_fn,
             single_thread,
             case_sensitive,
             algorithm,
             encoding,
             limit_num,
+            limit_bytes: None,
+            extra_encodings: Vec::new(),
+            crc_spec: None,
+            block_size: None,
+            key: None,
+            customization: None,
+            sample: None,
+            seed: None,
             supplied_path: None,
+            jobs: None,
+            max_open: None,
+            zip: false,
+            combine: false,
+            check: None,
+            convert: None,
+            copy_to: None,
+            io: IoOptions::default(),
+            file_threads: None,
+            bench: false,
+            bench_size: DEFAULT_BENCH_SIZE,
+            stats: false,
+            timing: false,
+            show_size: false,
+            show_mtime: false,
+            with_metadata: false,
+            clipboard: false,
+            qr: false,
+            notify: false,
+            post_results: None,
+            sign: false,
+            secret_key: None,
+            verify_signature: false,
+            public_key: None,
+            progress: ProgressMode::Auto,
+            prescan: false,
+            log_file: None,
+            log_format: LogFormat::Text,
+            color: ColorMode::Auto,
+            fail_fast: false,
+            timeout: None,
+            journal: None,
+            newer_than: None,
+            older_than: None,
+            skip_empty: false,
+            only_empty: false,
+            include_pseudo_fs: false,
+            links: LinkMode::Follow,
+            events: None,
+            per_dir_sums: None,
+            relative_to: None,
+            base_dir: None,
+            absolute: false,
+            ordered: false,
+            hdd_mode: false,
+            sort: None,
+            group: false,
+            emit_script: None,
+            result_format: ResultFormat::Text,
+            tui: false,
         }
     }
 
     pub fn set_supplied_path(&mut self, path: Option<String>) {
         self.supplied_path = path;
     }
+
+    pub fn set_jobs(&mut self, jobs: Option<usize>) {
+        self.jobs = jobs;
+    }
+
+    pub fn set_max_open(&mut self, files: Option<usize>) {
+        self.max_open = files;
+    }
+
+    pub fn set_zip(&mut self, zip: bool) {
+        self.zip = zip;
+    }
+
+    pub fn set_combine(&mut self, combine: bool) {
+        self.combine = combine;
+    }
+
+    pub fn set_check(&mut self, check: Option<CheckOptions>) {
+        self.check = check;
+    }
+
+    pub fn set_convert(&mut self, convert: Option<ConvertOptions>) {
+        self.convert = convert;
+    }
+
+    pub fn set_copy_to(&mut self, dest: Option<String>) {
+        self.copy_to = dest;
+    }
+
+    pub fn set_io(&mut self, io: IoOptions) {
+        self.io = io;
+    }
+
+    pub fn set_bench(&mut self, bench: bool, size: Option<u64>) {
+        self.bench = bench;
+        self.bench_size = size.unwrap_or(DEFAULT_BENCH_SIZE);
+    }
+
+    pub fn set_stats(&mut self, stats: bool) {
+        self.stats = stats;
+    }
+
+    pub fn set_timing(&mut self, timing: bool) {
+        self.timing = timing;
+    }
+
+    pub fn set_show_size(&mut self, show_size: bool) {
+        self.show_size = show_size;
+    }
+
+    pub fn set_show_mtime(&mut self, show_mtime: bool) {
+        self.show_mtime = show_mtime;
+    }
+
+    pub fn set_with_metadata(&mut self, with_metadata: bool) {
+        self.with_metadata = with_metadata;
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: bool) {
+        self.clipboard = clipboard;
+    }
+
+    pub fn set_qr(&mut self, qr: bool) {
+        self.qr = qr;
+    }
+
+    pub fn set_notify(&mut self, notify: bool) {
+        self.notify = notify;
+    }
+
+    pub fn set_post_results(&mut self, url: Option<String>) {
+        self.post_results = url;
+    }
+
+    pub fn set_sign(&mut self, sign: bool) {
+        self.sign = sign;
+    }
+
+    pub fn set_secret_key(&mut self, path: Option<String>) {
+        self.secret_key = path;
+    }
+
+    pub fn set_verify_signature(&mut self, verify_signature: bool) {
+        self.verify_signature = verify_signature;
+    }
+
+    pub fn set_public_key(&mut self, path: Option<String>) {
+        self.public_key = path;
+    }
+
+    pub fn set_progress(&mut self, progress: ProgressMode, prescan: bool) {
+        self.progress = progress;
+        self.prescan = prescan;
+    }
+
+    pub fn set_log_file(&mut self, path: Option<String>) {
+        self.log_file = path;
+    }
+
+    pub fn set_fail_fast(&mut self, fail_fast: bool) {
+        self.fail_fast = fail_fast;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_journal(&mut self, path: Option<String>) {
+        self.journal = path;
+    }
+
+    pub fn set_modified(&mut self, newer_than: Option<SystemTime>, older_than: Option<SystemTime>) {
+        self.newer_than = newer_than;
+        self.older_than = older_than;
+    }
+
+    pub fn set_extra_encodings(&mut self, encodings: Vec<OutputEncoding>) {
+        self.extra_encodings = encodings;
+    }
+
+    pub fn set_crc_spec(&mut self, spec: Option<CrcSpec>) {
+        self.crc_spec = spec;
+    }
+
+    pub fn set_block_size(&mut self, size: Option<usize>) {
+        self.block_size = size;
+    }
+
+    pub fn set_key(&mut self, key: Option<Vec<u8>>) {
+        self.key = key;
+    }
+
+    pub fn set_customization(&mut self, customization: Option<Vec<u8>>) {
+        self.customization = customization;
+    }
+
+    pub fn set_limit_bytes(&mut self, budget: Option<u64>) {
+        self.limit_bytes = budget;
+    }
+
+    pub fn set_sample(&mut self, count: Option<usize>, seed: Option<u64>) {
+        self.sample = count;
+        self.seed = seed;
+    }
+
+    pub fn set_empty_files(&mut self, skip_empty: bool, only_empty: bool) {
+        self.skip_empty = skip_empty;
+        self.only_empty = only_empty;
+    }
+
+    pub fn set_include_pseudo_fs(&mut self, include_pseudo_fs: bool) {
+        self.include_pseudo_fs = include_pseudo_fs;
+    }
+
+    pub fn set_links(&mut self, links: LinkMode) {
+        self.links = links;
+    }
+
+    pub fn set_per_dir_sums(&mut self, name: Option<String>) {
+        self.per_dir_sums = name;
+    }
+
+    pub fn set_events(&mut self, path: Option<String>) {
+        self.events = path;
+    }
+
+    pub fn set_relative_to(&mut self, base: Option<PathBuf>) {
+        self.relative_to = base;
+    }
+
+    pub fn set_base_dir(&mut self, dir: Option<PathBuf>) {
+        self.base_dir = dir;
+    }
+
+    pub fn set_absolute(&mut self, absolute: bool) {
+        self.absolute = absolute;
+    }
+
+    pub fn set_ordered(&mut self, ordered: bool) {
+        self.ordered = ordered;
+    }
+
+    pub fn set_hdd_mode(&mut self, hdd_mode: bool) {
+        self.hdd_mode = hdd_mode;
+    }
+
+    pub fn set_sort(&mut self, sort: Option<SortOrder>) {
+        self.sort = sort;
+    }
+
+    pub fn set_group(&mut self, group: bool) {
+        self.group = group;
+    }
+
+    pub fn set_emit_script(&mut self, emit_script: Option<ScriptKind>) {
+        self.emit_script = emit_script;
+    }
+
+    pub fn set_result_format(&mut self, result_format: ResultFormat) {
+        self.result_format = result_format;
+    }
+
+    pub fn set_tui(&mut self, tui: bool) {
+        self.tui = tui;
+    }
+
+    pub fn set_color(&mut self, color: ColorMode) {
+        self.color = color;
+    }
+
+    pub fn set_log_format(&mut self, format: LogFormat) {
+        self.log_format = format;
+    }
+
+    pub fn set_file_threads(&mut self, threads: Option<usize>) {
+        self.file_threads = threads;
+    }
 }
 
 pub const HELP: &str = "\
@@ -115,16 +824,102 @@ USAGE:
     hash_rust.exe [flags] [options] file glob
 FLAGS:
     -h, --help                   Prints help information
-    -d, --debug                  Debug messages
+    -V, --version                Prints version information (add --format json for build details)
+    -q, --quiet                  Errors only, no warnings
+    -v, --verbose                Details of each file on stderr (-vv for debug messages)
+    -d, --debug                  Debug messages, same as -vv
     -c, --case-sensitive         Case-sensitive glob matching
     -x, --exclude-filenames      Exclude filenames from output
     -s, --single-thread          Single-threaded (not multi-threaded)
+    -z, --zip                    Hash each entry inside zip archives
+        --mmap                   Memory-map large files instead of buffered reads
+        --io-uring               Read files using io_uring, several reads in flight (Linux only)
+        --direct-io              Read files unbuffered, bypassing the OS cache (Windows, Linux)
+        --drop-cache             Drop files from the page cache after hashing (Linux only)
+        --combine                Hash all files as one stream, in sorted order
+        --check                  Read hashes from a checksum file (or stdin) and verify the files listed
+        --status                 With --check, print nothing. The exit code shows the result
+        --strict                 With --check, fail if any line is improperly formatted
+    -w, --warn                   With --check, warn about each improperly formatted line
+        --ignore-missing         With --check, skip files that don't exist
+    -n, --no-progress            Never show the progress bar, same as --progress never
+        --prescan                Measure every file first, so the progress bar counts bytes not files
+        --skip-empty             Leave out empty (zero byte) files
+        --only-empty             Only hash empty files, eg to find placeholders left by a failed copy
+        --include-pseudo-fs      With **, also go into /proc, /sys, /dev and other virtual filesystems
+        --absolute               Print full paths, with symbolic links resolved
+        --ordered                Print results in input order, even when hashing in parallel
+        --hdd-mode               Read each disk's files one at a time, in the order they are on the disk
+        --group                  Print files with the same hash together, under a line with the hash
+        --tui                    Browse the results as they come in: sort, filter and see why files failed
+        --fail-fast              Stop at the first file that fails or doesn't match
+        --timing                 Show the bytes and milliseconds taken for each file, after the hash
+        --show-size              Show the size of each file in bytes, after the hash
+        --show-mtime             Show the modification time of each file (RFC 3339, UTC), after the hash
+        --with-metadata          Show the permissions and owner of each file, and where links point
+        --clipboard              Also copy the hash to the clipboard, when a single file is hashed
+        --qr                     Also draw the hash as a QR code, when a single file is hashed
+        --stats                  Print files, bytes, time and speed to stderr when finished
+        --notify                 Show a desktop notification with a summary when finished
+        --sign                   Sign the output with --secret-key, adding a minisign signature at the end
+        --verify-signature       With --check, refuse a checksum file not signed by --public-key
+        --features-report        Show CPU features and hash backends in use, then exit
+        --list-algorithms        List every algorithm with its size, encoding and names, then exit
+        --self-test              Check every algorithm and encoding against known answers, then exit
+        --generate-man           Print a man page (roff) built from this help, then exit
+        --bench                  Measure the speed of every algorithm, on memory and the given file
 OPTIONS:
     -a, --algorithm [algorithm]  Hash algorithm to use
-    -e, --encoding [encoding]    Output encoding (Hex, Base64, Base32. Default is Hex)
+    -e, --encoding [encoding]    Output encoding (Hex, Base64, Base32. Default is Hex), or several eg hex,base64
+    -j, --jobs [num]             Number of files hashed in parallel (-j 1 is single-threaded)
+        --io-threads [num]       Number of files read at once, separate from --jobs
+        --device-readers [num]   Number of files read at once from each disk, eg 1 for a USB drive
+        --max-open [num]         Most files open at once, to stay within ulimit
+        --throttle [size]        Limit total read speed, in bytes per second (eg 50M)
     -l, --limit [num]            Limit number of files processed
+        --limit-bytes [size]     Stop starting new files once this many bytes are reached (eg 100G)
+        --sample [num]           Hash a random selection of this many of the files
+        --seed [num]             Seed for --sample, to pick the same files again
+        --copy-to [dir]          Copy files to dir while hashing, then verify the copies
+        --file-threads [num]     Threads used within each file (BLAKE3 only)
+        --timeout [time]         Give up on a file after this long, eg 90 (seconds) or 5m
+        --log-file [file]        Append timestamped results, errors and timings to a log file
+        --journal [file]         Record completed files, and skip them when the run is restarted
+        --crc-spec [spec]        CRC used by -a crc, eg width=16,poly=0x1021,init=0xffff
+        --block-size [size]      Block size for ParallelHash (Default is 8K)
+        --key [key]              Key for KMAC
+        --customization [text]   Customization string for KMAC or ParallelHash (Default is none)
+        --per-dir-sums [name]    Also write a checksum file with this name into each directory, for its files
+        --events [file]          Write each file started, finished or failed, and bytes hashed, as JSON lines
+        --post-results [url]     POST the results and a summary as JSON to this URL when finished
+        --secret-key [file]      Minisign secret key for --sign, made without a password (minisign -G -W)
+        --public-key [file]      Minisign public key for --verify-signature
+        --newer-than [time]      Only hash files modified after this time, eg 2024-06-01 or 7d (ago)
+        --older-than [time]      Only hash files modified before this time
+        --relative-to [dir]      Print paths relative to dir, so checksum files work from another location
+        --symlinks [mode]        With **, follow, skip or report links and junctions (Default is follow)
+        --base-dir [dir]         With --check, find the relative paths in the checksum file under dir
+        --sort [order]           Print results sorted by path, size, hash or time, once every file is hashed
+        --emit-script [action]   Print a script to hardlink, reflink or delete the duplicates found
+        --convert [format]       Rewrite a checksum file as gnu, bsd, hashdeep or json, without hashing
+        --prefer [family]        With --check or --convert and no -a, the algorithm for ambiguous hashes: sha2, sha3, blake2 or blake3 (Default is sha3)
+        --profile [name]         Add the options of a named profile from the config file
+        --format [format]        Results as text or canonical (a reproducible manifest), or --version and --list-algorithms as text or json (Default is text)
+        --log-format [format]    Format of messages on stderr: text or json (Default is text)
+        --size [size]            Amount of in-memory data for --bench (Default is 256M)
+        --color [when]           Color hashes, paths and statuses: auto, always or never (Default is auto)
+        --progress [when]        Progress bar on stderr: auto, always or never (Default is auto)
+        --buffer-size [size]     Size of each read (eg 1M. Default is 32K)
+        --max-in-memory [size]   Read files up to this size into memory in one go
+        --memory-budget [size]   Most memory used by in-memory reads across all threads
+        --mmap-threshold [size]  Smallest file to memory-map with --mmap (eg 64M. Default is 16M)
     
 Algorithm can be:
-    CRC32, MD5, SHA1, WHIRLPOOL, BLAKE2S-256, BLAKE2B-512,
+    CRC32, MD5, ED2K, SHA1, WHIRLPOOL, BLAKE2S-256, BLAKE2B-512, BLAKE3, TTH,
+    BLAKE2BP, BLAKE2SP, PARALLELHASH128, PARALLELHASH256, KMAC128, KMAC256,
     SHA2 / SHA2-256 / SHA-256, SHA-224, SHA2-384, SHA2-512,
-    SHA3 / SHA3-256 (default), SHA3-384, SHA3-512";
+    SHA3 / SHA3-256 (default), SHA3-384, SHA3-512,
+    CRC16-CCITT, CRC16-MODBUS, CRC16-XMODEM, CRC16-USB, CRC8-SMBUS, CRC8-MAXIM,
+    CRC (CRC-32 in hex, or any CRC given with --crc-spec),
+    RAPIDHASH, T1HA2, METROHASH64, FARMHASH64, CITYHASH128 (fast but not cryptographic),
+    GROESTL-256, GROESTL-512, JH-256, SKEIN-512-256 (built with the sha3-finalists feature)";
@@ -0,0 +1,28 @@
+use arboard::Clipboard;
+
+use crate::output;
+
+// Copying the hash to the system clipboard, for --clipboard, through arboard, which talks to the clipboard of
+// Windows, macOS, X11 and Wayland directly
+
+/// Copy the hash to the clipboard if exactly one was printed, or say why not
+pub fn copy_result() {
+    match output::single_hash() {
+        Ok(hash) => match copy(&hash) {
+            Ok(()) => tracing::info!("Copied the hash to the clipboard"),
+            Err(e) => tracing::warn!("Could not copy the hash to the clipboard: {e}"),
+        },
+        // nothing was hashed, and the failures have been reported already
+        Err(0) => {}
+        Err(count) => {
+            tracing::warn!("Not copying to the clipboard, as {count} files were hashed, not one");
+        }
+    }
+}
+
+/// Put the text on the clipboard. On X11 and Wayland the clipboard is held by the program that set it, so it
+/// is handed to the clipboard manager, if one is running, when the clipboard is dropped here
+fn copy(text: &str) -> anyhow::Result<()> {
+    Clipboard::new()?.set_text(text)?;
+    Ok(())
+}
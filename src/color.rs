@@ -0,0 +1,80 @@
+use std::fmt::{self, Display};
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::classes::ColorMode;
+
+/// Whether results on stdout are colored
+static STDOUT_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Whether the --tui browser on stderr is colored
+static STDERR_COLOR: AtomicBool = AtomicBool::new(false);
+
+const GREEN: &str = "32";
+const CYAN: &str = "36";
+const RED: &str = "31";
+
+/// Decide whether to color each stream. Auto colors terminals only, unless `NO_COLOR` is set
+pub fn init(mode: ColorMode) {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let (stdout, stderr) = match mode {
+        ColorMode::Always => (true, true),
+        ColorMode::Never => (false, false),
+        ColorMode::Auto if no_color => (false, false),
+        ColorMode::Auto => (io::stdout().is_terminal(), io::stderr().is_terminal()),
+    };
+
+    STDOUT_COLOR.store(stdout, Ordering::Relaxed);
+    STDERR_COLOR.store(stderr, Ordering::Relaxed);
+}
+
+/// Text wrapped in an ANSI color, when that stream is colored
+pub struct Painted<T: Display> {
+    text: T,
+    code: &'static str,
+    enabled: bool,
+}
+
+impl<T: Display> Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.enabled {
+            write!(f, "\x1b[{}m{}\x1b[0m", self.code, self.text)
+        } else {
+            self.text.fmt(f)
+        }
+    }
+}
+
+fn paint<T: Display>(text: T, code: &'static str, stream: &AtomicBool) -> Painted<T> {
+    Painted {
+        text,
+        code,
+        enabled: stream.load(Ordering::Relaxed),
+    }
+}
+
+/// A hash on stdout
+pub fn hash<T: Display>(text: T) -> Painted<T> {
+    paint(text, GREEN, &STDOUT_COLOR)
+}
+
+/// true if results on stdout are colored
+pub fn stdout_colored() -> bool {
+    STDOUT_COLOR.load(Ordering::Relaxed)
+}
+
+/// true if the --tui browser on stderr is colored
+#[cfg(feature = "tui")]
+pub fn stderr_colored() -> bool {
+    STDERR_COLOR.load(Ordering::Relaxed)
+}
+
+/// A path on stdout
+pub fn path<T: Display>(text: T) -> Painted<T> {
+    paint(text, CYAN, &STDOUT_COLOR)
+}
+
+/// A verification status on stdout, red unless it passed
+pub fn status<T: Display>(text: T, passed: bool) -> Painted<T> {
+    paint(text, if passed { GREEN } else { RED }, &STDOUT_COLOR)
+}
@@ -0,0 +1,271 @@
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use crate::check::{self, ChecksumEntry};
+use crate::classes::{ChecksumFormat, ConvertOptions, HashAlgorithm, HashFamily};
+use crate::json::{self, JsonString, JsonValue};
+
+/// One hash of one file, as read from any of the checksum formats
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    pub path: String,
+    pub algorithm: HashAlgorithm,
+    pub hash: String,
+    /// known from hashdeep and JSON manifests, otherwise read from the file when needed
+    pub size: Option<u64>,
+}
+
+/// The names hashdeep gives the algorithms it shares with us
+const HASHDEEP_NAMES: [(&str, HashAlgorithm); 4] = [
+    ("md5", HashAlgorithm::MD5),
+    ("sha1", HashAlgorithm::SHA1),
+    ("sha256", HashAlgorithm::SHA2_256),
+    ("whirlpool", HashAlgorithm::Whirlpool),
+];
+
+/// Read the checksum file given on the command line (or stdin), and print it in another format.
+/// Nothing is hashed. With -a, only hashes of that algorithm are kept
+pub fn run(path: Option<&str>, options: ConvertOptions) -> anyhow::Result<()> {
+    let text = check::read_text(path)?;
+
+    let mut records = read(&text, options.algorithm, options.prefer)?;
+    if let Some(algorithm) = options.algorithm {
+        records.retain(|record| record.algorithm == algorithm);
+    }
+    if records.is_empty() {
+        return Err(anyhow::anyhow!("No hashes found to convert"));
+    }
+
+    print!("{}", write(&records, options.to)?);
+    Ok(())
+}
+
+/// Read any of the formats, telling them apart by how the text starts.
+/// Untagged lines use the given algorithm, or have it worked out from the length of the hash
+pub fn read(
+    text: &str,
+    algorithm: Option<HashAlgorithm>,
+    prefer: HashFamily,
+) -> anyhow::Result<Vec<Record>> {
+    let start = text.trim_start();
+    if start.starts_with("%%%% HASHDEEP") {
+        read_hashdeep(text)
+    } else if start.starts_with('[') || start.starts_with('{') {
+        read_json(text)
+    } else {
+        read_lines(text, algorithm, prefer)
+    }
+}
+
+/// GNU (`hash  path`) and BSD (`ALGO (path) = hash`) lines, mixed in any order
+fn read_lines(
+    text: &str,
+    algorithm: Option<HashAlgorithm>,
+    prefer: HashFamily,
+) -> anyhow::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = check::parse_line(line).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Line {}: improperly formatted checksum line",
+                line_number + 1
+            )
+        })?;
+        let algorithm = match (entry.algorithm, algorithm) {
+            (Some(tagged), _) => tagged,
+            (None, Some(given)) => given,
+            (None, None) => check::detect_algorithm(&entry, prefer)
+                .map_err(|e| anyhow::anyhow!("Line {}: {e}", line_number + 1))?,
+        };
+        let ChecksumEntry { path, expected, .. } = entry;
+        records.push(Record {
+            path,
+            algorithm,
+            hash: expected,
+            size: None,
+        });
+    }
+    Ok(records)
+}
+
+/// hashdeep's CSV: a `%%%% size,md5,sha256,filename` header names the columns, and the file name is last
+fn read_hashdeep(text: &str) -> anyhow::Result<Vec<Record>> {
+    let mut columns: Vec<Option<HashAlgorithm>> = Vec::new();
+    let mut records = Vec::new();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if let Some(header) = line.strip_prefix("%%%% ") {
+            if !header.starts_with("HASHDEEP") {
+                columns = header
+                    .split(',')
+                    .map(|name| {
+                        HASHDEEP_NAMES
+                            .iter()
+                            .find(|(known, _)| *known == name)
+                            .map(|&(_, algorithm)| algorithm)
+                    })
+                    .collect();
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with("##") {
+            continue;
+        }
+
+        // the file name can itself contain commas, so only split off the columns before it
+        let fields: Vec<&str> = line.splitn(columns.len(), ',').collect();
+        if columns.len() < 2 || fields.len() != columns.len() {
+            return Err(anyhow::anyhow!(
+                "Line {}: doesn't match the hashdeep header",
+                line_number + 1
+            ));
+        }
+        let path = fields[fields.len() - 1];
+        let size = fields[0].parse().ok();
+        for (field, column) in fields.iter().zip(&columns).skip(1) {
+            if let Some(algorithm) = column {
+                records.push(Record {
+                    path: path.to_string(),
+                    algorithm: *algorithm,
+                    hash: (*field).to_string(),
+                    size,
+                });
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// An array of `{"path": ..., "algorithm": ..., "hash": ..., "size": ...}` objects, as written by `write_json`
+fn read_json(text: &str) -> anyhow::Result<Vec<Record>> {
+    let JsonValue::Array(items) = json::parse(text)? else {
+        return Err(anyhow::anyhow!("Expected a JSON array of files"));
+    };
+
+    items
+        .iter()
+        .map(|item| {
+            let field = |name: &str| {
+                item.get(name)
+                    .and_then(JsonValue::as_str)
+                    .ok_or_else(|| anyhow::anyhow!("A JSON entry has no \"{name}\""))
+            };
+            let algorithm = field("algorithm")?;
+            Ok(Record {
+                path: field("path")?.to_string(),
+                algorithm: HashAlgorithm::from_str(algorithm)
+                    .map_err(|_| anyhow::anyhow!("Unknown algorithm '{algorithm}'"))?,
+                hash: field("hash")?.to_string(),
+                size: item
+                    .get("size")
+                    .and_then(JsonValue::as_f64)
+                    .map(|size| size as u64),
+            })
+        })
+        .collect()
+}
+
+/// Write the records in the given format
+pub fn write(records: &[Record], to: ChecksumFormat) -> anyhow::Result<String> {
+    let mut text = String::new();
+    match to {
+        ChecksumFormat::Gnu => {
+            for record in records {
+                // like sha256sum, names with a backslash or newline are escaped, and the line marked with a backslash
+                if record.path.contains(['\\', '\n']) {
+                    let escaped = record.path.replace('\\', "\\\\").replace('\n', "\\n");
+                    let _ = writeln!(text, "\\{}  {escaped}", record.hash);
+                } else {
+                    let _ = writeln!(text, "{}  {}", record.hash, record.path);
+                }
+            }
+        }
+        ChecksumFormat::Bsd => {
+            for record in records {
+                let tag = check::bsd_tag(record.algorithm);
+                let _ = writeln!(text, "{tag} ({}) = {}", record.path, record.hash);
+            }
+        }
+        ChecksumFormat::Hashdeep => write_hashdeep(records, &mut text)?,
+        ChecksumFormat::Json => {
+            let items: Vec<String> = records
+                .iter()
+                .map(|record| {
+                    let size = record
+                        .size
+                        .map(|size| format!(r#","size":{size}"#))
+                        .unwrap_or_default();
+                    format!(
+                        r#"  {{"path":{},"algorithm":{},"hash":{}{size}}}"#,
+                        JsonString(&record.path),
+                        JsonString(record.algorithm.name()),
+                        JsonString(&record.hash)
+                    )
+                })
+                .collect();
+            let _ = writeln!(text, "[\n{}\n]", items.join(",\n"));
+        }
+    }
+    Ok(text)
+}
+
+/// One line per file, with a column for each algorithm found. Sizes not in the input are read from the files
+fn write_hashdeep(records: &[Record], text: &mut String) -> anyhow::Result<()> {
+    let mut algorithms: Vec<HashAlgorithm> = Vec::new();
+    let mut files: Vec<&str> = Vec::new();
+    for record in records {
+        if !algorithms.contains(&record.algorithm) {
+            algorithms.push(record.algorithm);
+        }
+        if !files.contains(&record.path.as_str()) {
+            files.push(&record.path);
+        }
+    }
+
+    let names: Vec<&str> = algorithms
+        .iter()
+        .map(|algorithm| {
+            HASHDEEP_NAMES
+                .iter()
+                .find(|(_, known)| known == algorithm)
+                .map(|&(name, _)| name)
+                .ok_or_else(|| anyhow::anyhow!("hashdeep has no column for {}", algorithm.name()))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let _ = writeln!(text, "%%%% HASHDEEP-1.0");
+    let _ = writeln!(text, "%%%% size,{},filename", names.join(","));
+    let _ = writeln!(text, "## Converted by hash_rust");
+    let _ = writeln!(text, "## ");
+
+    for path in files {
+        let of_file: Vec<&Record> = records.iter().filter(|r| r.path == path).collect();
+        let size = match of_file.iter().find_map(|r| r.size) {
+            Some(size) => size,
+            None => std::fs::metadata(path)
+                .map_err(|e| anyhow::anyhow!("hashdeep needs the size of '{path}': {e}"))?
+                .len(),
+        };
+
+        let hashes: Vec<&str> = algorithms
+            .iter()
+            .map(|algorithm| {
+                of_file
+                    .iter()
+                    .find(|r| r.algorithm == *algorithm)
+                    .map(|r| r.hash.as_str())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("'{path}' has no {} hash for hashdeep", algorithm.name())
+                    })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let _ = writeln!(text, "{size},{},{path}", hashes.join(","));
+    }
+    Ok(())
+}
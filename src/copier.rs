@@ -0,0 +1,148 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+
+use crate::call_hasher;
+use crate::classes::{BasicHash, ConfigSettings};
+use crate::hasher::{CopySource, FileSource};
+use crate::paths;
+
+/// Outcome of copying a file and checking the copy
+pub struct CopyResult {
+    pub source_hash: BasicHash,
+    pub dest_hash: BasicHash,
+    pub dest_path: String,
+}
+
+impl CopyResult {
+    /// true if the destination hashed the same as the source stream
+    pub fn verified(&self) -> bool {
+        self.source_hash == self.dest_hash
+    }
+}
+
+/// Copy a file into the destination directory, hashing the stream as it is copied, then re-hash the copy. The
+/// copy keeps its path below the directory the scan started from, and an existing file is never overwritten
+pub fn copy_and_verify(
+    config: &ConfigSettings,
+    pathstr: &str,
+    dest_dir: &str,
+) -> anyhow::Result<CopyResult> {
+    let dest_path = Path::new(dest_dir).join(copy_path(config, pathstr)?);
+    if let Some(parent) = dest_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if same_file(pathstr, &dest_path) {
+        return Err(anyhow::anyhow!(
+            "'{}' is the file being copied",
+            dest_path.display()
+        ));
+    }
+
+    // create_new fails if the file is there, whether left by an earlier run or copied from another source
+    // directory by another thread, so nothing is ever overwritten
+    let dest_file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&dest_path)
+        .map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => anyhow::anyhow!(
+                "'{}' already exists, not overwriting it",
+                dest_path.display()
+            ),
+            _ => e.into(),
+        })?;
+
+    let copied = copy_into(config, pathstr, dest_file, &dest_path);
+    if copied.is_err() {
+        // don't leave a partial copy behind, it would look like a finished one
+        let _ = std::fs::remove_file(&dest_path);
+    }
+    copied
+}
+
+/// Where a file goes under the destination: its path relative to the scan root, or just its name if it isn't
+/// below the root, eg a path read from stdin
+fn copy_path(config: &ConfigSettings, pathstr: &str) -> anyhow::Result<PathBuf> {
+    let file_name = Path::new(pathstr)
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("No file name in path: {pathstr}"))?;
+
+    let relative = match (paths::absolute(pathstr), paths::absolute(scan_dir(config))) {
+        (Ok(full_path), Ok(root)) => paths::relative_to(&full_path, &root),
+        _ => return Ok(PathBuf::from(file_name)),
+    };
+    if relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        Ok(relative)
+    } else {
+        Ok(PathBuf::from(file_name))
+    }
+}
+
+/// The directory the scan started from, which is the current directory when the root is empty
+fn scan_dir(config: &ConfigSettings) -> PathBuf {
+    let root = paths::scan_root(config);
+    if root.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        root
+    }
+}
+
+/// true if the destination is the directory the scan started from, where every copy would land on its source
+pub fn is_scan_dir(config: &ConfigSettings, dest_dir: &str) -> bool {
+    match (
+        std::fs::canonicalize(dest_dir),
+        std::fs::canonicalize(scan_dir(config)),
+    ) {
+        (Ok(dest_dir), Ok(root)) => dest_dir == root,
+        _ => false,
+    }
+}
+
+/// true if both paths lead to the same file on disk
+fn same_file(source: &str, dest: &Path) -> bool {
+    match (std::fs::canonicalize(source), std::fs::canonicalize(dest)) {
+        (Ok(source), Ok(dest)) => source == dest,
+        _ => false,
+    }
+}
+
+/// Copy the source into the newly created destination file while hashing it, then hash the copy
+fn copy_into(
+    config: &ConfigSettings,
+    pathstr: &str,
+    dest_file: File,
+    dest_path: &Path,
+) -> anyhow::Result<CopyResult> {
+    // hash while copying, so the source is only read once
+    let source_hash = call_hasher(
+        config.algorithm,
+        config.encoding,
+        CopySource {
+            path: pathstr,
+            dest: BufWriter::new(&dest_file),
+            buffer_size: config.io.buffer_size,
+        },
+    )?;
+
+    // make sure the copy is on disk before reading it back
+    dest_file.sync_all()?;
+    drop(dest_file);
+
+    let dest_path = dest_path.to_string_lossy().to_string();
+    let dest_source = FileSource {
+        path: &dest_path,
+        io: &config.io,
+    };
+    let dest_hash = call_hasher(config.algorithm, config.encoding, dest_source)?;
+
+    Ok(CopyResult {
+        source_hash,
+        dest_hash,
+        dest_path,
+    })
+}
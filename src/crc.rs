@@ -0,0 +1,233 @@
+use std::sync::OnceLock;
+
+use digest::Update;
+
+/// The parameters of a CRC, in the Rocksoft model used by the CRC catalogues (eg reveng), so any CRC
+/// found in firmware or a file format can be described
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CrcSpec {
+    /// bits in the CRC, 1 to 64
+    pub width: u32,
+    /// the generator polynomial, without its top bit
+    pub poly: u64,
+    /// the register before any data is added
+    pub init: u64,
+    /// each input byte is taken least significant bit first
+    pub refin: bool,
+    /// the register is reversed before the final XOR
+    pub refout: bool,
+    /// XORed with the register to give the CRC
+    pub xorout: u64,
+}
+
+impl CrcSpec {
+    /// CRC-32/ISO-HDLC, the CRC-32 of zip, PNG and Ethernet, and the same as -a crc32
+    pub const CRC32: Self = Self {
+        width: 32,
+        poly: 0x04C1_1DB7,
+        init: 0xFFFF_FFFF,
+        refin: true,
+        refout: true,
+        xorout: 0xFFFF_FFFF,
+    };
+
+    /// CRC-16/CCITT-FALSE (also called CRC-16/IBM-3740)
+    pub const CRC16_CCITT: Self = Self::unreflected(16, 0x1021, 0xFFFF);
+
+    /// CRC-16/MODBUS, used by Modbus RTU
+    pub const CRC16_MODBUS: Self = Self::reflected(16, 0x8005, 0xFFFF, 0);
+
+    /// CRC-16/XMODEM, used by XMODEM and ZMODEM transfers
+    pub const CRC16_XMODEM: Self = Self::unreflected(16, 0x1021, 0);
+
+    /// CRC-16/USB, used by USB data packets
+    pub const CRC16_USB: Self = Self::reflected(16, 0x8005, 0xFFFF, 0xFFFF);
+
+    /// CRC-8/SMBUS, the packet error check of SMBus and PMBus
+    pub const CRC8_SMBUS: Self = Self::unreflected(8, 0x07, 0);
+
+    /// CRC-8/MAXIM-DOW, used by Maxim (Dallas) 1-Wire devices such as the DS18B20
+    pub const CRC8_MAXIM: Self = Self::reflected(8, 0x31, 0, 0);
+
+    /// A CRC that takes bits most significant first, with no final XOR
+    const fn unreflected(width: u32, poly: u64, init: u64) -> Self {
+        Self {
+            width,
+            poly,
+            init,
+            refin: false,
+            refout: false,
+            xorout: 0,
+        }
+    }
+
+    /// A CRC that takes bits least significant first, as most serial protocols send them
+    const fn reflected(width: u32, poly: u64, init: u64, xorout: u64) -> Self {
+        Self {
+            width,
+            poly,
+            init,
+            refin: true,
+            refout: true,
+            xorout,
+        }
+    }
+
+    /// The largest value of a CRC this wide
+    fn mask(self) -> u64 {
+        u64::MAX >> (64 - self.width)
+    }
+
+    /// Bytes needed to hold the CRC, it is output big endian
+    pub fn output_bytes(self) -> usize {
+        self.width.div_ceil(8) as usize
+    }
+}
+
+/// The CRC used by -a crc, if --crc-spec was given
+static SPEC: OnceLock<CrcSpec> = OnceLock::new();
+
+/// Use this CRC for -a crc from now on
+pub fn set_spec(spec: CrcSpec) {
+    // only ever set once, so there is nothing to replace
+    let _ = SPEC.set(spec);
+}
+
+/// The CRC used by -a crc, CRC-32 unless --crc-spec gave another
+pub fn spec() -> CrcSpec {
+    SPEC.get().copied().unwrap_or(CrcSpec::CRC32)
+}
+
+/// Parse a CRC from its parameters, eg `width=16,poly=0x1021,init=0xffff`, with commas or spaces between them.
+/// width and poly are required, init and xorout default to zero, refin to false and refout to refin
+pub fn parse_spec(text: &str) -> anyhow::Result<CrcSpec> {
+    let mut width = None;
+    let mut poly = None;
+    let mut spec = CrcSpec {
+        width: 0,
+        poly: 0,
+        init: 0,
+        refin: false,
+        refout: false,
+        xorout: 0,
+    };
+    let mut refout = None;
+
+    for part in text.split([',', ' ']).filter(|part| !part.is_empty()) {
+        let (name, value) = part.split_once('=').ok_or_else(|| {
+            anyhow::anyhow!("Expected name=value in the CRC spec, found '{part}'")
+        })?;
+        match name.to_ascii_lowercase().as_str() {
+            "width" => width = Some(value.parse::<u32>()?),
+            "poly" => poly = Some(parse_number(value)?),
+            "init" => spec.init = parse_number(value)?,
+            "refin" => spec.refin = parse_bool(value)?,
+            "refout" => refout = Some(parse_bool(value)?),
+            "xorout" => spec.xorout = parse_number(value)?,
+            _ => return Err(anyhow::anyhow!("Unknown CRC parameter '{name}'")),
+        }
+    }
+
+    spec.width = width.ok_or_else(|| anyhow::anyhow!("The CRC spec needs a width"))?;
+    spec.poly = poly.ok_or_else(|| anyhow::anyhow!("The CRC spec needs a poly"))?;
+    spec.refout = refout.unwrap_or(spec.refin);
+    if !(1..=64).contains(&spec.width) {
+        return Err(anyhow::anyhow!("CRC width must be from 1 to 64 bits"));
+    }
+    if [spec.poly, spec.init, spec.xorout]
+        .iter()
+        .any(|value| value & !spec.mask() != 0)
+    {
+        return Err(anyhow::anyhow!(
+            "CRC poly, init and xorout must fit in {} bits",
+            spec.width
+        ));
+    }
+    Ok(spec)
+}
+
+/// A number in hex (with 0x) or decimal
+fn parse_number(value: &str) -> anyhow::Result<u64> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| anyhow::anyhow!("'{value}' is not a number"))
+}
+
+/// true or false, as the catalogues write them
+fn parse_bool(value: &str) -> anyhow::Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(anyhow::anyhow!("'{value}' is not true or false")),
+    }
+}
+
+/// A CRC of any width, worked out a byte at a time from a table. The register is kept in the top bits
+/// of a u64, so every width uses the same code
+#[derive(Clone)]
+pub struct Crc {
+    spec: CrcSpec,
+    table: Box<[u64; 256]>,
+    register: u64,
+}
+
+impl Crc {
+    pub fn new(spec: CrcSpec) -> Self {
+        let shift = 64 - spec.width;
+        let poly = spec.poly << shift;
+
+        let mut table = Box::new([0u64; 256]);
+        for (byte, entry) in table.iter_mut().enumerate() {
+            let mut register = (byte as u64) << 56;
+            for _ in 0..8 {
+                register = if register & (1 << 63) == 0 {
+                    register << 1
+                } else {
+                    (register << 1) ^ poly
+                };
+            }
+            *entry = register;
+        }
+
+        Self {
+            spec,
+            table,
+            register: spec.init << shift,
+        }
+    }
+
+    /// The CRC of everything added so far
+    pub fn value(&self) -> u64 {
+        let mut value = self.register >> (64 - self.spec.width);
+        if self.spec.refout {
+            value = value.reverse_bits() >> (64 - self.spec.width);
+        }
+        (value ^ self.spec.xorout) & self.spec.mask()
+    }
+
+    /// The CRC as big endian bytes, only as many as the width needs
+    pub fn finalize_bytes(&self) -> Vec<u8> {
+        let bytes = self.value().to_be_bytes();
+        bytes[bytes.len() - self.spec.output_bytes()..].to_vec()
+    }
+}
+
+impl Update for Crc {
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            // a reflected CRC is the same as an ordinary one fed the bits of each byte in reverse
+            let byte = if self.spec.refin {
+                byte.reverse_bits()
+            } else {
+                byte
+            };
+            let index = ((self.register >> 56) as u8 ^ byte) as usize;
+            self.register = (self.register << 8) ^ self.table[index];
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::classes::ScriptKind;
+use crate::output::{self, HeldLine};
+use crate::progress;
+
+// Scripts to get rid of the duplicates found in a run, for --emit-script. hash_rust never changes the files
+// itself: the script is written out to be read, edited if need be, and run by hand
+
+/// Print a script removing the duplicates among the results, keeping the first file of each group by path
+pub fn print_script(held: Vec<HeldLine>, kind: ScriptKind) {
+    let lines = script_lines(held, kind, cfg!(windows));
+    progress::suspend(|| {
+        for line in lines {
+            output::print_line(line);
+        }
+    });
+}
+
+/// The lines of the script, for sh or else PowerShell. Empty files are left alone, as they take no space
+pub fn script_lines(mut held: Vec<HeldLine>, kind: ScriptKind, powershell: bool) -> Vec<String> {
+    held.sort_by(|a, b| a.hash.cmp(&b.hash).then_with(|| a.path.cmp(&b.path)));
+
+    let what = match kind {
+        ScriptKind::Hardlink => "replaces duplicate files with hard links to the first copy",
+        ScriptKind::Reflink => {
+            "replaces duplicate files with copy-on-write clones of the first copy"
+        }
+        ScriptKind::Delete => "deletes duplicate files, keeping the first copy",
+    };
+    let mut lines = if powershell {
+        vec![
+            format!("# Written by hash_rust: {what}"),
+            "# Read it before running. Paths are as printed, so run it from the same directory"
+                .into(),
+            "$ErrorActionPreference = 'Stop'".into(),
+        ]
+    } else {
+        vec![
+            "#!/bin/sh".into(),
+            format!("# Written by hash_rust: {what}"),
+            "# Read it before running. Paths are as printed, so run it from the same directory"
+                .into(),
+            "set -eu".into(),
+        ]
+    };
+
+    let mut copies = 0;
+    let mut bytes = 0;
+    for group in held.chunk_by(|a, b| a.hash == b.hash) {
+        let Some((original, duplicates)) = group.split_first() else {
+            continue;
+        };
+        if duplicates.is_empty() || original.timing.bytes == 0 {
+            continue;
+        }
+
+        lines.push(String::new());
+        lines.push(format!("# {} ({} files)", original.hash, group.len()));
+        for duplicate in duplicates {
+            lines.push(command(kind, powershell, &original.path, &duplicate.path));
+        }
+        copies += duplicates.len();
+        bytes += original.timing.bytes * duplicates.len() as u64;
+    }
+
+    lines.push(String::new());
+    lines.push(format!("# {copies} duplicates, {bytes} bytes"));
+    lines
+}
+
+/// The command dealing with one duplicate
+fn command(kind: ScriptKind, powershell: bool, original: &str, duplicate: &str) -> String {
+    if powershell {
+        let (original, duplicate) = (ps_quote(original), ps_quote(duplicate));
+        match kind {
+            ScriptKind::Hardlink => format!(
+                "Remove-Item -LiteralPath {duplicate}; New-Item -ItemType HardLink -Path {duplicate} -Value {original} | Out-Null"
+            ),
+            // refused when the options are read, as Windows has no command for block cloning
+            ScriptKind::Reflink => unreachable!("reflink scripts are not written for PowerShell"),
+            ScriptKind::Delete => format!("Remove-Item -LiteralPath {duplicate}"),
+        }
+    } else {
+        let (original, duplicate) = (sh_quote(original), sh_quote(duplicate));
+        match kind {
+            ScriptKind::Hardlink => format!("ln -f -- {original} {duplicate}"),
+            // macOS cp clones with -c, GNU cp with --reflink
+            ScriptKind::Reflink if cfg!(target_os = "macos") => {
+                format!("cp -c -f -- {original} {duplicate}")
+            }
+            ScriptKind::Reflink => format!("cp --reflink=always -f -- {original} {duplicate}"),
+            ScriptKind::Delete => format!("rm -f -- {duplicate}"),
+        }
+    }
+}
+
+/// A path in single quotes for sh, where nothing is special except the closing quote
+fn sh_quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', r"'\''"))
+}
+
+/// A path in single quotes for PowerShell, which also takes the typographic single quotes as quotes
+fn ps_quote(path: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in path.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::Read;
+
+use digest::Update;
+
+/// Alignment of buffers for unbuffered reads. Covers both 512 byte and 4K sector devices
+const ALIGNMENT: usize = 4096;
+
+/// Size of each unbuffered read, must be a multiple of `ALIGNMENT`
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+#[cfg(windows)]
+const FILE_FLAG_NO_BUFFERING: u32 = 0x2000_0000;
+#[cfg(windows)]
+const FILE_FLAG_SEQUENTIAL_SCAN: u32 = 0x0800_0000;
+
+/// Read a file bypassing the OS cache, using sector-aligned buffers, and feed it to the hasher
+pub fn feed_file<U: Update>(path: &str, size: u64, hasher: &mut U) -> anyhow::Result<()> {
+    let mut file = open_unbuffered(path)?;
+
+    // over-allocate, then take a slice starting on an aligned address
+    let mut storage = vec![0u8; CHUNK_SIZE + ALIGNMENT];
+    let start = storage.as_ptr().align_offset(ALIGNMENT);
+    let buffer = &mut storage[start..start + CHUNK_SIZE];
+
+    // stop at the known size, because after a partial final read the file offset is no longer aligned
+    let mut remaining = size;
+    while remaining > 0 {
+        let bytes_read = file.read(buffer)?;
+        if bytes_read == 0 {
+            return Err(anyhow::anyhow!("File was truncated while hashing"));
+        }
+        let bytes_used = bytes_read.min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        crate::limits::throttle(bytes_read);
+        hasher.update(&buffer[..bytes_used]);
+        remaining -= bytes_used as u64;
+    }
+
+    Ok(())
+}
+
+/// Open a file for reading without going through the system cache
+#[cfg(windows)]
+fn open_unbuffered(path: &str) -> std::io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_NO_BUFFERING | FILE_FLAG_SEQUENTIAL_SCAN)
+        .open(path)
+}
+
+/// Open a file for reading with `O_DIRECT`, so the page cache is left alone
+#[cfg(target_os = "linux")]
+fn open_unbuffered(path: &str) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+}
+
+/// Unbuffered reads are not implemented for this platform
+#[cfg(not(any(windows, target_os = "linux")))]
+fn open_unbuffered(_path: &str) -> std::io::Result<File> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Direct I/O is not supported on this platform",
+    ))
+}
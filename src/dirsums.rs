@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use crate::archive::ARCHIVE_SEPARATOR;
+use crate::classes::BasicHash;
+
+/// Checksum files to write, one per directory, if --per-dir-sums was given
+static DIR_SUMS: OnceLock<DirSums> = OnceLock::new();
+
+struct DirSums {
+    /// name of the checksum file written into each directory, eg SHA256SUMS
+    name: String,
+    /// file names and hashes, for each directory
+    dirs: Mutex<BTreeMap<PathBuf, Vec<(String, String)>>>,
+}
+
+/// Start collecting hashes, to write a checksum file with this name into every directory
+pub fn enable(name: &str) {
+    // only ever set once, so there is nothing to replace
+    let _ = DIR_SUMS.set(DirSums {
+        name: name.to_string(),
+        dirs: Mutex::new(BTreeMap::new()),
+    });
+}
+
+/// Remember a file's hash for the checksum file in its directory
+pub fn record(path: &str, hash: &BasicHash) {
+    let Some(sums) = DIR_SUMS.get() else {
+        return;
+    };
+
+    // entries inside archives aren't files in the directory
+    let path = Path::new(path);
+    let Some(file_name) = path.file_name().map(|name| name.to_string_lossy()) else {
+        return;
+    };
+    if path.to_string_lossy().contains(ARCHIVE_SEPARATOR) || file_name == sums.name {
+        return;
+    }
+
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    sums.dirs
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(dir)
+        .or_default()
+        .push((file_name.into_owned(), hash.0.clone()));
+}
+
+/// Write the checksum file into each directory, sorted by file name, in the format sha256sum reads.
+/// Returns how many were written
+pub fn write_all() -> anyhow::Result<usize> {
+    let Some(sums) = DIR_SUMS.get() else {
+        return Ok(0);
+    };
+
+    let mut dirs = sums.dirs.lock().unwrap_or_else(PoisonError::into_inner);
+    for (dir, files) in dirs.iter_mut() {
+        files.sort();
+        let mut text = String::new();
+        for (file_name, hash) in files.iter() {
+            // like sha256sum, names with a backslash or newline are escaped, and the line marked with a backslash
+            if file_name.contains(['\\', '\n']) {
+                let escaped = file_name.replace('\\', "\\\\").replace('\n', "\\n");
+                let _ = writeln!(text, "\\{hash}  {escaped}");
+            } else {
+                let _ = writeln!(text, "{hash}  {file_name}");
+            }
+        }
+
+        let sums_path = dir.join(&sums.name);
+        std::fs::write(&sums_path, text)
+            .map_err(|e| anyhow::anyhow!("Could not write {}: {e}", sums_path.display()))?;
+    }
+    Ok(dirs.len())
+}
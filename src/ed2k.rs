@@ -0,0 +1,74 @@
+use digest::{Digest, FixedOutput, HashMarker, Output, OutputSizeUser, Reset, Update};
+use generic_array::typenum::U16;
+use md4::Md4;
+
+/// eD2k splits files into chunks of 9500 KiB
+const CHUNK_SIZE: usize = 9_728_000;
+
+// The eD2k hash of eMule and eDonkey links: the MD4 of each chunk, then the MD4 of those hashes. A file of
+// one chunk or less is just its MD4. Built from the md4 crate in the same way as Crc32 and Blake3
+
+#[derive(Clone, Default)]
+pub struct Ed2k {
+    /// MD4 of the chunk being read
+    chunk: Md4,
+    /// bytes read into the current chunk
+    chunk_len: usize,
+    /// MD4 of the chunk hashes so far
+    chunk_hashes: Md4,
+    /// chunks finished, so far
+    chunks: u64,
+}
+
+// Indicate that the Ed2k struct is a Digest algorithm (a hash function)
+impl HashMarker for Ed2k {}
+
+// Indicate that the Ed2k struct has a fixed output size of 16 bytes, as MD4
+impl OutputSizeUser for Ed2k {
+    type OutputSize = U16;
+}
+
+// Update the hash with the provided data, starting a new chunk every CHUNK_SIZE bytes
+impl Update for Ed2k {
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.chunk_len == CHUNK_SIZE {
+                let hash = std::mem::take(&mut self.chunk).finalize();
+                Digest::update(&mut self.chunk_hashes, hash);
+                self.chunks += 1;
+                self.chunk_len = 0;
+            }
+
+            let take = data.len().min(CHUNK_SIZE - self.chunk_len);
+            Digest::update(&mut self.chunk, &data[..take]);
+            self.chunk_len += take;
+            data = &data[take..];
+        }
+    }
+}
+
+// Finalize the hash and write it into the provided buffer
+impl FixedOutput for Ed2k {
+    fn finalize_into(mut self, out: &mut Output<Self>) {
+        let last = self.chunk.finalize();
+        if self.chunks == 0 && self.chunk_len < CHUNK_SIZE {
+            // a single partial chunk is hashed on its own
+            out.copy_from_slice(&last);
+            return;
+        }
+
+        Digest::update(&mut self.chunk_hashes, last);
+        if self.chunk_len == CHUNK_SIZE {
+            // eMule's rule for a file that ends exactly on a chunk boundary: it is followed by an empty chunk
+            Digest::update(&mut self.chunk_hashes, Md4::digest([]));
+        }
+        out.copy_from_slice(&self.chunk_hashes.finalize());
+    }
+}
+
+// Reset the hash to its initial state
+impl Reset for Ed2k {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,428 @@
+use digest::{FixedOutput, HashMarker, Output, OutputSizeUser, Reset, Update};
+use generic_array::typenum::U8;
+use rapidhash::v3::{RapidStreamHasherV3, DEFAULT_RAPID_SECRETS};
+
+// Fast non-cryptographic fingerprints, for spotting changed files where nobody is trying to forge a match.
+// They are wrapped as a Digest like Crc32, and give the value big endian, as their own tools print it
+
+/// rapidhash v3 with the default secrets, the same as `rapidhash_v3` in the crate and the C reference
+pub struct Rapidhash(RapidStreamHasherV3<'static>);
+
+impl Default for Rapidhash {
+    fn default() -> Self {
+        Self(RapidStreamHasherV3::new(&DEFAULT_RAPID_SECRETS))
+    }
+}
+
+// Indicate that the Rapidhash struct is a Digest algorithm (a hash function)
+impl HashMarker for Rapidhash {}
+
+// Indicate that the Rapidhash struct has a fixed output size of 8 bytes
+impl OutputSizeUser for Rapidhash {
+    type OutputSize = U8;
+}
+
+// Update the hash with the provided data. The stream hasher gives the same result however the data is split
+impl Update for Rapidhash {
+    #[inline]
+    fn update(&mut self, data: &[u8]) {
+        self.0.write(data);
+    }
+}
+
+// Finalize the hash and write it into the provided buffer
+impl FixedOutput for Rapidhash {
+    #[inline]
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.0.finish().to_be_bytes());
+    }
+}
+
+// Reset the hash to its initial state
+impl Reset for Rapidhash {
+    #[inline]
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// Multipliers from the t1ha reference
+const T1HA_PRIME_0: u64 = 0xEC99_BF0D_8372_CAAB;
+const T1HA_PRIME_1: u64 = 0x8243_4FE9_0EDC_EF39;
+const T1HA_PRIME_2: u64 = 0xD4F0_6DB9_9D67_BE4B;
+const T1HA_PRIME_3: u64 = 0xBD9C_ACC2_2C6E_9571;
+const T1HA_PRIME_4: u64 = 0x9C06_FAF4_D023_E3AB;
+const T1HA_PRIME_5: u64 = 0xC060_724A_8424_F345;
+const T1HA_PRIME_6: u64 = 0xCB5A_F53A_E3AA_AC31;
+
+/// t1ha2 as fed in pieces, the same as `t1ha2_init` with a zero seed, `t1ha2_update` and `t1ha2_final` in the
+/// reference. This differs from `t1ha2_atonce`, which needs the length before the data
+#[derive(Clone)]
+pub struct T1ha2 {
+    state: [u64; 4],
+    /// bytes waiting for a whole 32 byte block
+    buffer: [u8; 32],
+    partial: usize,
+    total: u64,
+}
+
+impl Default for T1ha2 {
+    fn default() -> Self {
+        // init_ab and init_cd from the reference, which with both seeds zero leave c and d all ones
+        Self {
+            state: [0, 0, !0, !0],
+            buffer: [0; 32],
+            partial: 0,
+            total: 0,
+        }
+    }
+}
+
+/// The 128-bit product of two numbers, as its low and high halves
+fn mul_64x64_128(a: u64, b: u64) -> (u64, u64) {
+    let product = u128::from(a) * u128::from(b);
+    (product as u64, (product >> 64) as u64)
+}
+
+fn mixup64(a: &mut u64, b: &mut u64, v: u64, prime: u64) {
+    let (low, high) = mul_64x64_128(b.wrapping_add(v), prime);
+    *a ^= low;
+    *b = b.wrapping_add(high);
+}
+
+fn final64(a: u64, b: u64) -> u64 {
+    let x = a
+        .wrapping_add(b.rotate_right(41))
+        .wrapping_mul(T1HA_PRIME_0);
+    let y = a
+        .rotate_right(23)
+        .wrapping_add(b)
+        .wrapping_mul(T1HA_PRIME_6);
+    let (low, high) = mul_64x64_128(x ^ y, T1HA_PRIME_5);
+    low ^ high
+}
+
+impl T1ha2 {
+    /// Mix in one 32 byte block
+    fn block(&mut self, block: &[u8]) {
+        let w: [u64; 4] = std::array::from_fn(|i| {
+            u64::from_le_bytes(block[i * 8..i * 8 + 8].try_into().unwrap())
+        });
+        let [a, b, c, d] = &mut self.state;
+
+        let d02 = w[0].wrapping_add(w[2].wrapping_add(*d).rotate_right(56));
+        let c13 = w[1].wrapping_add(w[3].wrapping_add(*c).rotate_right(19));
+        *d ^= b.wrapping_add(w[1].rotate_right(38));
+        *c ^= a.wrapping_add(w[0].rotate_right(57));
+        *b ^= T1HA_PRIME_6.wrapping_mul(c13.wrapping_add(w[2]));
+        *a ^= T1HA_PRIME_5.wrapping_mul(d02.wrapping_add(w[3]));
+    }
+
+    fn write(&mut self, mut data: &[u8]) {
+        // first complete a block left from the last write
+        if self.partial > 0 {
+            let take = data.len().min(32 - self.partial);
+            self.buffer[self.partial..self.partial + take].copy_from_slice(&data[..take]);
+            self.partial += take;
+            data = &data[take..];
+            if self.partial < 32 {
+                return;
+            }
+            let buffer = self.buffer;
+            self.block(&buffer);
+            self.partial = 0;
+        }
+
+        // whole blocks straight from the data, then keep the rest
+        let blocks = data.chunks_exact(32);
+        let rest = blocks.remainder();
+        for block in blocks {
+            self.block(block);
+        }
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.partial = rest.len();
+    }
+
+    fn finish(mut self) -> u64 {
+        // the length in bits, with the top bit set, is added as the last 8 bytes
+        let bits = (self.total << 3) ^ (1 << 63);
+        self.write(&bits.to_le_bytes());
+
+        // squash the four lanes into two
+        let [mut a, mut b, c, d] = self.state;
+        a ^= T1HA_PRIME_6.wrapping_mul(c.wrapping_add(d.rotate_right(23)));
+        b ^= T1HA_PRIME_5.wrapping_mul(c.rotate_right(19).wrapping_add(d));
+
+        // then the last partial block, a word at a time, the last word padded with zeros
+        let tail = &self.buffer[..self.partial];
+        let primes = [T1HA_PRIME_4, T1HA_PRIME_3, T1HA_PRIME_2, T1HA_PRIME_1];
+        let words = tail.chunks(8);
+        let skip = primes.len() - words.len();
+        for (i, (word, prime)) in words.zip(&primes[skip..]).enumerate() {
+            let mut bytes = [0; 8];
+            bytes[..word.len()].copy_from_slice(word);
+            let v = u64::from_le_bytes(bytes);
+            // the words alternate between the two lanes, ending on b
+            if (skip + i).is_multiple_of(2) {
+                mixup64(&mut a, &mut b, v, *prime);
+            } else {
+                mixup64(&mut b, &mut a, v, *prime);
+            }
+        }
+        final64(a, b)
+    }
+}
+
+// Indicate that the T1ha2 struct is a Digest algorithm (a hash function)
+impl HashMarker for T1ha2 {}
+
+// Indicate that the T1ha2 struct has a fixed output size of 8 bytes
+impl OutputSizeUser for T1ha2 {
+    type OutputSize = U8;
+}
+
+// Update the hash with the provided data, mixing in each whole block of 32 bytes
+impl Update for T1ha2 {
+    #[inline]
+    fn update(&mut self, data: &[u8]) {
+        self.total += data.len() as u64;
+        self.write(data);
+    }
+}
+
+// Finalize the hash and write it into the provided buffer
+impl FixedOutput for T1ha2 {
+    #[inline]
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.finish().to_be_bytes());
+    }
+}
+
+// Reset the hash to its initial state
+impl Reset for T1ha2 {
+    #[inline]
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Multipliers from the MetroHash64 reference
+const METRO_K0: u64 = 0xD6D0_18F5;
+const METRO_K1: u64 = 0xA2AA_033B;
+const METRO_K2: u64 = 0x6299_2FC1;
+const METRO_K3: u64 = 0x30BC_5B29;
+
+/// MetroHash64 with a zero seed. Unlike the others here it is printed in the byte order of the reference,
+/// which stores the 64-bit value little endian
+#[derive(Clone)]
+pub struct MetroHash64 {
+    state: [u64; 4],
+    /// bytes waiting for a whole 32 byte block
+    buffer: [u8; 32],
+    partial: usize,
+    total: u64,
+}
+
+/// The seed mixed into every lane at the start, and into the result of long inputs
+const METRO_VSEED: u64 = METRO_K2.wrapping_mul(METRO_K0);
+
+impl Default for MetroHash64 {
+    fn default() -> Self {
+        Self {
+            state: [METRO_VSEED; 4],
+            buffer: [0; 32],
+            partial: 0,
+            total: 0,
+        }
+    }
+}
+
+/// Read a little endian number of up to 8 bytes
+fn read_le(bytes: &[u8]) -> u64 {
+    let mut padded = [0; 8];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(padded)
+}
+
+impl MetroHash64 {
+    /// Mix in one 32 byte block
+    fn block(&mut self, block: &[u8]) {
+        let v = &mut self.state;
+        let w = |i: usize| read_le(&block[i * 8..i * 8 + 8]);
+        v[0] = v[0]
+            .wrapping_add(w(0).wrapping_mul(METRO_K0))
+            .rotate_right(29)
+            .wrapping_add(v[2]);
+        v[1] = v[1]
+            .wrapping_add(w(1).wrapping_mul(METRO_K1))
+            .rotate_right(29)
+            .wrapping_add(v[3]);
+        v[2] = v[2]
+            .wrapping_add(w(2).wrapping_mul(METRO_K2))
+            .rotate_right(29)
+            .wrapping_add(v[0]);
+        v[3] = v[3]
+            .wrapping_add(w(3).wrapping_mul(METRO_K3))
+            .rotate_right(29)
+            .wrapping_add(v[1]);
+    }
+
+    fn finish(self) -> u64 {
+        let mut v = self.state;
+        if self.total >= 32 {
+            let mix = |x: u64, y: u64, z: u64, k: u64, l: u64| {
+                x.wrapping_add(y)
+                    .wrapping_mul(k)
+                    .wrapping_add(z)
+                    .rotate_right(37)
+                    .wrapping_mul(l)
+            };
+            v[2] ^= mix(v[0], v[3], v[1], METRO_K0, METRO_K1);
+            v[3] ^= mix(v[1], v[2], v[0], METRO_K1, METRO_K0);
+            v[0] ^= mix(v[0], v[2], v[3], METRO_K0, METRO_K1);
+            v[1] ^= mix(v[1], v[3], v[2], METRO_K1, METRO_K0);
+            v[0] = METRO_VSEED.wrapping_add(v[0] ^ v[1]);
+        }
+
+        // the bytes after the last whole block, in pieces of 16, 8, 4, 2 and 1
+        let mut tail = &self.buffer[..self.partial];
+        if tail.len() >= 16 {
+            let mut v1 = v[0].wrapping_add(read_le(&tail[..8]).wrapping_mul(METRO_K2));
+            v1 = v1.rotate_right(29).wrapping_mul(METRO_K3);
+            let mut v2 = v[0].wrapping_add(read_le(&tail[8..16]).wrapping_mul(METRO_K2));
+            v2 = v2.rotate_right(29).wrapping_mul(METRO_K3);
+            v1 ^= v1.wrapping_mul(METRO_K0).rotate_right(21).wrapping_add(v2);
+            v2 ^= v2.wrapping_mul(METRO_K3).rotate_right(21).wrapping_add(v1);
+            v[0] = v[0].wrapping_add(v2);
+            tail = &tail[16..];
+        }
+        for (size, rotate) in [(8, 55), (4, 26), (2, 48), (1, 37)] {
+            if tail.len() >= size {
+                v[0] = v[0].wrapping_add(read_le(&tail[..size]).wrapping_mul(METRO_K3));
+                v[0] ^= v[0].rotate_right(rotate).wrapping_mul(METRO_K1);
+                tail = &tail[size..];
+            }
+        }
+
+        v[0] ^= v[0].rotate_right(28);
+        v[0] = v[0].wrapping_mul(METRO_K0);
+        v[0] ^ v[0].rotate_right(29)
+    }
+}
+
+// Indicate that the MetroHash64 struct is a Digest algorithm (a hash function)
+impl HashMarker for MetroHash64 {}
+
+// Indicate that the MetroHash64 struct has a fixed output size of 8 bytes
+impl OutputSizeUser for MetroHash64 {
+    type OutputSize = U8;
+}
+
+// Update the hash with the provided data, mixing in each whole block of 32 bytes
+impl Update for MetroHash64 {
+    fn update(&mut self, mut data: &[u8]) {
+        self.total += data.len() as u64;
+
+        // first complete a block left from the last update
+        if self.partial > 0 {
+            let take = data.len().min(32 - self.partial);
+            self.buffer[self.partial..self.partial + take].copy_from_slice(&data[..take]);
+            self.partial += take;
+            data = &data[take..];
+            if self.partial < 32 {
+                return;
+            }
+            let buffer = self.buffer;
+            self.block(&buffer);
+            self.partial = 0;
+        }
+
+        let blocks = data.chunks_exact(32);
+        let rest = blocks.remainder();
+        for block in blocks {
+            self.block(block);
+        }
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.partial = rest.len();
+    }
+}
+
+// Finalize the hash and write it into the provided buffer
+impl FixedOutput for MetroHash64 {
+    #[inline]
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.finish().to_le_bytes());
+    }
+}
+
+// Reset the hash to its initial state
+impl Reset for MetroHash64 {
+    #[inline]
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// CityHash and FarmHash have no incremental form, so the whole input is collected and hashed at the end. That
+// takes memory for all of it, so a limit is put on how much they will take
+
+/// Most bytes CityHash128 and FarmHash64 will hash
+pub const WHOLE_INPUT_LIMIT: u64 = 1024 * 1024 * 1024;
+
+/// Input collected for a hash that can only be made from all of it at once. Past the limit the input is
+/// dropped, and the hash fails when it is finished
+pub struct WholeInput {
+    data: Vec<u8>,
+    too_big: bool,
+    hash: fn(&[u8]) -> Vec<u8>,
+}
+
+impl WholeInput {
+    /// CityHash128 v1.1, printed as the first then the second half of the reference's pair
+    pub fn city_hash128() -> Self {
+        Self::new(|data| cityhash_rs::cityhash_110_128(data).to_be_bytes().to_vec())
+    }
+
+    /// FarmHash Fingerprint64, the stable FarmHash that BigQuery's FARM_FINGERPRINT gives as a signed number
+    pub fn farm_hash64() -> Self {
+        Self::new(|data| farmhash::fingerprint64(data).to_be_bytes().to_vec())
+    }
+
+    fn new(hash: fn(&[u8]) -> Vec<u8>) -> Self {
+        Self {
+            data: Vec::new(),
+            too_big: false,
+            hash,
+        }
+    }
+
+    /// Collect the next chunk, unless the input has gone over the limit
+    pub fn update(&mut self, data: &[u8]) {
+        if self.too_big {
+            return;
+        }
+        if (self.data.len() + data.len()) as u64 > WHOLE_INPUT_LIMIT {
+            self.too_big = true;
+            self.data = Vec::new();
+        } else {
+            self.data.extend_from_slice(data);
+        }
+    }
+
+    /// Hash everything collected
+    pub fn finalize_bytes(self) -> anyhow::Result<Vec<u8>> {
+        if self.too_big {
+            Err(too_big())
+        } else {
+            Ok((self.hash)(&self.data))
+        }
+    }
+}
+
+/// The error for an input over WHOLE_INPUT_LIMIT
+pub fn too_big() -> anyhow::Error {
+    anyhow::anyhow!(
+        "CityHash128 and FarmHash64 can only hash up to {} MB, as they hold the whole input in memory",
+        WHOLE_INPUT_LIMIT / (1024 * 1024)
+    )
+}
@@ -1,81 +1,517 @@
+use std::cell::RefCell;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{Read, Write};
 use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
 
+use blake2::{Blake2b512, Blake2s256};
 use byteorder::{BigEndian, ByteOrder};
 use data_encoding::{BASE32, BASE64};
-use digest::{Digest, Output};
+use digest::{Digest, Update};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Sha224, Sha256, Sha384, Sha512};
+use sha3::{Sha3_256, Sha3_384, Sha3_512};
+use tiger::Tiger;
+use whirlpool::Whirlpool;
 
-use crate::classes::{BasicHash, OutputEncoding};
+use crate::classes::{BasicHash, HashAlgorithm, IoOptions, OutputEncoding};
+use crate::crc::CrcSpec;
+use crate::limits::MemoryPermit;
+use crate::{
+    blake2p, blake3_digest, crc, crc32, ed2k, fingerprint, limits, merkle, sp800_185, stats,
+};
 
-const BUFFER_SIZE: usize = 4096 * 8;
+/// Files at least this big are read on a separate thread, overlapping I/O with hashing
+const PIPELINE_THRESHOLD: u64 = 4 * 1024 * 1024;
 
-/// Hash a file using the given hasher as a Digest implementation, eg `Sha1`, `Sha256`, `Sha3_256`
-/// Returns Output<D>, which is an owned fixed size array of u8
-/// Output<D> = `GenericArray<u8, <D as OutputSizeUser>::OutputSize>`
-fn hash_file<D: Digest>(filename: impl AsRef<str>) -> anyhow::Result<Output<D>> {
-    let filesize = usize::try_from(file_size(filename.as_ref())?).ok();
+/// A source of bytes that can be fed into any hasher, eg a file on disk or an entry in an archive
+pub trait HashSource {
+    /// Feed the entire contents of this source into the given hasher
+    fn feed<U: Update>(self, hasher: &mut U) -> anyhow::Result<()>;
 
-    if filesize.map_or(false, |size| size <= BUFFER_SIZE) {
-        // this file is smaller than the buffer size, so we can hash it all at once
-        return hash_file_whole::<D>(filename);
+    /// How many bytes the source holds, if that is known before it is read
+    fn size(&self) -> Option<u64> {
+        None
     }
+}
+
+/// A file on disk, read according to the given I/O options
+pub struct FileSource<'a> {
+    pub path: &'a str,
+    pub io: &'a IoOptions,
+}
+
+impl HashSource for FileSource<'_> {
+    fn size(&self) -> Option<u64> {
+        file_size(self.path).ok()
+    }
+
+    fn feed<U: Update>(self, hasher: &mut U) -> anyhow::Result<()> {
+        let size = file_size(self.path)?;
 
-    // read the file in chunks
-    let file = File::open(filename.as_ref())?;
-    let mut reader = BufReader::new(file);
-    let mut buffer = build_heap_buffer(BUFFER_SIZE);
+        // there is no memory mapping on WebAssembly, and --mmap is refused there
+        #[cfg(not(target_family = "wasm"))]
+        if self
+            .io
+            .mmap_threshold
+            .is_some_and(|threshold| size >= threshold)
+        {
+            // large file, map it into memory instead of issuing many small reads
+            let file = File::open(self.path)?;
+            // SAFETY: the map is read-only and dropped before returning. If another process truncates
+            // the file while we hash it the result is undefined, just as with any tool that maps files
+            let mapped = unsafe { memmap2::Mmap::map(&file)? };
+            limits::throttle(mapped.len());
+            hasher.update(&mapped);
+            return Ok(());
+        }
 
-    let mut hasher = D::new();
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break; // nothing more to read
+        if self.io.direct_io {
+            // bypass the OS cache, even for small files
+            let _permit = limits::io_permit(self.path);
+            return crate::direct_io::feed_file(self.path, size, hasher);
         }
-        hasher.update(&buffer[..bytes_read]);
-        if bytes_read < BUFFER_SIZE {
-            break; // we've reached the end of the file
+
+        let buffer_size = self.io.buffer_size;
+
+        #[cfg(target_os = "linux")]
+        if self.io.io_uring && size > buffer_size as u64 {
+            // keep several reads in flight, instead of one blocking read at a time
+            let _permit = limits::io_permit(self.path);
+            return crate::uring::feed_file(self.path, size, hasher);
+        }
+
+        // files too big for the buffer, but small enough to read into memory in one go
+        let whole_file = usize::try_from(size).ok().filter(|&whole| {
+            !self.io.drop_cache
+                && whole > buffer_size
+                && self.io.max_in_memory.is_some_and(|limit| size <= limit)
+        });
+
+        if let Some(whole_size) = whole_file {
+            // read it all, then hash without holding a read permit
+            let _memory = limits::memory_permit(whole_size);
+            let data = {
+                let _permit = limits::io_permit(self.path);
+                std::fs::read(self.path)?
+            };
+            limits::throttle(data.len());
+            hasher.update(&data);
+            return Ok(());
+        }
+
+        // read the file in chunks. Files smaller than the buffer are read in one go
+        let file = File::open(self.path)?;
+
+        #[cfg(target_os = "linux")]
+        if self.io.drop_cache {
+            advise(&file, libc::POSIX_FADV_SEQUENTIAL);
+            advise(&file, libc::POSIX_FADV_WILLNEED);
         }
+
+        // when reading is limited separately, hash on this thread while another holds the read permit.
+        // WebAssembly has no threads to read on
+        let pipelined = cfg!(not(target_family = "wasm"))
+            && (size >= PIPELINE_THRESHOLD || (limits::io_limited() && size > buffer_size as u64));
+
+        let result = if pipelined {
+            feed_double_buffered(&file, self.path, buffer_size, hasher)
+        } else {
+            let _permit = limits::io_permit(self.path);
+            ReaderSource {
+                reader: &file,
+                buffer_size,
+            }
+            .feed(hasher)
+        };
+
+        #[cfg(target_os = "linux")]
+        if self.io.drop_cache {
+            // we won't read this again, so don't leave it in the page cache
+            advise(&file, libc::POSIX_FADV_DONTNEED);
+        }
+
+        result
+    }
+}
+
+/// Read on a separate thread into a pair of alternating buffers, so reading the next chunk overlaps hashing this one
+fn feed_double_buffered<R: Read + Send, U: Update>(
+    mut reader: R,
+    path: &str,
+    buffer_size: usize,
+    hasher: &mut U,
+) -> anyhow::Result<()> {
+    // filled buffers go to the hasher, and empty ones come back to be refilled
+    let (full_tx, full_rx) = mpsc::sync_channel::<(Box<[u8]>, usize)>(2);
+    let (empty_tx, empty_rx) = mpsc::sync_channel::<Box<[u8]>>(2);
+    for _ in 0..2 {
+        empty_tx.send(build_heap_buffer(buffer_size))?;
     }
 
-    // Output<T> = GenericArray<u8, <T as OutputSizeUser>::OutputSize>
-    // just return this directly to avoid an extra allocation
-    let hash_array = hasher.finalize();
-    Ok(hash_array)
+    thread::scope(|scope| {
+        let read_thread = scope.spawn(move || -> std::io::Result<()> {
+            let _permit = limits::io_permit(path);
+
+            // stops when the file is exhausted, or the hasher has gone away
+            for mut buffer in empty_rx {
+                let bytes_read = reader.read(&mut buffer)?;
+                limits::throttle(bytes_read);
+                if bytes_read == 0 || full_tx.send((buffer, bytes_read)).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+
+        // hash on this thread, until the reader hangs up
+        for (buffer, bytes_read) in full_rx {
+            hasher.update(&buffer[..bytes_read]);
+            if empty_tx.send(buffer).is_err() {
+                break;
+            }
+        }
+
+        match read_thread.join() {
+            Ok(result) => result.map_err(anyhow::Error::from),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    })
 }
 
-/// Hash the entire file at once
-fn hash_file_whole<D: Digest>(filename: impl AsRef<str>) -> anyhow::Result<Output<D>> {
-    let data = std::fs::read(filename.as_ref())?;
-    let mut hasher = D::new();
-    hasher.update(&data);
+/// Give the kernel a hint about how the whole file will be accessed. Hints are optional, so failure is ignored
+#[cfg(target_os = "linux")]
+fn advise(file: &File, advice: libc::c_int) {
+    use std::os::unix::io::AsRawFd;
 
-    let hash_array = hasher.finalize();
-    Ok(hash_array)
+    // SAFETY: the descriptor is valid for the lifetime of the borrowed file
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, advice);
+    }
 }
 
-/// Hash a file using the given hasher as a Digest implementation, and encode the output
+/// Any reader, consumed until EOF. Used for streams that are not plain files, eg zip entries
+pub struct ReaderSource<R: Read> {
+    pub reader: R,
+    pub buffer_size: usize,
+}
+
+impl<R: Read> HashSource for ReaderSource<R> {
+    fn feed<U: Update>(mut self, hasher: &mut U) -> anyhow::Result<()> {
+        with_thread_buffer(self.buffer_size, |buffer| {
+            loop {
+                // short reads are normal for decompressing readers, so only stop at zero
+                let bytes_read = self.reader.read(buffer)?;
+                limits::throttle(bytes_read);
+                if bytes_read == 0 {
+                    break; // nothing more to read
+                }
+                hasher.update(&buffer[..bytes_read]);
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// A file copied to a destination writer as it is read, so the data is only read once
+pub struct CopySource<'a, W: Write> {
+    pub path: &'a str,
+    pub dest: W,
+    pub buffer_size: usize,
+}
+
+impl<W: Write> HashSource for CopySource<'_, W> {
+    fn size(&self) -> Option<u64> {
+        file_size(self.path).ok()
+    }
+
+    fn feed<U: Update>(mut self, hasher: &mut U) -> anyhow::Result<()> {
+        let mut file = File::open(self.path)?;
+
+        with_thread_buffer(self.buffer_size, |buffer| {
+            loop {
+                let bytes_read = file.read(buffer)?;
+                limits::throttle(bytes_read);
+                if bytes_read == 0 {
+                    break; // nothing more to read
+                }
+                hasher.update(&buffer[..bytes_read]);
+                self.dest.write_all(&buffer[..bytes_read])?;
+            }
+
+            self.dest.flush()?;
+            Ok(())
+        })
+    }
+}
+
+/// Data already in memory
+impl HashSource for &[u8] {
+    fn size(&self) -> Option<u64> {
+        Some(self.len() as u64)
+    }
+
+    fn feed<U: Update>(self, hasher: &mut U) -> anyhow::Result<()> {
+        hasher.update(self);
+        Ok(())
+    }
+}
+
+/// Several sources fed one after another, as if they were a single concatenated stream
+impl<S: HashSource> HashSource for Vec<S> {
+    fn size(&self) -> Option<u64> {
+        self.iter().map(HashSource::size).sum()
+    }
+
+    fn feed<U: Update>(self, hasher: &mut U) -> anyhow::Result<()> {
+        for source in self {
+            source.feed(hasher)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hash a source with the given hasher, and encode the output
 #[inline]
-pub fn hash_file_encoded<D: Digest>(
-    filename: impl AsRef<str>,
+pub fn hash_file_encoded(source: impl HashSource, mut hasher: Hasher) -> anyhow::Result<BasicHash> {
+    let _memory = hasher.reserve(source.size())?;
+    source.feed(&mut CountingHasher(&mut hasher))?;
+    hasher.finalize_encoded()
+}
+
+/// A hash built up a chunk at a time, with the algorithm chosen at runtime. Every source is hashed through
+/// one of these, and it can be fed directly with data that doesn't come from a `HashSource`, eg from a socket
+pub struct Hasher {
+    inner: Box<dyn DynHasher>,
     encoding: OutputEncoding,
-) -> anyhow::Result<BasicHash> {
-    let h = hash_file::<D>(filename)?;
+    /// the algorithm holds all its input in memory, and only hashes it at the end
+    whole_input: bool,
+}
+
+impl Hasher {
+    /// Start a new hash using the given algorithm, to be output in the given encoding
+    pub fn new(algorithm: HashAlgorithm, encoding: OutputEncoding) -> Self {
+        let inner: Box<dyn DynHasher> = match algorithm {
+            HashAlgorithm::CRC32 => Box::new(crc32::Crc32::new()),
+            HashAlgorithm::Crc => Box::new(crc::Crc::new(crc::spec())),
+            HashAlgorithm::Crc16Ccitt => Box::new(crc::Crc::new(CrcSpec::CRC16_CCITT)),
+            HashAlgorithm::Crc16Modbus => Box::new(crc::Crc::new(CrcSpec::CRC16_MODBUS)),
+            HashAlgorithm::Crc16Xmodem => Box::new(crc::Crc::new(CrcSpec::CRC16_XMODEM)),
+            HashAlgorithm::Crc16Usb => Box::new(crc::Crc::new(CrcSpec::CRC16_USB)),
+            HashAlgorithm::Crc8Smbus => Box::new(crc::Crc::new(CrcSpec::CRC8_SMBUS)),
+            HashAlgorithm::Crc8Maxim => Box::new(crc::Crc::new(CrcSpec::CRC8_MAXIM)),
+            // old algorithms
+            HashAlgorithm::MD5 => Box::new(Md5::new()),
+            HashAlgorithm::Ed2k => Box::new(ed2k::Ed2k::default()),
+            HashAlgorithm::SHA1 => Box::new(Sha1::new()),
+            // SHA2
+            HashAlgorithm::SHA2_224 => Box::new(Sha224::new()),
+            HashAlgorithm::SHA2_256 => Box::new(Sha256::new()),
+            HashAlgorithm::SHA2_384 => Box::new(Sha384::new()),
+            HashAlgorithm::SHA2_512 => Box::new(Sha512::new()),
+            // SHA3
+            HashAlgorithm::SHA3_256 => Box::new(Sha3_256::new()),
+            HashAlgorithm::SHA3_384 => Box::new(Sha3_384::new()),
+            HashAlgorithm::SHA3_512 => Box::new(Sha3_512::new()),
+            // WHIRLPOOL
+            HashAlgorithm::Whirlpool => Box::new(Whirlpool::new()),
+            // BLAKE2
+            HashAlgorithm::Blake2S256 => Box::new(Blake2s256::new()),
+            HashAlgorithm::Blake2B512 => Box::new(Blake2b512::new()),
+            HashAlgorithm::Blake2bp => Box::new(blake2p::Blake2bp::default()),
+            HashAlgorithm::Blake2sp => Box::new(blake2p::Blake2sp::default()),
+            HashAlgorithm::Blake3 => Box::new(blake3_digest::Blake3::new()),
+            // Tiger Tree Hash, as used by DC++ and magnet links
+            HashAlgorithm::Tth => Box::new(merkle::Merkle::<Tiger>::default()),
+            // NIST SP 800-185
+            HashAlgorithm::ParallelHash128 => Box::new(sp800_185::ParallelHash::new_128(
+                sp800_185::block_size(),
+                sp800_185::customization(),
+            )),
+            HashAlgorithm::ParallelHash256 => Box::new(sp800_185::ParallelHash::new_256(
+                sp800_185::block_size(),
+                sp800_185::customization(),
+            )),
+            HashAlgorithm::Kmac128 => Box::new(sp800_185::Kmac::new_128(
+                sp800_185::key(),
+                sp800_185::customization(),
+            )),
+            HashAlgorithm::Kmac256 => Box::new(sp800_185::Kmac::new_256(
+                sp800_185::key(),
+                sp800_185::customization(),
+            )),
+            // fast fingerprints
+            HashAlgorithm::Rapidhash => Box::new(fingerprint::Rapidhash::default()),
+            HashAlgorithm::T1ha2 => Box::new(fingerprint::T1ha2::default()),
+            HashAlgorithm::MetroHash64 => Box::new(fingerprint::MetroHash64::default()),
+            HashAlgorithm::CityHash128 => Box::new(fingerprint::WholeInput::city_hash128()),
+            HashAlgorithm::FarmHash64 => Box::new(fingerprint::WholeInput::farm_hash64()),
+            // SHA-3 finalists
+            #[cfg(feature = "sha3-finalists")]
+            HashAlgorithm::Groestl256 => Box::new(groestl::Groestl256::new()),
+            #[cfg(feature = "sha3-finalists")]
+            HashAlgorithm::Groestl512 => Box::new(groestl::Groestl512::new()),
+            #[cfg(feature = "sha3-finalists")]
+            HashAlgorithm::Jh256 => Box::new(jh::Jh256::new()),
+            #[cfg(feature = "sha3-finalists")]
+            HashAlgorithm::Skein512_256 => {
+                Box::new(skein::Skein512::<generic_array::typenum::U32>::new())
+            }
+        };
+
+        Self {
+            inner,
+            encoding,
+            whole_input: matches!(
+                algorithm,
+                HashAlgorithm::CityHash128 | HashAlgorithm::FarmHash64
+            ),
+        }
+    }
+
+    /// Room for the whole input, for an algorithm that holds it all in memory. An input over the limit is refused
+    /// before any of it is read, and the memory is taken from the --memory-budget until the hash is finished
+    pub fn reserve(&self, size: Option<u64>) -> anyhow::Result<Option<MemoryPermit>> {
+        match size {
+            Some(size) if self.whole_input && size > fingerprint::WHOLE_INPUT_LIMIT => {
+                Err(fingerprint::too_big())
+            }
+            // the limit keeps the size well within usize
+            Some(size) if self.whole_input => Ok(limits::memory_permit(size as usize)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Add the next chunk of data to the hash
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update_dyn(data);
+    }
+
+    /// Finish the hash, and encode it. Fails if the input was too big for the algorithm
+    pub fn finalize_encoded(self) -> anyhow::Result<BasicHash> {
+        Ok(encode_hash(&self.inner.finalize_dyn()?, self.encoding))
+    }
+}
+
+impl Update for Hasher {
+    fn update(&mut self, data: &[u8]) {
+        self.inner.update_dyn(data);
+    }
+}
+
+/// Object-safe view of a Digest implementation, so hashers for different algorithms can share a type
+trait DynHasher: Send {
+    fn update_dyn(&mut self, data: &[u8]);
+    fn finalize_dyn(self: Box<Self>) -> anyhow::Result<Vec<u8>>;
+}
+
+impl<D: Digest + Send> DynHasher for D {
+    fn update_dyn(&mut self, data: &[u8]) {
+        Digest::update(self, data);
+    }
+
+    fn finalize_dyn(self: Box<Self>) -> anyhow::Result<Vec<u8>> {
+        Ok(self.finalize().to_vec())
+    }
+}
+
+/// CRCs of any width, whose output size is only known at runtime so they can't be a Digest
+impl DynHasher for crc::Crc {
+    fn update_dyn(&mut self, data: &[u8]) {
+        Update::update(self, data);
+    }
+
+    fn finalize_dyn(self: Box<Self>) -> anyhow::Result<Vec<u8>> {
+        Ok(self.finalize_bytes())
+    }
+}
 
+/// ParallelHash, whose output is read from an XOF rather than a Digest
+impl DynHasher for sp800_185::ParallelHash {
+    fn update_dyn(&mut self, data: &[u8]) {
+        Update::update(self, data);
+    }
+
+    fn finalize_dyn(self: Box<Self>) -> anyhow::Result<Vec<u8>> {
+        Ok(self.finalize_bytes())
+    }
+}
+
+/// KMAC, which like ParallelHash is read from an XOF
+impl DynHasher for sp800_185::Kmac {
+    fn update_dyn(&mut self, data: &[u8]) {
+        Update::update(self, data);
+    }
+
+    fn finalize_dyn(self: Box<Self>) -> anyhow::Result<Vec<u8>> {
+        Ok(self.finalize_bytes())
+    }
+}
+
+/// CityHash128 and FarmHash64, which collect their input and can refuse it as too big
+impl DynHasher for fingerprint::WholeInput {
+    fn update_dyn(&mut self, data: &[u8]) {
+        self.update(data);
+    }
+
+    fn finalize_dyn(self: Box<Self>) -> anyhow::Result<Vec<u8>> {
+        self.finalize_bytes()
+    }
+}
+
+/// Passes data straight through to a hasher, counting the bytes for the run statistics
+struct CountingHasher<'a, U: Update>(&'a mut U);
+
+impl<U: Update> Update for CountingHasher<'_, U> {
+    fn update(&mut self, data: &[u8]) {
+        stats::add_bytes(data.len() as u64);
+        self.0.update(data);
+    }
+}
+
+/// Encode raw hash bytes using the requested output encoding
+pub fn encode_hash(h: &[u8], encoding: OutputEncoding) -> BasicHash {
     let encoded = match encoding {
         OutputEncoding::Hex | OutputEncoding::Unspecified => hex::encode(h),
-        OutputEncoding::Base64 => BASE64.encode(&h),
-        OutputEncoding::Base32 => BASE32.encode(&h),
+        OutputEncoding::Base64 => BASE64.encode(h),
+        OutputEncoding::Base32 => BASE32.encode(h),
         OutputEncoding::U32 => {
-            // check if h size is 4 bytes
-            assert_eq!(h.len(), 4, "Hash size is not 4 bytes, but u32 requested");
+            // small checksums only, eg CRC32 or a CRC-16, padded to the digits of the largest possible value
+            assert!(h.len() <= 4, "Hash is over 4 bytes, but u32 requested");
 
-            let number = BigEndian::read_u32(&h);
-            format!("{number:010}")
+            let number = BigEndian::read_uint(h, h.len());
+            let digits = match h.len() {
+                1 => 3,
+                2 => 5,
+                3 => 8,
+                _ => 10,
+            };
+            format!("{number:0digits$}")
         }
     };
 
-    Ok(BasicHash(encoded))
+    BasicHash(encoded)
+}
+
+/// Turn a hash made by the algorithm back into raw bytes, eg to print it in another encoding. A number is cut
+/// to the bytes of the checksum, as a CRC-16 is two bytes, not four
+pub fn decode_hash(
+    hash: &BasicHash,
+    encoding: OutputEncoding,
+    algorithm: HashAlgorithm,
+) -> Option<Vec<u8>> {
+    match encoding {
+        OutputEncoding::Hex | OutputEncoding::Unspecified => hex::decode(&hash.0).ok(),
+        OutputEncoding::Base64 => BASE64.decode(hash.0.as_bytes()).ok(),
+        OutputEncoding::Base32 => BASE32.decode(hash.0.as_bytes()).ok(),
+        OutputEncoding::U32 => hash.0.parse::<u32>().ok().map(|n| {
+            let bytes = n.to_be_bytes();
+            bytes[bytes.len() - algorithm.digest_bits().div_ceil(8).min(4)..].to_vec()
+        }),
+    }
 }
 
 /// check if file exists
@@ -94,6 +530,25 @@ fn file_size(path: impl AsRef<str>) -> anyhow::Result<u64> {
     }
 }
 
+thread_local! {
+    /// Read buffer reused for every file hashed on this thread, so millions of small files don't churn the allocator
+    static THREAD_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run a function with this thread's reusable buffer, grown to at least `len` bytes
+fn with_thread_buffer<T>(len: usize, f: impl FnOnce(&mut [u8]) -> T) -> T {
+    THREAD_BUFFER.with(|cell| match cell.try_borrow_mut() {
+        Ok(mut buffer) => {
+            if buffer.len() < len {
+                buffer.resize(len, 0);
+            }
+            f(&mut buffer[..len])
+        }
+        // already in use further up the stack, so fall back to a fresh buffer
+        Err(_) => f(&mut build_heap_buffer(len)),
+    })
+}
+
 /// Build a heap buffer of a given size, filled with default values
 fn build_heap_buffer<T: Default + Copy>(len: usize) -> Box<[T]> {
     let vec = vec![T::default(); len];
@@ -0,0 +1,126 @@
+use std::path::Path;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Where files are on disk, for --hdd-mode and --device-readers. On a spinning disk the time goes on seeks, so
+// with --hdd-mode the files of each device are read one after another, in the order they are laid out on
+// the disk, instead of several at once in any order
+
+/// Where a file lives: its device, then roughly where on that device. Files without a known physical
+/// offset (empty, or on a filesystem that won't say) come after the rest, in inode order
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub device: u64,
+    pub offset: u64,
+    pub inode: u64,
+}
+
+/// The positions of the items, grouped by device and each group in physical order. Every group is meant
+/// to be worked through by one thread, so each disk has a single sequential reader
+pub fn plan<T: AsRef<Path> + Sync>(items: &[T]) -> Vec<Vec<usize>> {
+    // looking up thousands of files is itself slow, so it is spread over the pool where possible
+    #[cfg(feature = "parallel")]
+    let locations: Vec<Location> = items.par_iter().map(|item| locate(item.as_ref())).collect();
+    #[cfg(not(feature = "parallel"))]
+    let locations: Vec<Location> = items.iter().map(|item| locate(item.as_ref())).collect();
+
+    group_by_device(&locations)
+}
+
+/// The positions grouped by device, each group sorted by location. Ties keep their original order
+pub fn group_by_device(locations: &[Location]) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..locations.len()).collect();
+    order.sort_by_key(|&index| (locations[index], index));
+
+    order
+        .chunk_by(|&a, &b| locations[a].device == locations[b].device)
+        .map(<[usize]>::to_vec)
+        .collect()
+}
+
+/// Find where a file is. Files that can't be looked up all share the default location, and fail later
+#[cfg(unix)]
+fn locate(path: &Path) -> Location {
+    use std::os::unix::fs::MetadataExt;
+
+    match std::fs::metadata(path) {
+        Ok(metadata) => Location {
+            device: metadata.dev(),
+            offset: physical_offset(path).unwrap_or(u64::MAX),
+            inode: metadata.ino(),
+        },
+        Err(_) => Location::default(),
+    }
+}
+
+/// The device a file is on. Files that can't be looked up are put on device 0, and fail later
+#[cfg(unix)]
+pub fn device(path: &Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::metadata(path).map_or(0, |metadata| metadata.dev())
+}
+
+/// Without device numbers every file is on the same device
+#[cfg(not(unix))]
+pub fn device(_path: &Path) -> u64 {
+    0
+}
+
+/// Without device and inode numbers everything is one device, left in the order given
+#[cfg(not(unix))]
+fn locate(_path: &Path) -> Location {
+    Location::default()
+}
+
+/// The physical offset of the start of the file on its device, from the FIEMAP ioctl
+#[cfg(target_os = "linux")]
+fn physical_offset(path: &Path) -> Option<u64> {
+    use std::os::unix::io::AsRawFd;
+
+    // struct fiemap_extent and struct fiemap from linux/fiemap.h, with room for a single extent
+    #[repr(C)]
+    #[derive(Default)]
+    struct FiemapExtent {
+        logical: u64,
+        physical: u64,
+        length: u64,
+        reserved64: [u64; 2],
+        flags: u32,
+        reserved: [u32; 3],
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Fiemap {
+        start: u64,
+        length: u64,
+        flags: u32,
+        mapped_extents: u32,
+        extent_count: u32,
+        reserved: u32,
+        extents: [FiemapExtent; 1],
+    }
+
+    // _IOWR('f', 11, struct fiemap)
+    const FS_IOC_FIEMAP: u32 = 0xC020_660B;
+
+    let file = std::fs::File::open(path).ok()?;
+    let mut map = Fiemap {
+        length: u64::MAX,
+        extent_count: 1,
+        ..Fiemap::default()
+    };
+
+    // SAFETY: the descriptor is valid for the lifetime of the file, and map is a fiemap with room for the
+    // single extent asked for
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FIEMAP as _, &mut map) };
+    (result == 0 && map.mapped_extents > 0).then_some(map.extents[0].physical)
+}
+
+/// Other systems have no portable way to ask, so inode order is the best guess
+#[cfg(all(unix, not(target_os = "linux")))]
+fn physical_offset(_path: &Path) -> Option<u64> {
+    None
+}
@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// The journal for this run, if one was asked for
+static JOURNAL: OnceLock<Journal> = OnceLock::new();
+
+/// Paths completed by this and earlier runs, one per line, so an interrupted run can carry on where it stopped
+struct Journal {
+    /// paths already completed when this run started
+    done: HashSet<String>,
+    writer: Mutex<LineWriter<File>>,
+}
+
+/// Open the journal, reading the paths completed by earlier runs. It is created if it doesn't exist
+pub fn open(path: &str) -> anyhow::Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+
+    let mut done = HashSet::new();
+    for line in BufReader::new(&file).lines() {
+        done.insert(line?);
+    }
+
+    // only ever opened once, so there is nothing to replace
+    let _ = JOURNAL.set(Journal {
+        done,
+        writer: Mutex::new(LineWriter::new(file)),
+    });
+    Ok(())
+}
+
+/// Remove paths already completed by an earlier run, returning how many were skipped
+pub fn skip_done(paths: &mut Vec<String>) -> usize {
+    let before = paths.len();
+    paths.retain(|path| !is_done(path));
+    before - paths.len()
+}
+
+/// true if the journal says the path was completed by an earlier run
+pub fn is_done(path: &str) -> bool {
+    JOURNAL
+        .get()
+        .is_some_and(|journal| journal.done.contains(path))
+}
+
+/// Record a path as completed, so it is skipped if the run is restarted
+pub fn record(path: &str) -> anyhow::Result<()> {
+    if let Some(journal) = JOURNAL.get() {
+        let mut writer = journal
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        writeln!(writer, "{path}")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use std::fmt::{self, Display, Write};
+
+/// A string written as a quoted JSON string
+pub struct JsonString<'a>(pub &'a str);
+
+impl Display for JsonString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str(r#"\""#)?,
+                '\\' => f.write_str(r"\\")?,
+                '\n' => f.write_str(r"\n")?,
+                '\r' => f.write_str(r"\r")?,
+                '\t' => f.write_str(r"\t")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// A parsed JSON value
+#[derive(Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// The value of a field, if this is an object that has it
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            Self::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a JSON document. Only as much of JSON as checksum manifests need, but all of it is accepted
+pub fn parse(text: &str) -> anyhow::Result<JsonValue> {
+    let mut parser = Parser {
+        chars: text.char_indices().peekable(),
+    };
+    let value = parser.value()?;
+    parser.skip_space();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some((at, _)) => Err(anyhow::anyhow!("Unexpected text after the JSON at {at}")),
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, wanted: char) -> anyhow::Result<()> {
+        self.skip_space();
+        match self.chars.next() {
+            Some((_, c)) if c == wanted => Ok(()),
+            Some((at, c)) => Err(anyhow::anyhow!("Expected '{wanted}' at {at}, found '{c}'")),
+            None => Err(anyhow::anyhow!("Expected '{wanted}', found the end")),
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<JsonValue> {
+        self.skip_space();
+        match self.chars.peek().copied() {
+            Some((_, '{')) => self.object(),
+            Some((_, '[')) => self.array(),
+            Some((_, '"')) => Ok(JsonValue::String(self.string()?)),
+            Some((_, 't')) => self.word("true", JsonValue::Bool(true)),
+            Some((_, 'f')) => self.word("false", JsonValue::Bool(false)),
+            Some((_, 'n')) => self.word("null", JsonValue::Null),
+            Some((_, c)) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some((at, c)) => Err(anyhow::anyhow!("Unexpected '{c}' at {at}")),
+            None => Err(anyhow::anyhow!("Unexpected end of the JSON")),
+        }
+    }
+
+    fn word(&mut self, word: &str, value: JsonValue) -> anyhow::Result<JsonValue> {
+        for wanted in word.chars() {
+            self.expect(wanted)?;
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> anyhow::Result<JsonValue> {
+        let mut text = String::new();
+        while let Some((_, c)) = self
+            .chars
+            .next_if(|(_, c)| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            text.push(c);
+        }
+        Ok(JsonValue::Number(text.parse()?))
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next().map(|(_, c)| c) {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.chars.next().map(|(_, c)| c) {
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex: String = (0..4)
+                            .filter_map(|_| self.chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        let unit = u32::from_str_radix(&hex, 16)?;
+                        s.push(char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    Some(c) => s.push(c),
+                    None => break,
+                },
+                Some(c) => s.push(c),
+                None => break,
+            }
+        }
+        Err(anyhow::anyhow!("Unterminated string in the JSON"))
+    }
+
+    fn array(&mut self) -> anyhow::Result<JsonValue> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_space();
+        if self.chars.next_if(|(_, c)| *c == ']').is_some() {
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_space();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, ']')) => return Ok(JsonValue::Array(items)),
+                _ => return Err(anyhow::anyhow!("Expected ',' or ']' in an array")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> anyhow::Result<JsonValue> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_space();
+        if self.chars.next_if(|(_, c)| *c == '}').is_some() {
+            return Ok(JsonValue::Object(fields));
+        }
+        loop {
+            self.skip_space();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_space();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, '}')) => return Ok(JsonValue::Object(fields)),
+                _ => return Err(anyhow::anyhow!("Expected ',' or '}}' in an object")),
+            }
+        }
+    }
+}
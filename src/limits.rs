@@ -0,0 +1,215 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Condvar, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::hdd;
+
+/// Limits how many files are read at once, independently of how many threads are hashing
+static IO_LIMIT: OnceLock<Semaphore> = OnceLock::new();
+
+/// Limits how many files are read at once from each device
+static DEVICE_LIMIT: OnceLock<DeviceLimit> = OnceLock::new();
+
+/// Limits how many files are open at once, to stay within the process file handle limit
+static OPEN_LIMIT: OnceLock<Semaphore> = OnceLock::new();
+
+/// Limits the bytes held by whole-file reads, across all threads
+static MEMORY_BUDGET: OnceLock<Semaphore> = OnceLock::new();
+
+/// Limits the total read bandwidth, across all threads
+static THROTTLE: OnceLock<Throttle> = OnceLock::new();
+
+/// A counting semaphore, limiting how many threads can do something at the same time
+pub struct Semaphore {
+    total: usize,
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Semaphore {
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            total: permits,
+            available: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Wait for a permit. It is given back when the returned guard is dropped
+    pub fn acquire(&self) -> Permit<'_> {
+        self.acquire_many(1)
+    }
+
+    /// Wait for several permits at once, eg one per byte. Asking for more than the total waits for all of them
+    pub fn acquire_many(&self, permits: usize) -> Permit<'_> {
+        let permits = permits.min(self.total);
+        let mut available = self
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while *available < permits {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *available -= permits;
+
+        Permit {
+            semaphore: self,
+            permits,
+        }
+    }
+}
+
+/// Permits from a `Semaphore`, released on drop
+pub struct Permit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut available = self
+            .semaphore
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *available += self.permits;
+        // waiters may need different amounts, so let them all check
+        self.semaphore.released.notify_all();
+    }
+}
+
+/// A semaphore for each device, made the first time a file on it is read
+struct DeviceLimit {
+    readers: usize,
+    devices: Mutex<HashMap<u64, &'static Semaphore>>,
+}
+
+impl DeviceLimit {
+    fn semaphore(&self, device: u64) -> &'static Semaphore {
+        let mut devices = self.devices.lock().unwrap_or_else(PoisonError::into_inner);
+        // there are only ever a handful of devices, and each lives for the rest of the run
+        devices
+            .entry(device)
+            .or_insert_with(|| Box::leak(Box::new(Semaphore::new(self.readers))))
+    }
+}
+
+/// The permits held while reading a file: one for its device, then one of the overall readers
+pub type ReadPermits = (Option<Permit<'static>>, Option<Permit<'static>>);
+
+/// Allow at most this many files to be read at once. Only the first call has any effect
+pub fn set_io_limit(readers: usize) {
+    let _ = IO_LIMIT.set(Semaphore::new(readers));
+}
+
+/// Allow at most this many files to be read at once from any one device. Only the first call has any effect
+pub fn set_device_limit(readers: usize) {
+    let _ = DEVICE_LIMIT.set(DeviceLimit {
+        readers,
+        devices: Mutex::new(HashMap::new()),
+    });
+}
+
+/// true if reading is limited separately from hashing
+pub fn io_limited() -> bool {
+    IO_LIMIT.get().is_some() || DEVICE_LIMIT.get().is_some()
+}
+
+/// Wait until this thread may read the file. The device comes first, so threads queued for a slow disk don't
+/// hold readers that files on other disks could use. Returns at once if reading is unlimited
+pub fn io_permit(path: &str) -> ReadPermits {
+    let device = DEVICE_LIMIT
+        .get()
+        .map(|limit| limit.semaphore(hdd::device(Path::new(path))).acquire());
+    (device, IO_LIMIT.get().map(Semaphore::acquire))
+}
+
+/// Allow at most this many files to be open at once. Only the first call has any effect
+pub fn set_open_limit(files: usize) {
+    let _ = OPEN_LIMIT.set(Semaphore::new(files));
+}
+
+/// Wait until this thread may open another file. Returns None immediately if open files are unlimited
+pub fn open_permit() -> Option<Permit<'static>> {
+    OPEN_LIMIT.get().map(Semaphore::acquire)
+}
+
+/// Limit the memory used by whole-file reads, across all threads. Only the first call has any effect
+pub fn set_memory_budget(bytes: usize) {
+    let _ = MEMORY_BUDGET.set(Semaphore::new(bytes));
+}
+
+thread_local! {
+    /// true while this thread has memory from the budget for the file it is working on
+    static HOLDS_MEMORY: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Memory taken from the budget, given back on drop
+pub struct MemoryPermit {
+    _permit: Permit<'static>,
+}
+
+impl Drop for MemoryPermit {
+    fn drop(&mut self) {
+        HOLDS_MEMORY.set(false);
+    }
+}
+
+/// Wait until there is room in the memory budget to hold this many bytes. Returns None if memory is unlimited,
+/// or if this thread already has memory set aside for the file, eg for CityHash128 to collect it, as waiting
+/// for more could wait forever
+pub fn memory_permit(bytes: usize) -> Option<MemoryPermit> {
+    if HOLDS_MEMORY.get() {
+        return None;
+    }
+    let permit = MEMORY_BUDGET.get()?.acquire_many(bytes);
+    HOLDS_MEMORY.set(true);
+    Some(MemoryPermit { _permit: permit })
+}
+
+/// Shared bandwidth limit. Each read books the next slot of time its bytes need at the given rate
+struct Throttle {
+    bytes_per_sec: u64,
+    next_free: Mutex<Instant>,
+}
+
+impl Throttle {
+    /// Book time for some bytes just read, and sleep until the booking has passed
+    fn consume(&self, bytes: usize) {
+        let needed = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        let now = Instant::now();
+
+        let slot_end = {
+            let mut next_free = self
+                .next_free
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            *next_free = (*next_free).max(now) + needed;
+            *next_free
+        };
+
+        // sleep without holding the lock, so other threads can book their own slots
+        std::thread::sleep(slot_end.saturating_duration_since(now));
+    }
+}
+
+/// Limit the total read rate of all threads to this many bytes per second. Only the first call has any effect
+pub fn set_throttle(bytes_per_sec: u64) {
+    let _ = THROTTLE.set(Throttle {
+        bytes_per_sec,
+        next_free: Mutex::new(Instant::now()),
+    });
+}
+
+/// Account for bytes just read, sleeping if the bandwidth limit has been reached
+#[inline]
+pub fn throttle(bytes: usize) {
+    if let Some(throttle) = THROTTLE.get() {
+        throttle.consume(bytes);
+    }
+}
@@ -0,0 +1,27 @@
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::SystemTime;
+
+/// The log file, if one was asked for. Shared by all threads, one whole line at a time
+static LOG_FILE: OnceLock<Mutex<LineWriter<File>>> = OnceLock::new();
+
+/// Open the log file, appending so earlier runs are kept
+pub fn open(path: &str) -> anyhow::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    // only ever opened once, so there is nothing to replace
+    let _ = LOG_FILE.set(Mutex::new(LineWriter::new(file)));
+    Ok(())
+}
+
+/// Write a timestamped line to the log file, if there is one
+pub fn record(message: impl Display) {
+    if let Some(log) = LOG_FILE.get() {
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let mut writer = log.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // a failed write shouldn't stop the scan, the results still go to stdout
+        let _ = writeln!(writer, "{timestamp} {message}");
+    }
+}
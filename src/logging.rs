@@ -0,0 +1,62 @@
+use std::io::{self, Write};
+
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::classes::{LogFormat, Verbosity};
+use crate::progress;
+
+/// Target of the reports asked for on the command line, eg the --stats summary, which are shown at any verbosity
+pub const REPORT: &str = "hash_rust::report";
+
+/// Send diagnostics to stderr at the given verbosity, as plain text or one JSON object per line.
+/// Spans report how long they took when they close, eg the discovery and hashing phases
+pub fn init(verbosity: Verbosity, format: LogFormat) {
+    let level = match verbosity {
+        Verbosity::Quiet => LevelFilter::ERROR,
+        Verbosity::Normal => LevelFilter::WARN,
+        Verbosity::Verbose => LevelFilter::INFO,
+        Verbosity::Debug => LevelFilter::DEBUG,
+    };
+
+    let filter = Targets::new()
+        .with_default(level)
+        .with_target(REPORT, LevelFilter::INFO);
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(LevelFilter::DEBUG)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(|| ProgressAwareStderr);
+
+    match format {
+        // keep the text short, like the rest of the output. No timestamps, but spans still show their timing
+        LogFormat::Text => builder
+            .with_timer(())
+            .with_target(false)
+            .with_ansi(false)
+            .finish()
+            .with(filter)
+            .init(),
+        LogFormat::Json => builder.json().finish().with(filter).init(),
+    }
+}
+
+/// Stderr, with the progress bar hidden while each message is written, and messages kept back while the
+/// --tui browser is open
+struct ProgressAwareStderr;
+
+impl Write for ProgressAwareStderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[cfg(feature = "tui")]
+        if crate::tui::hold_message(buf) {
+            return Ok(buf.len());
+        }
+        progress::suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
@@ -2,52 +2,128 @@
 // #![allow(dead_code)]
 // #![allow(unused_variables)]
 
+#[cfg(feature = "glob")]
+use std::collections::HashSet;
 use std::ffi::OsString;
-use std::fmt::Display;
 use std::io;
-use std::io::BufRead;
+use std::io::{BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::str::FromStr;
+#[cfg(all(feature = "parallel", feature = "glob"))]
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::mpsc::{self, RecvTimeoutError};
+#[cfg(all(feature = "parallel", feature = "glob"))]
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 //use crate::hasher::hash_file_crc32;
-use blake2::{Blake2b512, Blake2s256};
+#[cfg(feature = "glob")]
 use glob::GlobResult;
-use md5::Md5;
 use pico_args::Arguments;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use sha1::Sha1;
-use sha2::{Sha224, Sha256, Sha384, Sha512};
-use sha3::{Sha3_256, Sha3_384, Sha3_512};
-use whirlpool::Whirlpool;
 
 use classes::OutputEncoding;
-use hasher::{file_exists, hash_file_encoded};
+use hasher::{
+    decode_hash, encode_hash, file_exists, hash_file_encoded, FileSource, HashSource, Hasher,
+};
+use json::JsonString;
+use observer::Event;
+use stats::FileTiming;
+use strum::IntoEnumIterator;
 
 use crate::classes::{
-    BasicHash, ConfigSettings, HashAlgorithm, DEFAULT_HASH, GIT_VERSION_SHORT, HELP, VERSION,
+    BasicHash, CheckOptions, ChecksumFormat, ColorMode, ConfigSettings, ConvertOptions, ExitStatus,
+    HashAlgorithm, HashFamily, IoOptions, LinkMode, LogFormat, OutputFormat, ProgressMode,
+    ResultFormat, ScriptKind, SortOrder, UsageError, Verbosity, DEFAULT_BUFFER_SIZE, DEFAULT_HASH,
+    DEFAULT_MMAP_THRESHOLD, GIT_VERSION_SHORT, HELP, VERSION,
 };
 
+mod accel;
+mod archive;
+mod bench;
+mod blake2p;
+mod blake3_digest;
+#[cfg(feature = "glob")]
+mod braces;
+mod canonical;
+mod check;
 mod classes;
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod color;
+mod convert;
+mod copier;
+mod crc;
 mod crc32;
+mod dedupe;
+mod direct_io;
+mod dirsums;
+mod ed2k;
+mod fingerprint;
 mod hasher;
+mod hdd;
+mod journal;
+mod json;
+mod limits;
+mod logfile;
+mod logging;
+mod man;
+mod merkle;
+#[cfg(feature = "notify")]
+mod notify;
+mod observer;
+mod output;
+mod paths;
+mod profiles;
+#[cfg(feature = "progress")]
+mod progress;
+#[cfg(not(feature = "progress"))]
+#[path = "progress_off.rs"]
+mod progress;
+mod qr;
+mod sample;
+mod self_test;
+mod signing;
+mod sp800_185;
+mod stats;
+#[cfg(feature = "tui")]
+mod tui;
 mod unit_tests;
+#[cfg(target_os = "linux")]
+mod uring;
+#[cfg(feature = "glob")]
+mod walk;
+#[cfg(feature = "webhook")]
+mod webhook;
 
-/// Call the inner worker function, and show help if there is an error
-fn main() -> anyhow::Result<()> {
-    let result = worker_func();
-
-    if let Err(e) = result {
-        // there was an error, show help
-        show_help(true);
-        println!();
-        return Err(e);
-    }
+/// Call the inner worker function, and show help if the command line was wrong
+fn main() -> ExitCode {
+    let status = match worker_func() {
+        Ok(status) => status,
+        Err(e) if e.is::<UsageError>() => {
+            // there was an error in the arguments, show help
+            show_help(true);
+            println!();
+            eprintln!("Error: {e}");
+            ExitStatus::Usage
+        }
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitStatus::Fatal
+        }
+    };
 
-    Ok(())
+    status.into()
 }
 
 /// main worker function for entire app
-fn worker_func() -> anyhow::Result<()> {
-    let mut pargs = Arguments::from_env();
+fn worker_func() -> anyhow::Result<ExitStatus> {
+    // a --profile is swapped for the options it stands for, before anything else looks at them
+    let args = profiles::expand(std::env::args_os().skip(1).collect()).map_err(UsageError)?;
+    let mut pargs = Arguments::from_vec(args);
 
     // diagnostic code to set the parameters
     //let paramsvec: Vec<std::ffi::OsString> = vec!["--rubbish".into()];
@@ -57,66 +133,479 @@ fn worker_func() -> anyhow::Result<()> {
     // special handling of help
     if pargs.contains(["-h", "--help"]) {
         show_help(true);
-        return Ok(());
+        return Ok(ExitStatus::Success);
+    }
+
+    // print the version, with build details as JSON, and exit
+    if pargs.contains(["-V", "--version"]) {
+        let format = pargs
+            .opt_value_from_fn("--format", parse_output_format)
+            .map_err(|e| UsageError(e.into()))?;
+        accel::show_version(format.unwrap_or(OutputFormat::Text));
+        return Ok(ExitStatus::Success);
+    }
+
+    // list the algorithms and their details, and exit
+    if pargs.contains("--list-algorithms") {
+        let format = pargs
+            .opt_value_from_fn("--format", parse_output_format)
+            .map_err(|e| UsageError(e.into()))?;
+        show_algorithms(format.unwrap_or(OutputFormat::Text));
+        return Ok(ExitStatus::Success);
+    }
+
+    // report on hardware acceleration, and exit
+    if pargs.contains("--features-report") {
+        accel::show_features_report();
+        return Ok(ExitStatus::Success);
+    }
+
+    // print a man page built from the help text, and exit
+    if pargs.contains("--generate-man") {
+        print!("{}", man::generate());
+        return Ok(ExitStatus::Success);
+    }
+
+    // check every algorithm and encoding against known answers, and exit
+    if pargs.contains("--self-test") {
+        return Ok(if self_test::run_self_test()? {
+            ExitStatus::Success
+        } else {
+            ExitStatus::Mismatch
+        });
     }
 
     // parse the command line arguments
-    let config = process_command_line(pargs)?;
+    let config = process_command_line(pargs).map_err(UsageError)?;
+
+    logging::init(config.verbosity, config.log_format);
+    color::init(config.color);
+    show_initial_info(&config);
+
+    if let Some(options) = config.convert {
+        convert::run(config.supplied_path.as_deref(), options)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if config.bench {
+        bench::run_benchmark(&config)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if let Some(spec) = config.crc_spec {
+        crc::set_spec(spec);
+    }
+    if let Some(size) = config.block_size {
+        sp800_185::set_block_size(size);
+    }
+    if let Some(key) = config.key.clone() {
+        sp800_185::set_key(key);
+    }
+    if let Some(customization) = config.customization.clone() {
+        sp800_185::set_customization(customization);
+    }
 
-    if config.debug_mode {
-        show_initial_info(&config);
+    // one pool of --jobs threads, for hashing and everything done in parallel before it
+    #[cfg(feature = "parallel")]
+    if let Some(jobs) = config.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
     }
 
+    if let Some(readers) = config.io.io_threads {
+        limits::set_io_limit(readers);
+    }
+    if let Some(readers) = config.io.device_readers {
+        limits::set_device_limit(readers);
+    }
+    if let Some(files) = config.max_open {
+        limits::set_open_limit(files);
+    }
+    if let Some(bytes) = config.io.memory_budget {
+        limits::set_memory_budget(bytes);
+    }
+    if let Some(bytes_per_sec) = config.io.throttle {
+        limits::set_throttle(bytes_per_sec);
+    }
+    stats::set_fail_fast(config.fail_fast);
+
+    let start = Instant::now();
+
+    if let Some(log_file) = &config.log_file {
+        logfile::open(log_file)?;
+        logfile::record(format_args!(
+            "START {} {:?} {}",
+            VERSION.unwrap_or("?"),
+            config.algorithm,
+            config.supplied_path.as_deref().unwrap_or("(stdin)")
+        ));
+    }
+
+    if let Some(journal_path) = &config.journal {
+        journal::open(journal_path)?;
+    }
+
+    if let Some(name) = &config.per_dir_sums {
+        dirsums::enable(name);
+    }
+
+    if let Some(events_path) = &config.events {
+        observer::write_events(events_path)?;
+    }
+
+    if config.post_results.is_some() {
+        stats::keep_results();
+    }
+
+    // read before any hashing, so a bad key doesn't waste the run
+    let signing_key = match &config.secret_key {
+        Some(path) if config.sign => {
+            let key = signing::read_secret_key(path)?;
+            signing::start();
+            Some(key)
+        }
+        _ => None,
+    };
+
+    // in check mode the files and their expected hashes are read from a checksum file
+    let public_key = match &config.public_key {
+        Some(path) if config.verify_signature => Some(signing::read_public_key(path)?),
+        _ => None,
+    };
+    let checksums = match config.check {
+        Some(_) => Some(check::load(&config, public_key.as_ref())?),
+        None => None,
+    };
+
+    // when nothing needs the whole list of files first, a walk is hashed as it goes
+    let streaming = checksums.is_none() && hashes_as_found(&config);
+
     // get the required files, either using supplied path or from reading stdin
-    let paths = get_required_filenames(&config)?;
+    let mut paths = match &checksums {
+        Some(checksums) => checksums
+            .entries
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect(),
+        None if streaming => Vec::new(),
+        None => tracing::info_span!("discovery").in_scope(|| get_required_filenames(&config))?,
+    };
 
-    if paths.is_empty() {
-        if config.debug_mode {
-            eprintln!("No files found");
+    if paths.is_empty() && !streaming {
+        if config.check.is_some() {
+            // like sha256sum, having nothing to verify is a failure
+            tracing::warn!("No files were verified");
+            return Ok(ExitStatus::FileErrors);
         }
-        return Ok(());
+        tracing::warn!("No files found");
+        return Ok(ExitStatus::Success);
+    }
+
+    tracing::debug!("Files to hash: {paths:?}");
+
+    let show_progress = match config.progress {
+        ProgressMode::Always => true,
+        ProgressMode::Never => false,
+        ProgressMode::Auto => io::stderr().is_terminal(),
+    } && !config.tui;
+    #[cfg(feature = "tui")]
+    if config.tui {
+        tui::start(paths.len(), config.algorithm)?;
+    }
+    if show_progress && checksums.is_some() {
+        progress::start_verify(&paths, config.prescan)?;
+    } else if show_progress {
+        progress::start(&paths, config.prescan)?;
     }
 
-    if config.debug_mode {
-        eprintln!("Files to hash: {paths:?}");
+    // the files found by a streaming walk are counted once it is over
+    let hashing = tracing::info_span!("hashing", files = tracing::field::Empty).entered();
+    if !streaming {
+        hashing.record("files", paths.len());
     }
 
-    if config.single_thread || paths.len() == 1 {
-        // asked for single thread, or only one path given
-        file_hashes_st(&config, &paths);
+    if config.combine {
+        // all files as one continuous stream
+        file_hash_combined(&config, &mut paths);
+    } else if let Some(checksums) = &checksums {
+        // each file compared with the hash it is expected to have
+        file_hashes(&config, &checksums.entries, |entry| {
+            check::verify(&config, entry);
+        });
+    } else if streaming {
+        #[cfg(all(feature = "parallel", feature = "glob"))]
+        {
+            if hash_as_found(&config)? == 0 {
+                tracing::warn!("No files found");
+            }
+        }
     } else {
-        // multithreaded
-        file_hashes_mt(&config, &paths);
+        file_hashes(&config, &paths, |pathstr| {
+            hash_and_print(&config, pathstr);
+        });
+    }
+
+    hashing.exit();
+    progress::finish();
+    let sums_written = dirsums::write_all()?;
+    if sums_written > 0 {
+        tracing::info!("Wrote checksum files into {sums_written} directories");
+    }
+    if let Some(e) = output::write_error() {
+        return Err(anyhow::anyhow!("Could not write the results: {e}"));
+    }
+    if stats::aborted() && config.tui {
+        tracing::warn!("Stopped when the browser was closed, some files were not hashed");
+    } else if stats::aborted() && !output::closed() {
+        tracing::warn!("Stopped at the first failure, some files were not hashed");
+    }
+    if !config.check.is_some_and(|options| options.status) {
+        stats::show_failures();
+    }
+    #[cfg(feature = "clipboard")]
+    if config.clipboard {
+        clipboard::copy_result();
     }
+    if config.qr {
+        qr::print_result();
+    }
+    if let Some(key) = &signing_key {
+        // last of all, as it covers everything printed before it
+        signing::print_signature(
+            key,
+            &format!(
+                "hashed:{}\talgorithm:{}",
+                config.supplied_path.as_deref().unwrap_or("(stdin)"),
+                config.algorithm.name()
+            ),
+        );
+    }
+    logfile::record(format_args!("END {:.3}s", start.elapsed().as_secs_f64()));
 
-    Ok(())
+    if config.stats {
+        stats::show_stats(start.elapsed());
+    }
+    #[cfg(feature = "notify")]
+    if config.notify {
+        notify::run_finished(start.elapsed());
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(url) = &config.post_results {
+        webhook::post_results(&config, url, start.elapsed());
+    }
+
+    let malformed = checksums.map_or(0, |checksums| checksums.malformed);
+    if malformed > 0 && config.check.is_some_and(|options| options.strict) {
+        // lines that couldn't be understood are files that couldn't be verified
+        return Ok(ExitStatus::FileErrors);
+    }
+
+    Ok(stats::exit_status())
 }
 
 /// get the required files, either using supplied path or from reading stdin
 fn get_required_filenames(config: &ConfigSettings) -> anyhow::Result<Vec<String>> {
     let mut paths = if config.supplied_path.is_none() {
         // no path specified, read from stdin
-        get_paths_from_stdin(config)?
+        get_paths_from_stdin()?
     } else {
-        get_paths_matching_glob(config)?
+        get_paths_matching_glob(config).map_err(UsageError)?
     };
 
+    // leave out files modified outside the --newer-than and --older-than times
+    if config.newer_than.is_some() || config.older_than.is_some() {
+        paths.retain(|path| modified_in_range(config, path));
+    }
+
+    // leave out empty files, or everything else
+    if config.skip_empty || config.only_empty {
+        let before = paths.len();
+        paths.retain(|path| size_wanted(config, path));
+        stats::files_skipped(before - paths.len());
+    }
+
+    // leave out files finished by an earlier run, before applying the limit
+    let skipped = journal::skip_done(&mut paths);
+    if skipped > 0 {
+        tracing::info!("Skipping {skipped} files already completed in the journal");
+    }
+
+    // pick a random subset, for a spot check
+    if let Some(count) = config.sample {
+        let seed = config.seed.unwrap_or_else(sample::random_seed);
+        tracing::info!("Sampling {count} of {} files, --seed {seed}", paths.len());
+        sample::sample(&mut paths, count, seed);
+    }
+
     // limit the number of paths if required
-    if config.limit_num.is_some() && paths.len() > config.limit_num.unwrap() {
-        paths.truncate(config.limit_num.unwrap());
+    if let Some(limit) = config.limit_num {
+        paths.truncate(limit);
+    }
+
+    // stop once the files so far add up to the byte budget. The file that reaches it is hashed in full
+    if let Some(budget) = config.limit_bytes {
+        let mut total = 0;
+        let within_budget = paths
+            .iter()
+            .take_while(|path| {
+                let start = total < budget;
+                total += std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+                start
+            })
+            .count();
+        paths.truncate(within_budget);
     }
 
     Ok(paths)
 }
 
+/// true if a file is wanted by --skip-empty or --only-empty. Files whose size can't be read are kept, so the
+/// error is reported when they are hashed
+fn size_wanted(config: &ConfigSettings, path: &str) -> bool {
+    std::fs::metadata(path).map_or(true, |metadata| (metadata.len() == 0) == config.only_empty)
+}
+
+/// true if the files can be hashed as the walk finds them, instead of once it is finished. Only a `**` walk
+/// hashed in parallel can be, and not when the whole list is needed first: to pick from it, measure it,
+/// order it or count it, or with --copy-to, whose copies the walk would find if they go under the tree
+fn hashes_as_found(config: &ConfigSettings) -> bool {
+    cfg!(all(feature = "parallel", feature = "glob"))
+        && config
+            .supplied_path
+            .as_deref()
+            .is_some_and(|pattern| pattern.contains("**"))
+        && !config.single_thread
+        && !config.combine
+        && config.sample.is_none()
+        && config.limit_num.is_none()
+        && config.limit_bytes.is_none()
+        && !config.hdd_mode
+        && !config.ordered
+        && !config.prescan
+        && !config.tui
+        && config.copy_to.is_none()
+}
+
+/// Most files found by the walk and waiting to be hashed, so a fast walk doesn't fill memory with paths
+#[cfg(all(feature = "parallel", feature = "glob"))]
+const FOUND_QUEUE: usize = 10_000;
+
+/// hash the files matching the `**` pattern as the walk finds them, so hashing starts straight away. The walk
+/// has its own threads, and hands the files to the hashing threads through a queue. Returns how many were found
+#[cfg(all(feature = "parallel", feature = "glob"))]
+fn hash_as_found(config: &ConfigSettings) -> anyhow::Result<usize> {
+    let pattern = config.supplied_path.as_deref().unwrap_or_default();
+    let patterns = braces::expand(pattern).map_err(UsageError)?;
+    for pattern in &patterns {
+        glob::Pattern::new(pattern).map_err(|e| UsageError(e.into()))?;
+    }
+    let found = AtomicUsize::new(0);
+    let journal_done = AtomicUsize::new(0);
+    // files matched by more than one of the patterns from braces are only hashed once
+    let seen = Mutex::new(HashSet::new());
+    let (tx, rx) = mpsc::sync_channel(FOUND_QUEUE);
+    let walked = thread::scope(|scope| {
+        let walk = scope.spawn(|| {
+            // dropped once the walk is over, which ends the queue
+            let tx = tx;
+            let send = |path: String| {
+                if patterns.len() > 1
+                    && !seen
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .insert(path.clone())
+                {
+                    return;
+                }
+                if wanted_as_found(config, &path, &journal_done) {
+                    found.fetch_add(1, atomic::Ordering::Relaxed);
+                    progress::file_found();
+                    let _ = tx.send(path);
+                }
+            };
+            for pattern in &patterns {
+                if pattern.contains("**") {
+                    walk::stream_matching_files(config, pattern, &send)?;
+                } else {
+                    paths_matching_pattern(config, pattern)?
+                        .into_iter()
+                        .for_each(&send);
+                }
+            }
+            anyhow::Ok(())
+        });
+
+        output::batched(|| {
+            let files = rx.into_iter().par_bridge();
+            if holds_results(config) {
+                let (held_tx, held_rx) = mpsc::channel();
+                let _ = files.try_for_each(|path| {
+                    let _ = held_tx.send(output::capture(|| hash_and_print(config, &path)));
+                    keep_going()
+                });
+                drop(held_tx);
+                print_held(config, held_rx.into_iter().flatten().collect());
+            } else {
+                let _ = files.try_for_each(|path| {
+                    hash_and_print(config, &path);
+                    keep_going()
+                });
+            }
+        });
+        walk.join()
+    });
+    walked.unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+
+    let found = found.into_inner();
+    tracing::Span::current().record("files", found);
+    let skipped = journal_done.into_inner();
+    if skipped > 0 {
+        tracing::info!("Skipped {skipped} files already completed in the journal");
+    }
+    Ok(found)
+}
+
+/// true if a file found by the walk is to be hashed, after the same checks `get_required_filenames` makes of
+/// the whole list. Files done by an earlier run are counted
+#[cfg(all(feature = "parallel", feature = "glob"))]
+fn wanted_as_found(config: &ConfigSettings, path: &str, journal_done: &AtomicUsize) -> bool {
+    if (config.newer_than.is_some() || config.older_than.is_some())
+        && !modified_in_range(config, path)
+    {
+        return false;
+    }
+    if (config.skip_empty || config.only_empty) && !size_wanted(config, path) {
+        stats::files_skipped(1);
+        return false;
+    }
+    if journal::is_done(path) {
+        journal_done.fetch_add(1, atomic::Ordering::Relaxed);
+        return false;
+    }
+    true
+}
+
+/// true if the file was modified between the --newer-than and --older-than times. Files whose time
+/// can't be read are kept, so the error is reported when they are hashed
+fn modified_in_range(config: &ConfigSettings, path: &str) -> bool {
+    let Ok(modified) = std::fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+        return true;
+    };
+
+    config.newer_than.is_none_or(|newer| modified > newer)
+        && config.older_than.is_none_or(|older| modified < older)
+}
+
 fn show_initial_info(config: &ConfigSettings) {
-    show_help(false);
-    eprintln!();
-    eprintln!("Config: {config:?}");
-    if config.supplied_path.is_none() {
-        eprintln!("No path specified, reading from stdin");
-    } else {
-        eprintln!("Path: {}", config.supplied_path.as_ref().unwrap());
+    tracing::debug!(
+        "File hasher version {} ({GIT_VERSION_SHORT})",
+        VERSION.unwrap_or("?")
+    );
+    tracing::debug!("Config: {config:?}");
+    match &config.supplied_path {
+        Some(path) => tracing::debug!("Path: {path}"),
+        None => tracing::debug!("No path specified, reading from stdin"),
     }
 }
 
@@ -126,56 +615,599 @@ fn process_command_line(mut pargs: Arguments) -> anyhow::Result<ConfigSettings>
     let algo_str: Option<String> = pargs.opt_value_from_str(["-a", "--algorithm"])?;
     let algo = parse_hash_algorithm(algo_str.as_ref());
 
-    if algo.is_err() {
+    if algo.is_err() {
+        return Err(anyhow::anyhow!(
+            "Algorithm can be: CRC32, CRC16-CCITT, CRC16-MODBUS, CRC16-XMODEM, CRC16-USB, CRC8-SMBUS, CRC8-MAXIM, CRC, MD5, ED2K, SHA1, SHA2 / SHA2-256 / SHA-256, SHA2-224, SHA2-384, SHA2-512, SHA3 / SHA3-256, SHA3-384, SHA3-512, WHIRLPOOL, BLAKE2S-256, BLAKE2B-512, BLAKE2BP, BLAKE2SP, BLAKE3, TTH, PARALLELHASH128, PARALLELHASH256, KMAC128, KMAC256, RAPIDHASH, T1HA2, METROHASH64, FARMHASH64, CITYHASH128. Default is {DEFAULT_HASH:?}",
+        ));
+    }
+
+    // get output encoding as string and parse it. Several can be given, eg hex,base64, the first is
+    // the main one and the rest are printed as extra columns
+    let encoding_list: Option<String> = pargs.opt_value_from_str(["-e", "--encoding"])?;
+    let mut encoding_names = encoding_list.iter().flat_map(|list| list.split(','));
+    let encoding_str: Option<String> = encoding_names.next().map(str::to_string);
+    let encoding = parse_hash_encoding(encoding_str.as_ref());
+    let extra_encodings: Result<Vec<OutputEncoding>, _> =
+        encoding_names.map(OutputEncoding::from_str).collect();
+
+    if encoding.is_err() || extra_encodings.is_err() {
+        return Err(anyhow::anyhow!(
+            "Encoding can be: Hex, Base64, Base32. Default is Hex",
+        ));
+    }
+
+    let algo = algo.unwrap(); // unwrap the algorithm
+
+    // unwrap, and properly assign the default encoding
+    let encoding = {
+        let mut e = encoding.unwrap();
+
+        if e == OutputEncoding::Unspecified {
+            e = default_encoding(algo);
+        }
+
+        // return the encoding, to be assigned to the variable
+        e
+    };
+
+    // the CRC for -a crc, needed here as its width decides whether it fits in a U32
+    let crc_spec = pargs.opt_value_from_fn("--crc-spec", crc::parse_spec)?;
+    if crc_spec.is_some() && algo != HashAlgorithm::Crc {
+        return Err(anyhow::anyhow!("--crc-spec can only be used with -a crc"));
+    }
+    let digest_bits = match (algo, crc_spec) {
+        (HashAlgorithm::Crc, Some(spec)) => spec.width as usize,
+        _ => algo.digest_bits(),
+    };
+    if algo == HashAlgorithm::CRC32 && encoding != OutputEncoding::U32 {
+        return Err(anyhow::anyhow!(
+            "CRC32 can only be output as a 32-bit integer"
+        ));
+    }
+    if encoding == OutputEncoding::U32 && digest_bits > 32 {
+        return Err(anyhow::anyhow!(
+            "Only checksums of 32 bits or less can be output as U32"
+        ));
+    }
+
+    // number of worker threads, -j 1 is the same as --single-thread
+    let jobs: Option<usize> = pargs.opt_value_from_str(["-j", "--jobs"])?;
+    if jobs == Some(0) {
+        return Err(anyhow::anyhow!("--jobs must be greater than zero"));
+    }
+
+    // options for --check, named as sha256sum names them
+    let check_options = CheckOptions {
+        status: pargs.contains("--status"),
+        strict: pargs.contains("--strict"),
+        warn: pargs.contains(["-w", "--warn"]),
+        ignore_missing: pargs.contains("--ignore-missing"),
+        detect_algorithm: algo_str.is_none() && encoding_str.is_none(),
+        prefer: pargs
+            .opt_value_from_fn("--prefer", parse_hash_family)?
+            .unwrap_or_default(),
+    };
+
+    // --status silences everything, including warnings
+    let mut verbosity = parse_verbosity(&mut pargs);
+    if check_options.status {
+        verbosity = Verbosity::Quiet;
+    }
+
+    // build the config struct
+    let mut config = ConfigSettings::new(
+        verbosity,
+        pargs.contains(["-x", "--exclude-filenames"]),
+        pargs.contains(["-s", "--single-thread"]) || jobs == Some(1),
+        pargs.contains(["-c", "--case-sensitive"]),
+        algo,
+        encoding,
+        pargs.opt_value_from_str(["-l", "--limit"])?,
+    );
+    config.set_jobs(jobs);
+    config.set_crc_spec(crc_spec);
+    config.set_block_size(
+        pargs
+            .opt_value_from_fn("--block-size", parse_size)?
+            .map(usize::try_from)
+            .transpose()?,
+    );
+    if let Some(size) = config.block_size {
+        if size == 0 {
+            return Err(anyhow::anyhow!("--block-size must be greater than zero"));
+        }
+        if !matches!(
+            algo,
+            HashAlgorithm::ParallelHash128 | HashAlgorithm::ParallelHash256
+        ) {
+            return Err(anyhow::anyhow!(
+                "--block-size can only be used with ParallelHash"
+            ));
+        }
+    }
+    config.set_key(
+        pargs
+            .opt_value_from_str::<_, String>("--key")?
+            .map(String::into_bytes),
+    );
+    config.set_customization(
+        pargs
+            .opt_value_from_str::<_, String>("--customization")?
+            .map(String::into_bytes),
+    );
+    let kmac = matches!(algo, HashAlgorithm::Kmac128 | HashAlgorithm::Kmac256);
+    if kmac && config.key.is_none() {
+        return Err(anyhow::anyhow!("KMAC needs a key, given with --key"));
+    }
+    if !kmac && config.key.is_some() {
+        return Err(anyhow::anyhow!("--key can only be used with KMAC"));
+    }
+    if config.customization.is_some()
+        && !kmac
+        && !matches!(
+            algo,
+            HashAlgorithm::ParallelHash128 | HashAlgorithm::ParallelHash256
+        )
+    {
+        return Err(anyhow::anyhow!(
+            "--customization can only be used with KMAC or ParallelHash"
+        ));
+    }
+    config.set_extra_encodings(extra_encodings.unwrap_or_default());
+    if !config.extra_encodings.is_empty() {
+        if algo == HashAlgorithm::CRC32 {
+            return Err(anyhow::anyhow!(
+                "CRC32 can only be output as a 32-bit integer"
+            ));
+        }
+        if config.extra_encodings.contains(&OutputEncoding::U32) {
+            return Err(anyhow::anyhow!(
+                "Encoding can be: Hex, Base64, Base32. Default is Hex"
+            ));
+        }
+    }
+    config.set_limit_bytes(pargs.opt_value_from_fn("--limit-bytes", parse_size)?);
+    if config.limit_bytes == Some(0) {
+        return Err(anyhow::anyhow!("--limit-bytes must be greater than zero"));
+    }
+    config.set_max_open(pargs.opt_value_from_str("--max-open")?);
+    if config.max_open == Some(0) {
+        return Err(anyhow::anyhow!("--max-open must be greater than zero"));
+    }
+    config.set_zip(pargs.contains(["-z", "--zip"]));
+    config.set_combine(pargs.contains("--combine"));
+    config.set_stats(pargs.contains("--stats"));
+    config.set_notify(pargs.contains("--notify"));
+    if config.notify && !cfg!(feature = "notify") {
+        return Err(anyhow::anyhow!(
+            "--notify needs the notify feature, which this build leaves out"
+        ));
+    }
+    config.set_sign(pargs.contains("--sign"));
+    config.set_secret_key(pargs.opt_value_from_str("--secret-key")?);
+    if config.sign != config.secret_key.is_some() {
+        return Err(anyhow::anyhow!(
+            "--sign and --secret-key must be given together"
+        ));
+    }
+    config.set_verify_signature(pargs.contains("--verify-signature"));
+    config.set_public_key(pargs.opt_value_from_str("--public-key")?);
+    if config.verify_signature != config.public_key.is_some() {
+        return Err(anyhow::anyhow!(
+            "--verify-signature and --public-key must be given together"
+        ));
+    }
+    config.set_fail_fast(pargs.contains("--fail-fast"));
+    config.set_ordered(pargs.contains("--ordered"));
+    config.set_hdd_mode(pargs.contains("--hdd-mode"));
+    if config.hdd_mode && config.combine {
+        return Err(anyhow::anyhow!("--hdd-mode cannot be used with --combine"));
+    }
+    config.set_sort(pargs.opt_value_from_fn("--sort", parse_sort_order)?);
+    if config.sort.is_some() && config.ordered {
+        return Err(anyhow::anyhow!("--sort cannot be used with --ordered"));
+    }
+    config.set_group(pargs.contains("--group"));
+    if config.group && (config.sort.is_some() || config.ordered) {
+        return Err(anyhow::anyhow!(
+            "--group cannot be used with --sort or --ordered"
+        ));
+    }
+    config.set_emit_script(pargs.opt_value_from_fn("--emit-script", parse_script_kind)?);
+    if config.emit_script.is_some() && (config.group || config.sort.is_some() || config.ordered) {
+        return Err(anyhow::anyhow!(
+            "--emit-script cannot be used with --group, --sort or --ordered"
+        ));
+    }
+    // the script deletes or replaces files on the strength of matching hashes alone, so they must be ones that
+    // can't match by accident or on purpose
+    if config.emit_script.is_some() && !config.algorithm.is_cryptographic() {
+        return Err(anyhow::anyhow!(
+            "--emit-script needs a cryptographic hash, but {} is not",
+            config.algorithm.name()
+        ));
+    }
+    if config.emit_script == Some(ScriptKind::Reflink)
+        && !cfg!(any(target_os = "linux", target_os = "macos"))
+    {
+        return Err(anyhow::anyhow!(
+            "Reflink scripts can only be written on Linux and macOS"
+        ));
+    }
+    config.set_timeout(pargs.opt_value_from_fn("--timeout", parse_duration)?);
+    if config.timeout.is_some() && cfg!(target_family = "wasm") {
+        return Err(anyhow::anyhow!(
+            "--timeout needs threads, which are not available on WebAssembly"
+        ));
+    }
+    if config.timeout.is_some() && (config.zip || config.combine || config.copy_to.is_some()) {
+        return Err(anyhow::anyhow!(
+            "--timeout cannot be used with --zip, --combine or --copy-to"
+        ));
+    }
+    config.set_timing(pargs.contains("--timing"));
+    config.set_show_size(pargs.contains("--show-size"));
+    config.set_show_mtime(pargs.contains("--show-mtime"));
+    config.set_with_metadata(pargs.contains("--with-metadata"));
+    config.set_clipboard(pargs.contains("--clipboard"));
+    if config.clipboard && !cfg!(feature = "clipboard") {
+        return Err(anyhow::anyhow!(
+            "--clipboard needs the clipboard feature, which this build leaves out"
+        ));
+    }
+    config.set_qr(pargs.contains("--qr"));
+    let mut progress_mode = pargs
+        .opt_value_from_fn("--progress", parse_progress_mode)?
+        .unwrap_or(ProgressMode::Auto);
+    if pargs.contains(["-n", "--no-progress"]) {
+        progress_mode = ProgressMode::Never;
+    }
+    if progress_mode == ProgressMode::Always && !cfg!(feature = "progress") {
+        return Err(anyhow::anyhow!(
+            "--progress always needs the progress feature, which this build leaves out"
+        ));
+    }
+    let prescan = pargs.contains("--prescan");
+    if prescan && progress_mode == ProgressMode::Never {
+        return Err(anyhow::anyhow!(
+            "--prescan cannot be used without a progress bar"
+        ));
+    }
+    config.set_progress(progress_mode, prescan);
+    config.set_color(
+        pargs
+            .opt_value_from_fn("--color", parse_color_mode)?
+            .unwrap_or(ColorMode::Auto),
+    );
+
+    if pargs.contains("--check") {
+        config.set_check(Some(check_options));
+    } else if check_options.status
+        || check_options.strict
+        || check_options.warn
+        || check_options.ignore_missing
+    {
+        return Err(anyhow::anyhow!(
+            "--status, --strict, --warn and --ignore-missing can only be used with --check"
+        ));
+    }
+    if let Some(to) = pargs.opt_value_from_fn("--convert", parse_checksum_format)? {
+        if config.check.is_some() || config.zip || config.combine {
+            return Err(anyhow::anyhow!(
+                "--convert cannot be used with --check, --zip or --combine"
+            ));
+        }
+        config.set_convert(Some(ConvertOptions {
+            to,
+            algorithm: algo_str.is_some().then_some(algo),
+            prefer: check_options.prefer,
+        }));
+    }
+    if config.check.is_some() && (config.zip || config.combine) {
+        return Err(anyhow::anyhow!(
+            "--check cannot be used with --zip or --combine"
+        ));
+    }
+
+    config.set_modified(
+        pargs.opt_value_from_fn("--newer-than", parse_time)?,
+        pargs.opt_value_from_fn("--older-than", parse_time)?,
+    );
+    if config.check.is_some() && (config.newer_than.is_some() || config.older_than.is_some()) {
+        return Err(anyhow::anyhow!(
+            "--check cannot be used with --newer-than or --older-than"
+        ));
+    }
+    config.set_empty_files(
+        pargs.contains("--skip-empty"),
+        pargs.contains("--only-empty"),
+    );
+    if config.skip_empty && config.only_empty {
+        return Err(anyhow::anyhow!(
+            "--skip-empty cannot be used with --only-empty"
+        ));
+    }
+    config.set_include_pseudo_fs(pargs.contains("--include-pseudo-fs"));
+    config.set_links(
+        pargs
+            .opt_value_from_fn("--symlinks", parse_link_mode)?
+            .unwrap_or_default(),
+    );
+    config.set_sample(
+        pargs.opt_value_from_str("--sample")?,
+        pargs.opt_value_from_str("--seed")?,
+    );
+    if config.seed.is_some() && config.sample.is_none() {
+        return Err(anyhow::anyhow!("--seed can only be used with --sample"));
+    }
+    if config.check.is_some() && config.sample.is_some() {
+        return Err(anyhow::anyhow!("--check cannot be used with --sample"));
+    }
+    if config.check.is_some() && !config.extra_encodings.is_empty() {
+        return Err(anyhow::anyhow!("--check takes a single encoding"));
+    }
+    if config.check.is_some() && config.limit_bytes.is_some() {
+        return Err(anyhow::anyhow!("--check cannot be used with --limit-bytes"));
+    }
+    if config.check.is_some() && config.show_size {
+        return Err(anyhow::anyhow!("--check cannot be used with --show-size"));
+    }
+    if config.check.is_some() && config.show_mtime {
+        return Err(anyhow::anyhow!("--check cannot be used with --show-mtime"));
+    }
+    if config.check.is_some() && config.with_metadata {
+        return Err(anyhow::anyhow!(
+            "--check cannot be used with --with-metadata"
+        ));
+    }
+    if config.check.is_some() && (config.clipboard || config.qr) {
+        return Err(anyhow::anyhow!(
+            "--check cannot be used with --clipboard or --qr"
+        ));
+    }
+    if config.check.is_some() && (config.skip_empty || config.only_empty) {
+        return Err(anyhow::anyhow!(
+            "--check cannot be used with --skip-empty or --only-empty"
+        ));
+    }
+
+    config.set_copy_to(pargs.opt_value_from_str("--copy-to")?);
+    if config.check.is_some() && config.copy_to.is_some() {
+        return Err(anyhow::anyhow!("--check cannot be used with --copy-to"));
+    }
+    if config.convert.is_some() && config.copy_to.is_some() {
+        return Err(anyhow::anyhow!("--convert cannot be used with --copy-to"));
+    }
+    if config.sign && (config.check.is_some() || config.convert.is_some()) {
+        return Err(anyhow::anyhow!(
+            "--sign cannot be used with --check or --convert"
+        ));
+    }
+    if config.verify_signature && config.check.is_none() {
+        return Err(anyhow::anyhow!(
+            "--verify-signature can only be used with --check"
+        ));
+    }
+    if let Some(dir) = pargs.opt_value_from_str::<_, String>("--relative-to")? {
+        if !Path::new(&dir).is_dir() {
+            return Err(anyhow::anyhow!("--relative-to must be a directory"));
+        }
+        config.set_relative_to(Some(paths::absolute(dir)?));
+    }
+    if let Some(dir) = pargs.opt_value_from_str::<_, PathBuf>("--base-dir")? {
+        if config.check.is_none() {
+            return Err(anyhow::anyhow!("--base-dir can only be used with --check"));
+        }
+        if !dir.is_dir() {
+            return Err(anyhow::anyhow!("--base-dir must be a directory"));
+        }
+        config.set_base_dir(Some(dir));
+    }
+    config.set_absolute(pargs.contains("--absolute"));
+    if config.absolute && config.relative_to.is_some() {
+        return Err(anyhow::anyhow!(
+            "--absolute cannot be used with --relative-to"
+        ));
+    }
+    config.set_log_file(pargs.opt_value_from_str("--log-file")?);
+    config.set_journal(pargs.opt_value_from_str("--journal")?);
+    config.set_events(pargs.opt_value_from_str("--events")?);
+    config.set_post_results(pargs.opt_value_from_str("--post-results")?);
+    if config.post_results.is_some() && !cfg!(feature = "webhook") {
+        return Err(anyhow::anyhow!(
+            "--post-results needs the webhook feature, which this build leaves out"
+        ));
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(url) = &config.post_results {
+        webhook::check_url(url)?;
+    }
+    config.set_per_dir_sums(pargs.opt_value_from_str("--per-dir-sums")?);
+    if config.per_dir_sums.is_some()
+        && (config.zip || config.combine || config.check.is_some() || config.copy_to.is_some())
+    {
+        return Err(anyhow::anyhow!(
+            "--per-dir-sums cannot be used with --zip, --combine, --check or --copy-to"
+        ));
+    }
+    if config.journal.is_some() && config.combine {
+        return Err(anyhow::anyhow!("--journal cannot be used with --combine"));
+    }
+    config.set_log_format(
+        pargs
+            .opt_value_from_fn("--log-format", parse_log_format)?
+            .unwrap_or(LogFormat::Text),
+    );
+
+    // options controlling how files are read
+    let buffer_size = match pargs.opt_value_from_fn("--buffer-size", parse_size)? {
+        None => DEFAULT_BUFFER_SIZE,
+        Some(0) => return Err(anyhow::anyhow!("--buffer-size must be greater than zero")),
+        Some(size) => usize::try_from(size)?,
+    };
+    let use_mmap = pargs.contains("--mmap");
+    if use_mmap && cfg!(target_family = "wasm") {
+        return Err(anyhow::anyhow!("--mmap is not available on WebAssembly"));
+    }
+    let mmap_threshold: Option<u64> = pargs.opt_value_from_fn("--mmap-threshold", parse_size)?;
+    let io_uring = pargs.contains("--io-uring");
+    if io_uring && !cfg!(target_os = "linux") {
+        return Err(anyhow::anyhow!("--io-uring is only available on Linux"));
+    }
+    if io_uring && use_mmap {
+        return Err(anyhow::anyhow!("--io-uring cannot be used with --mmap"));
+    }
+
+    let direct_io = pargs.contains("--direct-io");
+    if direct_io && !cfg!(any(windows, target_os = "linux")) {
+        return Err(anyhow::anyhow!(
+            "--direct-io is only available on Windows and Linux"
+        ));
+    }
+    if direct_io && (use_mmap || io_uring) {
+        return Err(anyhow::anyhow!(
+            "--direct-io cannot be used with --mmap or --io-uring"
+        ));
+    }
+
+    let io_threads: Option<usize> = pargs.opt_value_from_str("--io-threads")?;
+    if io_threads == Some(0) {
+        return Err(anyhow::anyhow!("--io-threads must be greater than zero"));
+    }
+    let device_readers: Option<usize> = pargs.opt_value_from_str("--device-readers")?;
+    if device_readers == Some(0) {
         return Err(anyhow::anyhow!(
-            "Algorithm can be: CRC32, MD5, SHA1, SHA2 / SHA2-256 / SHA-256, SHA2-224, SHA2-384, SHA2-512, SHA3 / SHA3-256, SHA3-384, SHA3-512, WHIRLPOOL, BLAKE2S-256, BLAKE2B-512. Default is {DEFAULT_HASH:?}",
+            "--device-readers must be greater than zero"
         ));
     }
 
-    // get output encoding as string and parse it
-    let encoding_str: Option<String> = pargs.opt_value_from_str(["-e", "--encoding"])?;
-    let encoding = parse_hash_encoding(encoding_str.as_ref());
+    let throttle = pargs.opt_value_from_fn("--throttle", parse_size)?;
+    if throttle == Some(0) {
+        return Err(anyhow::anyhow!("--throttle must be greater than zero"));
+    }
+
+    let max_in_memory = pargs.opt_value_from_fn("--max-in-memory", parse_size)?;
+    let memory_budget = match pargs.opt_value_from_fn("--memory-budget", parse_size)? {
+        None => None,
+        Some(0) => return Err(anyhow::anyhow!("--memory-budget must be greater than zero")),
+        Some(size) => Some(usize::try_from(size)?),
+    };
 
-    if encoding.is_err() {
+    let drop_cache = pargs.contains("--drop-cache");
+    if drop_cache && !cfg!(target_os = "linux") {
+        return Err(anyhow::anyhow!("--drop-cache is only available on Linux"));
+    }
+    if drop_cache && (use_mmap || io_uring || direct_io) {
         return Err(anyhow::anyhow!(
-            "Encoding can be: Hex, Base64, Base32. Default is Hex",
+            "--drop-cache cannot be used with --mmap, --io-uring or --direct-io"
         ));
     }
 
-    let algo = algo.unwrap(); // unwrap the algorithm
-
-    // unwrap, and properly assign the default encoding
-    let encoding = {
-        let mut e = encoding.unwrap();
+    config.set_io(IoOptions {
+        buffer_size,
+        mmap_threshold: use_mmap.then(|| mmap_threshold.unwrap_or(DEFAULT_MMAP_THRESHOLD)),
+        io_uring,
+        direct_io,
+        drop_cache,
+        io_threads,
+        device_readers,
+        throttle,
+        max_in_memory,
+        memory_budget,
+    });
 
-        if e == OutputEncoding::Unspecified {
-            e = if algo == HashAlgorithm::CRC32 {
-                OutputEncoding::U32 // default for CRC32
-            } else {
-                OutputEncoding::Hex // default for everything else
-            };
-        }
+    let bench = pargs.contains("--bench");
+    config.set_bench(bench, pargs.opt_value_from_fn("--size", parse_size)?);
 
-        // return the encoding, to be assigned to the variable
-        e
-    };
+    config.set_file_threads(pargs.opt_value_from_str("--file-threads")?);
 
-    assert!(
-        (algo == HashAlgorithm::CRC32 && encoding == OutputEncoding::U32)
-            || (algo != HashAlgorithm::CRC32 && encoding != OutputEncoding::U32),
-        "CRC32 can only be output as U32, and other algorithms cannot be output as U32"
-    );
+    if config.file_threads.is_some() {
+        if !cfg!(feature = "parallel") {
+            return Err(anyhow::anyhow!(
+                "--file-threads needs the parallel feature, which this build leaves out"
+            ));
+        }
+        if config.algorithm != HashAlgorithm::Blake3 {
+            return Err(anyhow::anyhow!(
+                "--file-threads is only supported for BLAKE3"
+            ));
+        }
+        if config.file_threads == Some(0) {
+            return Err(anyhow::anyhow!("--file-threads must be greater than zero"));
+        }
+        if config.combine || config.zip || config.copy_to.is_some() {
+            return Err(anyhow::anyhow!(
+                "--file-threads cannot be used with --combine, --zip or --copy-to"
+            ));
+        }
+    }
 
-    // build the config struct
-    let mut config = ConfigSettings::new(
-        pargs.contains(["-d", "--debug"]),
-        pargs.contains(["-x", "--exclude-filenames"]),
-        pargs.contains(["-s", "--single-thread"]),
-        pargs.contains(["-c", "--case-sensitive"]),
-        algo,
-        encoding,
-        pargs.opt_value_from_str(["-l", "--limit"])?,
+    if config.combine && config.zip {
+        return Err(anyhow::anyhow!("--combine cannot be used with --zip"));
+    }
+    if config.copy_to.is_some() && (config.combine || config.zip) {
+        return Err(anyhow::anyhow!(
+            "--copy-to cannot be used with --combine or --zip"
+        ));
+    }
+    if config.emit_script.is_some()
+        && (config.check.is_some() || config.zip || config.combine || config.copy_to.is_some())
+    {
+        return Err(anyhow::anyhow!(
+            "--emit-script cannot be used with --check, --zip, --combine or --copy-to"
+        ));
+    }
+    config.set_tui(pargs.contains("--tui"));
+    if config.tui && !cfg!(feature = "tui") {
+        return Err(anyhow::anyhow!(
+            "--tui needs the tui feature, which this build leaves out"
+        ));
+    }
+    if config.tui
+        && (config.check.is_some() || config.combine || config.ordered || config.events.is_some())
+    {
+        return Err(anyhow::anyhow!(
+            "--tui cannot be used with --check, --combine, --ordered or --events"
+        ));
+    }
+    config.set_result_format(
+        pargs
+            .opt_value_from_fn("--format", parse_result_format)?
+            .unwrap_or(ResultFormat::Text),
     );
+    if config.result_format == ResultFormat::Canonical {
+        if config.check.is_some()
+            || config.convert.is_some()
+            || config.combine
+            || config.exclude_fn
+            || config.absolute
+        {
+            return Err(anyhow::anyhow!(
+                "--format canonical cannot be used with --check, --convert, --combine, -x or --absolute"
+            ));
+        }
+        if config.sort.is_some()
+            || config.group
+            || config.emit_script.is_some()
+            || config.tui
+            || config.ordered
+        {
+            return Err(anyhow::anyhow!(
+                "--format canonical cannot be used with --sort, --group, --emit-script, --tui or --ordered"
+            ));
+        }
+        // only the hash and path, so nothing that changes from one scan to the next
+        if config.show_size
+            || config.show_mtime
+            || config.with_metadata
+            || config.timing
+            || !config.extra_encodings.is_empty()
+        {
+            return Err(anyhow::anyhow!(
+                "--format canonical cannot be used with --show-size, --show-mtime, --with-metadata, --timing or several encodings"
+            ));
+        }
+    }
 
     // Check for unused arguments, and error out if there are any beginning with a dash
     // anything else might legitimately be a path, so we'll check that later
@@ -196,11 +1228,19 @@ fn process_command_line(mut pargs: Arguments) -> anyhow::Result<ConfigSettings>
     // add the supplied path to config object
     config.set_supplied_path(supplied_path);
 
+    if let Some(dest_dir) = &config.copy_to {
+        if copier::is_scan_dir(&config, dest_dir) {
+            return Err(anyhow::anyhow!(
+                "--copy-to cannot be the directory being hashed"
+            ));
+        }
+    }
+
     Ok(config)
 }
 
 /// read from standard input and return a vector of strings
-fn get_paths_from_stdin(config: &ConfigSettings) -> anyhow::Result<Vec<String>> {
+fn get_paths_from_stdin() -> anyhow::Result<Vec<String>> {
     let stdin = io::stdin();
     let mut lines = Vec::with_capacity(20);
 
@@ -209,8 +1249,8 @@ fn get_paths_from_stdin(config: &ConfigSettings) -> anyhow::Result<Vec<String>>
             Ok(line) => {
                 if file_exists(&line) {
                     lines.push(line);
-                } else if config.debug_mode {
-                    eprintln!("Not a file: {line}");
+                } else {
+                    tracing::info!("Not a file: {line}");
                 }
             }
 
@@ -222,21 +1262,47 @@ fn get_paths_from_stdin(config: &ConfigSettings) -> anyhow::Result<Vec<String>>
     Ok(lines)
 }
 
-/// function to take a glob and return a vector of path strings
+/// function to take a glob and return a vector of path strings. Braces are expanded first, and a file matched
+/// by more than one of the patterns they make is only listed once
+#[cfg(feature = "glob")]
 fn get_paths_matching_glob(config: &ConfigSettings) -> anyhow::Result<Vec<String>> {
+    // we've already checked config.supplied_path is not None
+    //assert!(config.supplied_path.is_some());
+
+    // have to clone to unwrap the string, because the struct is borrowed
+    let pattern = config.supplied_path.clone().unwrap();
+
+    let patterns = braces::expand(&pattern)?;
+    if let [pattern] = &patterns[..] {
+        return paths_matching_pattern(config, pattern);
+    }
+    let mut seen = HashSet::new();
+    let mut paths = Vec::new();
+    for pattern in &patterns {
+        for path in paths_matching_pattern(config, pattern)? {
+            if seen.insert(path.clone()) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// the files matching one pattern, without braces
+#[cfg(feature = "glob")]
+fn paths_matching_pattern(config: &ConfigSettings, pattern: &str) -> anyhow::Result<Vec<String>> {
     let glob_settings = glob::MatchOptions {
         case_sensitive: config.case_sensitive,
         require_literal_separator: false,
         require_literal_leading_dot: false,
     };
 
-    // we've already checked config.supplied_path is not None
-    //assert!(config.supplied_path.is_some());
-
-    // have to clone to unwrap the string, because the struct is borrowed
-    let pattern = config.supplied_path.clone().unwrap();
+    // recursive patterns are walked here, so it can choose which directories to go into
+    if pattern.contains("**") {
+        return walk::matching_files(config, pattern);
+    }
 
-    let temp_paths = glob::glob_with(&pattern, glob_settings)?;
+    let temp_paths = glob::glob_with(pattern, glob_settings)?;
 
     // filter out non-files
     let path_globs: Vec<GlobResult> = temp_paths
@@ -252,95 +1318,539 @@ fn get_paths_matching_glob(config: &ConfigSettings) -> anyhow::Result<Vec<String
     Ok(paths)
 }
 
-/// output all file hashes matching a pattern, directly to stdout. Single-threaded
-fn file_hashes_st<S>(config: &ConfigSettings, paths: &[S])
-where
-    S: AsRef<str> + Display,
-{
-    if config.debug_mode {
-        eprintln!("Single-threaded mode");
-        eprintln!("Algorithm: {:?}", config.algorithm);
+/// without glob support the path is taken literally, and must be a file
+#[cfg(not(feature = "glob"))]
+fn get_paths_matching_glob(config: &ConfigSettings) -> anyhow::Result<Vec<String>> {
+    let path = config.supplied_path.clone().unwrap();
+    if Path::new(&path).is_file() {
+        Ok(vec![path])
+    } else {
+        Ok(Vec::new())
     }
+}
 
-    for pathstr in paths {
-        let file_hash = call_hasher(config.algorithm, config.encoding, pathstr);
+/// hash every item, printing each result as it goes. Multithreaded unless asked otherwise
+fn file_hashes<T: AsRef<Path> + Sync>(
+    config: &ConfigSettings,
+    items: &[T],
+    hash_one: impl Fn(&T) + Sync,
+) {
+    // with --hdd-mode, the items of each device in the order they are on the disk
+    let order = config.hdd_mode.then(|| hdd::plan(items));
 
-        match file_hash {
-            Ok(basic_hash) => {
-                if config.exclude_fn {
-                    println!("{basic_hash}");
-                } else {
-                    println!("{basic_hash} {pathstr}");
+    #[cfg(feature = "parallel")]
+    if !config.single_thread && items.len() > 1 {
+        // multithreaded
+        file_hashes_mt(config, items, order.as_deref(), hash_one);
+        return;
+    }
+
+    // asked for single thread, only one path given, or built without the parallel feature
+    file_hashes_st(config, items, order.as_deref(), hash_one);
+}
+
+/// output all file hashes, directly to stdout. Single-threaded
+fn file_hashes_st<T>(
+    config: &ConfigSettings,
+    items: &[T],
+    order: Option<&[Vec<usize>]>,
+    hash_one: impl Fn(&T),
+) {
+    tracing::debug!("Single-threaded mode");
+    tracing::debug!("Algorithm: {:?}", config.algorithm);
+
+    let sequence: Vec<usize> = order.map_or_else(|| (0..items.len()).collect(), <[_]>::concat);
+    // the positions of the items to hash, in turn, until the run is stopped
+    let in_turn = || sequence.iter().copied().take_while(|_| !stats::aborted());
+
+    if holds_results(config) {
+        print_held(
+            config,
+            output::capture(|| in_turn().for_each(|index| hash_one(&items[index]))),
+        );
+    } else if config.ordered && order.is_some() {
+        // read in disk order, then printed in input order once every item is done
+        let mut held: Vec<_> = in_turn()
+            .map(|index| (index, output::capture(|| hash_one(&items[index]))))
+            .collect();
+        held.sort_by_key(|&(index, _)| index);
+        for line in held.into_iter().flat_map(|(_, lines)| lines) {
+            output::print_line(line.text);
+        }
+    } else {
+        in_turn().for_each(|index| hash_one(&items[index]));
+    }
+}
+
+/// output all file hashes, directly to stdout. Multithreaded version
+#[cfg(feature = "parallel")]
+fn file_hashes_mt<T: Sync>(
+    config: &ConfigSettings,
+    items: &[T],
+    order: Option<&[Vec<usize>]>,
+    hash_one: impl Fn(&T) + Sync,
+) {
+    tracing::debug!("Multi-threaded mode");
+    tracing::debug!("Algorithm: {:?}", config.algorithm);
+
+    // process the items in parallel, with the results written out in batches by a single thread.
+    // Once a failure stops the run, the remaining tasks are cancelled
+    output::batched(|| {
+        if holds_results(config) {
+            // everything is held back, then sorted or grouped once the run is finished
+            let (tx, rx) = mpsc::channel();
+            for_each_parallel(items, order, |_, item| {
+                let _ = tx.send(output::capture(|| hash_one(item)));
+                keep_going()
+            });
+            drop(tx);
+            print_held(config, rx.into_iter().flatten().collect());
+        } else if config.ordered {
+            // results are tagged with their position, and another thread puts them back in order
+            let (tx, rx) = mpsc::channel();
+            thread::scope(|scope| {
+                scope.spawn(|| output::print_in_order(rx));
+                for_each_parallel(items, order, |index, item| {
+                    let lines = output::capture(|| hash_one(item));
+                    let _ = tx.send((index, lines));
+                    keep_going()
+                });
+                drop(tx);
+            });
+        } else {
+            for_each_parallel(items, order, |_, item| {
+                hash_one(item);
+                keep_going()
+            });
+        }
+    });
+}
+
+/// run the task on every item and its position, in parallel, until it fails. Given an order, each group is
+/// taken by one thread from start to finish, so a disk only has one reader
+#[cfg(feature = "parallel")]
+fn for_each_parallel<T: Sync>(
+    items: &[T],
+    order: Option<&[Vec<usize>]>,
+    task: impl Fn(usize, &T) -> Result<(), ()> + Sync,
+) {
+    let _ = match order {
+        Some(groups) => groups.par_iter().try_for_each(|group| {
+            group
+                .iter()
+                .try_for_each(|&index| task(index, &items[index]))
+        }),
+        None => items
+            .par_iter()
+            .enumerate()
+            .try_for_each(|(index, item)| task(index, item)),
+    };
+}
+
+/// true if results are held back until the end of the run, to be sorted, grouped or made into a script or a
+/// canonical manifest, or to keep them from being drawn over by the browser
+fn holds_results(config: &ConfigSettings) -> bool {
+    config.sort.is_some()
+        || config.group
+        || config.emit_script.is_some()
+        || config.tui
+        || config.result_format == ResultFormat::Canonical
+}
+
+/// print the results held back until the end of the run, grouped, sorted, as a script or a manifest as asked
+fn print_held(config: &ConfigSettings, held: Vec<output::HeldLine>) {
+    // once the browser has been closed, so they aren't printed behind it
+    #[cfg(feature = "tui")]
+    if config.tui {
+        tui::finish();
+    }
+
+    if let Some(kind) = config.emit_script {
+        dedupe::print_script(held, kind);
+    } else if config.result_format == ResultFormat::Canonical {
+        canonical::print_manifest(config, held);
+    } else if config.group {
+        output::print_grouped(held, !config.exclude_fn);
+    } else {
+        output::print_sorted(held, config.sort.unwrap_or(SortOrder::Path));
+    }
+}
+
+/// stop rayon starting more tasks once the run has been stopped
+#[cfg(feature = "parallel")]
+fn keep_going() -> Result<(), ()> {
+    if stats::aborted() {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// output a single hash of all files concatenated together, in sorted path order
+fn file_hash_combined(config: &ConfigSettings, paths: &mut [String]) {
+    // sort so the result does not depend on glob or stdin ordering, eg file.part1, file.part2...
+    paths.sort();
+
+    tracing::debug!("Combined mode, hashing as one stream: {paths:?}");
+    tracing::debug!("Algorithm: {:?}", config.algorithm);
+
+    let sources: Vec<FileSource> = paths
+        .iter()
+        .map(|path| FileSource {
+            path,
+            io: &config.io,
+        })
+        .collect();
+
+    observer::notify(Event::FileStarted { path: "(combined)" });
+    let (combined_hash, timing) =
+        stats::timed(|| call_hasher(config.algorithm, config.encoding, sources));
+
+    let _span = tracing::debug_span!("output").entered();
+    progress::suspend(|| match combined_hash {
+        Ok(basic_hash) => {
+            stats::files_hashed(paths.len());
+            observer::notify(Event::FileFinished {
+                path: "(combined)",
+                hash: &basic_hash,
+                timing: &timing,
+            });
+            logfile::record(format_args!("OK {basic_hash} {timing} (combined)"));
+            if config.clipboard || config.qr {
+                output::note_hash(&basic_hash);
+            }
+            output::print_line(hash_column(config, None, &basic_hash, &timing));
+        }
+        Err(e) => {
+            stats::file_failed("(combined)", &e);
+            logfile::record(format_args!("ERROR (combined): {e:#}"));
+            tracing::error!(error = %format!("{e:#}"), "Could not hash the files combined");
+        }
+    });
+}
+
+/// hash a single path and print the result. With --zip, zip archives are expanded into one line per entry
+fn hash_and_print(config: &ConfigSettings, pathstr: &str) {
+    let _span = tracing::debug_span!("file", path = pathstr).entered();
+    // held until this path is finished with, and its file handles closed
+    let _permit = limits::open_permit();
+    observer::notify(Event::FileStarted { path: pathstr });
+
+    let succeeded = if let Some(dest_dir) = &config.copy_to {
+        copy_and_print(config, pathstr, dest_dir)
+    } else if config.zip && archive::is_zip(pathstr) {
+        match archive::hash_zip_entries(config, pathstr) {
+            Ok(entries) => {
+                let mut all_hashed = true;
+                for (entry_path, entry_hash, timing) in entries {
+                    if stats::aborted() {
+                        all_hashed = false;
+                        break;
+                    }
+                    all_hashed &= print_hash_result(config, &entry_path, entry_hash, &timing);
                 }
+                all_hashed
             }
-            Err(e) => eprintln!("'{pathstr}' file err {e:?}"),
+            Err(e) => {
+                stats::file_failed(pathstr, &e);
+                logfile::record(format_args!("ERROR {pathstr}: {e:#}"));
+                tracing::error!(path = pathstr, error = %format!("{e:#}"), "Could not read the zip archive");
+                false
+            }
+        }
+    } else {
+        let (file_hash, timing) = match config.timeout {
+            Some(timeout) => hash_with_timeout(config, pathstr, timeout),
+            None => stats::timed(|| {
+                hash_file(
+                    config.algorithm,
+                    config.encoding,
+                    &config.io,
+                    config.file_threads,
+                    pathstr,
+                )
+            }),
+        };
+        print_hash_result(config, pathstr, file_hash, &timing)
+    };
+
+    // failed files are left out of the journal, so they are tried again next time
+    if succeeded && !output::closed() {
+        if let Err(e) = journal::record(pathstr) {
+            tracing::warn!("Could not write to the journal: {e}");
         }
     }
+
+    progress::file_done();
+}
+
+/// hash a single file on disk, reading it as the I/O options say
+fn hash_file(
+    algorithm: HashAlgorithm,
+    encoding: OutputEncoding,
+    io: &IoOptions,
+    file_threads: Option<usize>,
+    pathstr: &str,
+) -> anyhow::Result<BasicHash> {
+    match file_threads {
+        #[cfg(feature = "parallel")]
+        Some(threads) => blake3_digest::hash_file_multithreaded(pathstr, threads, encoding),
+        _ => call_hasher(algorithm, encoding, FileSource { path: pathstr, io }),
+    }
 }
 
-/// output all file hashes matching a pattern, directly to stdout. Multithreaded version
-fn file_hashes_mt<S>(config: &ConfigSettings, paths: &[S])
-where
-    S: AsRef<str> + Sync + Display,
-{
-    if config.debug_mode {
-        eprintln!("Multi-threaded mode");
-        eprintln!("Algorithm: {:?}", config.algorithm);
+/// hash a file on its own thread, giving up if it takes longer than the timeout. A read blocked on a hung
+/// mount can't be cancelled, so the thread is left to finish (or not) in the background
+fn hash_with_timeout(
+    config: &ConfigSettings,
+    pathstr: &str,
+    timeout: Duration,
+) -> (anyhow::Result<BasicHash>, FileTiming) {
+    let (algorithm, encoding, io, file_threads) = (
+        config.algorithm,
+        config.encoding,
+        config.io,
+        config.file_threads,
+    );
+    let path = pathstr.to_string();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let result = stats::timed(|| hash_file(algorithm, encoding, &io, file_threads, &path));
+        // nobody is listening if we've already given up
+        let _ = tx.send(result);
+    });
+
+    let failed = |message: String| {
+        let timing = FileTiming {
+            bytes: 0,
+            elapsed: timeout,
+        };
+        (Err(anyhow::anyhow!(message)), timing)
+    };
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => failed(format!(
+            "Timed out after {}",
+            humantime::format_duration(timeout)
+        )),
+        Err(RecvTimeoutError::Disconnected) => failed("Hashing thread panicked".to_string()),
     }
+}
 
-    // process the paths in parallel
-    paths.par_iter().for_each(|pathstr| {
-        let file_hash = call_hasher(config.algorithm, config.encoding, pathstr);
+/// copy a file to the destination directory, and print the hash and verification status of the copy
+fn copy_and_print(config: &ConfigSettings, pathstr: &str, dest_dir: &str) -> bool {
+    let (copy_result, timing) = stats::timed(|| copier::copy_and_verify(config, pathstr, dest_dir));
+
+    // hide the progress bar while printing, so it doesn't get mixed into the output
+    let _span = tracing::debug_span!("output").entered();
+    progress::suspend(|| match copy_result {
+        Ok(copied) => {
+            if copied.verified() {
+                stats::file_hashed(pathstr, &copied.source_hash, &timing);
+            } else {
+                stats::file_mismatched(pathstr, "copy failed verification");
+            }
+            let status = if copied.verified() { "OK" } else { "FAILED" };
+            logfile::record(format_args!(
+                "{status} {} {timing} {pathstr} -> {}",
+                copied.source_hash, copied.dest_path
+            ));
+            let hash = hash_column(config, Some(pathstr), &copied.source_hash, &timing);
+            let status = color::status(status, copied.verified());
+            let shown_path = paths::display_path(config, pathstr);
+            if config.exclude_fn {
+                output::print_result(
+                    &shown_path,
+                    &copied.source_hash,
+                    &timing,
+                    format_args!("{hash} {status}"),
+                );
+            } else {
+                output::print_result(
+                    &shown_path,
+                    &copied.source_hash,
+                    &timing,
+                    format_args!(
+                        "{hash} {} -> {} {status}",
+                        color::path(&shown_path),
+                        color::path(paths::display_path(config, &copied.dest_path))
+                    ),
+                );
+            }
+            if !copied.verified() {
+                tracing::warn!(
+                    "'{pathstr}' copy verification failed, destination hash is {}",
+                    copied.dest_hash
+                );
+            }
+            copied.verified()
+        }
+        Err(e) => {
+            stats::file_failed(pathstr, &e);
+            logfile::record(format_args!("ERROR {pathstr}: {e:#}"));
+            tracing::error!(path = pathstr, error = %format!("{e:#}"), "Could not copy the file");
+            false
+        }
+    })
+}
 
-        match file_hash {
-            Ok(hash) => {
-                if config.exclude_fn {
-                    println!("{}", hash.0);
+/// print a single hash result to stdout, or the error to stderr. Returns true if there was a hash
+fn print_hash_result(
+    config: &ConfigSettings,
+    pathstr: &str,
+    file_hash: anyhow::Result<BasicHash>,
+    timing: &FileTiming,
+) -> bool {
+    // hide the progress bar while printing, so it doesn't get mixed into the output
+    let _span = tracing::debug_span!("output").entered();
+    progress::suspend(|| match file_hash {
+        Ok(basic_hash) => {
+            stats::file_hashed(pathstr, &basic_hash, timing);
+            logfile::record(format_args!("OK {basic_hash} {timing} {pathstr}"));
+            dirsums::record(pathstr, &basic_hash);
+            tracing::info!(
+                path = pathstr,
+                bytes = timing.bytes,
+                ms = u64::try_from(timing.elapsed.as_millis()).unwrap_or(u64::MAX),
+                "hashed"
+            );
+            if config.clipboard || config.qr {
+                output::note_hash(&basic_hash);
+            }
+            let hash = hash_column(config, Some(pathstr), &basic_hash, timing);
+            let shown_path = paths::display_path(config, pathstr);
+            if config.exclude_fn {
+                output::print_result(&shown_path, &basic_hash, timing, hash);
+            } else {
+                // where a link points goes after the path, as ls -l shows it
+                let link = if config.with_metadata {
+                    link_target(pathstr)
                 } else {
-                    println!("{} {}", hash.0, pathstr);
-                }
+                    String::new()
+                };
+                output::print_result(
+                    &shown_path,
+                    &basic_hash,
+                    timing,
+                    format_args!("{hash} {}{link}", color::path(&shown_path)),
+                );
             }
+            true
+        }
 
-            // failed to calculate the hash
-            Err(e) => eprintln!("'{pathstr}' file err {e:?}"),
+        // failed to calculate the hash
+        Err(e) => {
+            stats::file_failed(pathstr, &e);
+            logfile::record(format_args!("ERROR {pathstr}: {e:#}"));
+            tracing::error!(path = pathstr, error = %format!("{e:#}"), "Could not hash the file");
+            false
         }
-    });
+    })
+}
+
+/// the hash as printed, then the same hash in any extra encodings, followed by the size when --show-size is
+/// given, the modification time when --show-mtime is given, the permissions and owner when --with-metadata is
+/// given, and the bytes and time taken when --timing is given
+fn hash_column(
+    config: &ConfigSettings,
+    path: Option<&str>,
+    basic_hash: &BasicHash,
+    timing: &FileTiming,
+) -> String {
+    let mut column = color::hash(basic_hash).to_string();
+
+    if !config.extra_encodings.is_empty() {
+        // re-encoded from the hash just printed, so the file is only hashed once
+        if let Some(bytes) = decode_hash(basic_hash, config.encoding, config.algorithm) {
+            for &encoding in &config.extra_encodings {
+                column = format!("{column} {}", color::hash(&encode_hash(&bytes, encoding)));
+            }
+        }
+    }
+
+    if config.show_size {
+        column = format!("{column} {}", timing.bytes);
+    }
+    if config.show_mtime {
+        column = format!("{column} {}", modified_time(path));
+    }
+    if config.with_metadata {
+        column = format!("{column} {}", permissions_and_owner(path));
+    }
+    if config.timing {
+        column = format!("{column} {timing}");
+    }
+    column
+}
+
+/// the modification time of the file as RFC 3339 in UTC, or "-" when there is no file of its own (a zip entry
+/// or the combined hash) or it can't be read
+fn modified_time(path: Option<&str>) -> String {
+    path.and_then(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .map_or_else(
+            || "-".to_string(),
+            |modified| humantime::format_rfc3339_seconds(modified).to_string(),
+        )
+}
+
+/// the permissions in octal and the owner as `uid:gid`, eg `0644 1000:1000`, or "- -" when there is no file of
+/// its own or it can't be read
+#[cfg(unix)]
+fn permissions_and_owner(path: Option<&str>) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    path.and_then(|path| std::fs::metadata(path).ok())
+        .map_or_else(
+            || "- -".to_string(),
+            |m| format!("{:04o} {}:{}", m.mode() & 0o7777, m.uid(), m.gid()),
+        )
+}
+
+/// Windows has no mode bits or numeric owners, only whether the file is read-only, shown as `r` or `rw`
+#[cfg(not(unix))]
+fn permissions_and_owner(path: Option<&str>) -> String {
+    path.and_then(|path| std::fs::metadata(path).ok())
+        .map_or_else(
+            || "- -".to_string(),
+            |m| {
+                let access = if m.permissions().readonly() {
+                    "r"
+                } else {
+                    "rw"
+                };
+                format!("{access} -")
+            },
+        )
 }
 
-/// calculate the hash of a file using given algorithm
+/// " -> target" if the path is a symbolic link, otherwise nothing
+fn link_target(path: &str) -> String {
+    std::fs::read_link(path)
+        .map(|target| format!(" -> {}", target.display()))
+        .unwrap_or_default()
+}
+
+/// calculate the hash of a file (or other source) using given algorithm
 fn call_hasher(
     algo: HashAlgorithm,
     encoding: OutputEncoding,
-    path: impl AsRef<str>,
+    path: impl HashSource,
 ) -> anyhow::Result<BasicHash> {
-    // panic if algo is CRC32 and output is not U32
+    // panic if the hash is too big to output as U32
     assert!(
-        (algo == HashAlgorithm::CRC32 && encoding == OutputEncoding::U32)
-            || (algo != HashAlgorithm::CRC32 && encoding != OutputEncoding::U32),
-        "CRC32 can only be output as U32"
+        encoding != OutputEncoding::U32 || algo.digest_bits() <= 32,
+        "Only checksums of 32 bits or less can be output as U32"
     );
 
-    match algo {
-        // special case, u32 encoded
-        HashAlgorithm::CRC32 => hash_file_encoded::<crc32::Crc32>(path, OutputEncoding::U32),
-        // old algorithms
-        HashAlgorithm::MD5 => hash_file_encoded::<Md5>(path, encoding),
-        HashAlgorithm::SHA1 => hash_file_encoded::<Sha1>(path, encoding),
-        // SHA2
-        HashAlgorithm::SHA2_224 => hash_file_encoded::<Sha224>(path, encoding),
-        HashAlgorithm::SHA2_256 => hash_file_encoded::<Sha256>(path, encoding),
-        HashAlgorithm::SHA2_384 => hash_file_encoded::<Sha384>(path, encoding),
-        HashAlgorithm::SHA2_512 => hash_file_encoded::<Sha512>(path, encoding),
-        // SHA3
-        HashAlgorithm::SHA3_256 => hash_file_encoded::<Sha3_256>(path, encoding),
-        HashAlgorithm::SHA3_384 => hash_file_encoded::<Sha3_384>(path, encoding),
-        HashAlgorithm::SHA3_512 => hash_file_encoded::<Sha3_512>(path, encoding),
-        // WHIRLPOOL
-        HashAlgorithm::Whirlpool => hash_file_encoded::<Whirlpool>(path, encoding),
-        // BLAKE2
-        HashAlgorithm::Blake2S256 => hash_file_encoded::<Blake2s256>(path, encoding),
-        HashAlgorithm::Blake2B512 => hash_file_encoded::<Blake2b512>(path, encoding),
-    }
+    // CRC32 is always output as a number
+    let encoding = if algo == HashAlgorithm::CRC32 {
+        OutputEncoding::U32
+    } else {
+        encoding
+    };
+    hash_file_encoded(path, Hasher::new(algo, encoding))
 }
 
 /// convert hash algorithm string into an enum
@@ -351,6 +1861,15 @@ fn parse_hash_algorithm(algorithm: Option<&String>) -> Result<HashAlgorithm, str
     }
 }
 
+/// the encoding used when none is given
+fn default_encoding(algo: HashAlgorithm) -> OutputEncoding {
+    match algo {
+        HashAlgorithm::CRC32 => OutputEncoding::U32, // default for CRC32
+        HashAlgorithm::Tth => OutputEncoding::Base32, // as DC++ and magnet links show it
+        _ => OutputEncoding::Hex,                    // default for everything else
+    }
+}
+
 /// convert output encoding string into an enum
 fn parse_hash_encoding(encoding: Option<&String>) -> Result<OutputEncoding, strum::ParseError> {
     match encoding {
@@ -359,6 +1878,142 @@ fn parse_hash_encoding(encoding: Option<&String>) -> Result<OutputEncoding, stru
     }
 }
 
+/// parse -q, -v, -vv and -d into a verbosity level
+fn parse_verbosity(pargs: &mut Arguments) -> Verbosity {
+    // the flags can be given in any combination, so remove all of them
+    let quiet = pargs.contains(["-q", "--quiet"]);
+    let debug = pargs.contains(["-d", "--debug"]) | pargs.contains("-vv");
+    let mut verbose = 0;
+    while pargs.contains(["-v", "--verbose"]) {
+        verbose += 1;
+    }
+
+    if debug || verbose > 1 {
+        Verbosity::Debug
+    } else if verbose == 1 {
+        Verbosity::Verbose
+    } else if quiet {
+        Verbosity::Quiet
+    } else {
+        Verbosity::Normal
+    }
+}
+
+/// parse the format for messages on stderr
+fn parse_log_format(format: &str) -> anyhow::Result<LogFormat> {
+    LogFormat::from_str(format)
+        .map_err(|_| anyhow::anyhow!("Log format can be: text, json. Default is text"))
+}
+
+/// parse when to color the output
+fn parse_color_mode(mode: &str) -> anyhow::Result<ColorMode> {
+    ColorMode::from_str(mode)
+        .map_err(|_| anyhow::anyhow!("Color can be: auto, always, never. Default is auto"))
+}
+
+/// parse the format of reports such as --version
+fn parse_output_format(format: &str) -> anyhow::Result<OutputFormat> {
+    OutputFormat::from_str(format)
+        .map_err(|_| anyhow::anyhow!("Format can be: text, json. Default is text"))
+}
+
+/// parse the format of the results
+fn parse_result_format(format: &str) -> anyhow::Result<ResultFormat> {
+    ResultFormat::from_str(format)
+        .map_err(|_| anyhow::anyhow!("Format can be: text, canonical. Default is text"))
+}
+
+/// parse the format --convert writes
+fn parse_checksum_format(format: &str) -> anyhow::Result<ChecksumFormat> {
+    ChecksumFormat::from_str(format)
+        .map_err(|_| anyhow::anyhow!("Checksum format can be: gnu, bsd, hashdeep, json"))
+}
+
+/// parse when to show the progress bar
+fn parse_progress_mode(mode: &str) -> anyhow::Result<ProgressMode> {
+    ProgressMode::from_str(mode)
+        .map_err(|_| anyhow::anyhow!("Progress can be: auto, always, never. Default is auto"))
+}
+
+/// parse what to do with links for --symlinks
+fn parse_link_mode(mode: &str) -> anyhow::Result<LinkMode> {
+    LinkMode::from_str(mode)
+        .map_err(|_| anyhow::anyhow!("Symlinks can be: follow, skip, report. Default is follow"))
+}
+
+/// parse the preferred family of algorithms for --check
+fn parse_hash_family(family: &str) -> anyhow::Result<HashFamily> {
+    HashFamily::from_str(family).map_err(|_| {
+        anyhow::anyhow!("Preferred algorithms can be: sha2, sha3, blake2, blake3. Default is sha3")
+    })
+}
+
+/// parse the action for --emit-script
+fn parse_script_kind(kind: &str) -> anyhow::Result<ScriptKind> {
+    ScriptKind::from_str(kind)
+        .map_err(|_| anyhow::anyhow!("Script actions can be: hardlink, reflink, delete"))
+}
+
+/// parse the order for --sort
+fn parse_sort_order(order: &str) -> anyhow::Result<SortOrder> {
+    SortOrder::from_str(order)
+        .map_err(|_| anyhow::anyhow!("Sort order can be: path, size, hash, time"))
+}
+
+/// parse a duration, either in seconds or with units, eg "90" or "5m 30s"
+fn parse_duration(duration: &str) -> anyhow::Result<Duration> {
+    let parsed = match duration.parse::<f64>() {
+        Ok(seconds) => Duration::try_from_secs_f64(seconds)?,
+        Err(_) => humantime::parse_duration(duration)?,
+    };
+
+    if parsed.is_zero() {
+        return Err(anyhow::anyhow!("Duration must be greater than zero"));
+    }
+    Ok(parsed)
+}
+
+/// parse a point in time, either a date and time (eg "2024-06-01" or "2024-06-01 12:00:00", in UTC),
+/// or how long ago (eg "7d" or "12h")
+fn parse_time(time: &str) -> anyhow::Result<SystemTime> {
+    if let Ok(ago) = humantime::parse_duration(time) {
+        return SystemTime::now()
+            .checked_sub(ago)
+            .ok_or_else(|| anyhow::anyhow!("'{time}' is too long ago"));
+    }
+
+    let full = if time.len() == 10 {
+        format!("{time} 00:00:00") // date only, start of the day
+    } else {
+        time.to_string()
+    };
+    humantime::parse_rfc3339_weak(&full).map_err(|_| {
+        anyhow::anyhow!("Time can be a date like 2024-06-01, a date and time like '2024-06-01 12:00:00', or an age like 7d")
+    })
+}
+
+/// parse a size in bytes, with an optional K, M, G or T suffix (binary multiples), eg "64K" or "1G"
+fn parse_size(size: &str) -> anyhow::Result<u64> {
+    let upper = size.trim().to_ascii_uppercase();
+    // allow "64K", "64KB" and "64KiB" to mean the same thing
+    let digits = upper.trim_end_matches('B').trim_end_matches('I').trim_end();
+
+    let (number, multiplier) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 1024),
+        Some('M') => (&digits[..digits.len() - 1], 1024 * 1024),
+        Some('G') => (&digits[..digits.len() - 1], 1024 * 1024 * 1024),
+        Some('T') => (&digits[..digits.len() - 1], 1024 * 1024 * 1024 * 1024),
+        _ => (digits, 1),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| anyhow::anyhow!("Invalid size: {size}"))
+}
+
 /// Show help message
 fn show_help(longform: bool) {
     println!(
@@ -372,6 +2027,71 @@ fn show_help(longform: bool) {
     println!("Default algorithm is {DEFAULT_HASH:?}");
 }
 
+/// List every algorithm with its digest size, default encoding and the names -a accepts
+fn show_algorithms(format: OutputFormat) {
+    let algorithms: Vec<_> = HashAlgorithm::iter()
+        .map(|algorithm| {
+            let encoding = format!("{:?}", default_encoding(algorithm));
+            (algorithm, encoding, algorithm == DEFAULT_HASH)
+        })
+        .collect();
+
+    if format == OutputFormat::Text {
+        // columns as wide as their longest value, eg PARALLELHASH128 and Base32
+        let name_width = algorithms
+            .iter()
+            .map(|(algorithm, _, _)| algorithm.name().len())
+            .max()
+            .unwrap_or(0);
+        let bits_width = algorithms
+            .iter()
+            .map(|(algorithm, _, _)| algorithm.digest_bits().to_string().len())
+            .max()
+            .unwrap_or(0);
+        let encoding_width = algorithms
+            .iter()
+            .map(|(_, encoding, _)| encoding.len())
+            .max()
+            .unwrap_or(0);
+
+        for (algorithm, encoding, default) in algorithms {
+            println!(
+                "{:<name_width$} {:>bits_width$} bits  {encoding:<encoding_width$} {}{}{}",
+                algorithm.name(),
+                algorithm.digest_bits(),
+                algorithm.aliases().join(", "),
+                if default { " (default)" } else { "" },
+                if algorithm.is_cryptographic() {
+                    ""
+                } else {
+                    " (not cryptographic)"
+                }
+            );
+        }
+        return;
+    }
+
+    let entries: Vec<String> = algorithms
+        .into_iter()
+        .map(|(algorithm, encoding, default)| {
+            let aliases: Vec<String> = algorithm
+                .aliases()
+                .iter()
+                .map(|alias| JsonString(alias).to_string())
+                .collect();
+            format!(
+                r#"{{"name":{},"bits":{},"encoding":{},"aliases":[{}],"default":{default},"cryptographic":{}}}"#,
+                JsonString(algorithm.name()),
+                algorithm.digest_bits(),
+                JsonString(&encoding),
+                aliases.join(","),
+                algorithm.is_cryptographic()
+            )
+        })
+        .collect();
+    println!("[{}]", entries.join(","));
+}
+
 /// Check for unused arguments, and error out if there are any
 fn args_finished(args: Arguments) -> anyhow::Result<Vec<OsString>> {
     let unused = args.finish();
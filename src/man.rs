@@ -0,0 +1,107 @@
+use std::fmt::Write;
+
+use crate::classes::{HELP, VERSION};
+
+/// Exit codes and their meaning, as listed in the README
+const EXIT_CODES: [(u8, &str); 5] = [
+    (0, "Success, every file hashed"),
+    (1, "A hash did not match, eg a copy failed verification"),
+    (2, "Some files could not be read or hashed"),
+    (3, "Usage error in the command line"),
+    (4, "Fatal error, eg the log file could not be opened"),
+];
+
+/// One flag or option from the help text, eg `-a, --algorithm [algorithm]  Hash algorithm to use`
+pub struct OptionHelp<'a> {
+    pub short: Option<&'a str>,
+    pub long: &'a str,
+    pub value: Option<&'a str>,
+    pub description: &'a str,
+}
+
+/// Build a roff man page from the help text, so it never falls out of step with `--help`
+pub fn generate() -> String {
+    let mut page = String::new();
+    let version = VERSION.unwrap_or("?");
+
+    let _ = writeln!(
+        page,
+        ".TH HASH_RUST 1 \"\" \"hash_rust {version}\" \"User Commands\""
+    );
+    page.push_str(".SH NAME\nhash_rust \\- hash files using various algorithms\n");
+    page.push_str(
+        ".SH SYNOPSIS\n.B hash_rust\n[\\fIflags\\fR] [\\fIoptions\\fR] \\fIfile glob\\fR\n",
+    );
+    page.push_str(".SH DESCRIPTION\n");
+    page.push_str("Hash files matching a glob, or a list of paths read from stdin, printing one hash per file.\n");
+    page.push_str("Files are hashed in parallel unless asked otherwise.\n");
+
+    for (heading, section) in [("FLAGS", "FLAGS:"), ("OPTIONS", "OPTIONS:")] {
+        let _ = writeln!(page, ".SH {heading}");
+        for option in options(section) {
+            page.push_str(".TP\n");
+            if let Some(short) = option.short {
+                let _ = write!(page, "\\fB{}\\fR, ", escape(short));
+            }
+            let _ = write!(page, "\\fB{}\\fR", escape(option.long));
+            if let Some(value) = option.value {
+                let _ = write!(page, " \\fI{}\\fR", escape(value));
+            }
+            let _ = writeln!(page, "\n{}", escape(option.description));
+        }
+    }
+
+    page.push_str(".SH ALGORITHMS\n.nf\n");
+    if let Some((_, algorithms)) = HELP.split_once("Algorithm can be:\n") {
+        for line in algorithms.lines() {
+            let _ = writeln!(page, "{}", escape(line.trim()));
+        }
+    }
+    page.push_str(".fi\n");
+
+    page.push_str(".SH EXIT STATUS\n");
+    for (code, meaning) in EXIT_CODES {
+        let _ = writeln!(page, ".TP\n.B {code}\n{meaning}");
+    }
+    page
+}
+
+/// The flags or options listed under a heading of the help text
+pub fn options(heading: &str) -> Vec<OptionHelp<'static>> {
+    HELP.lines()
+        .skip_while(|line| line.trim() != heading)
+        .skip(1)
+        .take_while(|line| line.starts_with("    ") && !line.trim().is_empty())
+        .filter_map(parse_option)
+        .collect()
+}
+
+/// Split a help line into the option names, the value it takes and the description
+fn parse_option(line: &str) -> Option<OptionHelp<'_>> {
+    let (names, description) = line.trim().split_once("  ")?;
+    let (names, value) = match names.split_once(" [") {
+        Some((names, value)) => (names, value.strip_suffix(']')),
+        None => (names, None),
+    };
+    let (short, long) = match names.split_once(", ") {
+        Some((short, long)) => (Some(short), long),
+        None => (None, names),
+    };
+
+    Some(OptionHelp {
+        short,
+        long,
+        value,
+        description: description.trim(),
+    })
+}
+
+/// Escape text for roff: backslashes, minus signs, and dots or quotes that would start a request
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with(['.', '\'']) {
+        format!("\\&{escaped}")
+    } else {
+        escaped
+    }
+}
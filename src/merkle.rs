@@ -0,0 +1,106 @@
+use digest::{Digest, FixedOutput, HashMarker, Output, OutputSizeUser, Reset, Update};
+
+/// THEX splits data into leaves of 1 KiB
+const LEAF_SIZE: usize = 1024;
+
+// A Merkle tree hash as defined by THEX, over any Digest: each leaf is hashed with a 0x00 prefix, and each pair
+// of nodes with a 0x01 prefix. A node without a partner is moved up a level unchanged. Only one node per level
+// is kept, so files of any size are hashed in a few hundred bytes
+
+#[derive(Clone)]
+pub struct Merkle<D: Digest + Clone> {
+    /// hash of the leaf being read
+    leaf: D,
+    /// bytes read into the current leaf
+    leaf_len: usize,
+    /// nodes still waiting for a partner, with their level. Levels strictly decrease towards the end
+    pending: Vec<(u32, Output<D>)>,
+}
+
+impl<D: Digest + Clone> Merkle<D> {
+    /// A hasher for a new leaf, with its prefix already added
+    fn new_leaf() -> D {
+        let mut leaf = D::new();
+        Digest::update(&mut leaf, [0x00]);
+        leaf
+    }
+
+    /// The hash of two nodes joined together
+    fn join(left: &Output<D>, right: &Output<D>) -> Output<D> {
+        let mut node = D::new();
+        Digest::update(&mut node, [0x01]);
+        Digest::update(&mut node, left);
+        Digest::update(&mut node, right);
+        node.finalize()
+    }
+
+    /// Add the finished leaf to the tree, joining nodes of the same level as far up as they go
+    fn finish_leaf(&mut self) {
+        let leaf = std::mem::replace(&mut self.leaf, Self::new_leaf()).finalize();
+        self.leaf_len = 0;
+
+        let mut node = (0, leaf);
+        while let Some((level, left)) = self.pending.pop_if(|(level, _)| *level == node.0) {
+            node = (level + 1, Self::join(&left, &node.1));
+        }
+        self.pending.push(node);
+    }
+}
+
+impl<D: Digest + Clone> Default for Merkle<D> {
+    fn default() -> Self {
+        Self {
+            leaf: Self::new_leaf(),
+            leaf_len: 0,
+            pending: Vec::new(),
+        }
+    }
+}
+
+// Indicate that the Merkle struct is a Digest algorithm (a hash function)
+impl<D: Digest + Clone> HashMarker for Merkle<D> {}
+
+// The root of the tree is the same size as the hash it is built from
+impl<D: Digest + Clone> OutputSizeUser for Merkle<D> {
+    type OutputSize = D::OutputSize;
+}
+
+// Update the hash with the provided data, finishing a leaf every LEAF_SIZE bytes
+impl<D: Digest + Clone> Update for Merkle<D> {
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.leaf_len == LEAF_SIZE {
+                self.finish_leaf();
+            }
+
+            let take = data.len().min(LEAF_SIZE - self.leaf_len);
+            Digest::update(&mut self.leaf, &data[..take]);
+            self.leaf_len += take;
+            data = &data[take..];
+        }
+    }
+}
+
+// Finalize the hash and write it into the provided buffer
+impl<D: Digest + Clone> FixedOutput for Merkle<D> {
+    fn finalize_into(mut self, out: &mut Output<Self>) {
+        // the last leaf, which is the only one if the data was empty
+        if self.leaf_len > 0 || self.pending.is_empty() {
+            self.finish_leaf();
+        }
+
+        // join what is left from the right, which is the same as moving the unpartnered nodes up
+        let mut root = self.pending.pop().map(|(_, node)| node).unwrap_or_default();
+        while let Some((_, left)) = self.pending.pop() {
+            root = Self::join(&left, &root);
+        }
+        out.copy_from_slice(&root);
+    }
+}
+
+// Reset the hash to its initial state
+impl<D: Digest + Clone> Reset for Merkle<D> {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
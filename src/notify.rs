@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use notify_rust::Notification;
+
+use crate::classes::ExitStatus;
+use crate::stats;
+
+// The desktop notification for --notify, so a long run left in the background says when it is done. It is
+// shown with notify-rust: a toast on Windows, the notification centre on macOS, and over D-Bus to the
+// freedesktop notification daemon elsewhere
+
+/// Show a notification with the outcome of the run
+pub fn run_finished(elapsed: Duration) {
+    let title = match stats::exit_status() {
+        ExitStatus::Success => "hash_rust finished",
+        ExitStatus::Mismatch => "hash_rust finished: mismatches found",
+        _ => "hash_rust finished: some files failed",
+    };
+    let body = stats::summary(elapsed);
+
+    match Notification::new()
+        .appname("hash_rust")
+        .summary(title)
+        .body(&body)
+        .show()
+    {
+        Ok(_) => tracing::debug!("Notification shown"),
+        Err(e) => tracing::warn!("Could not show a notification: {e}"),
+    }
+}
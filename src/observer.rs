@@ -0,0 +1,77 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use crate::classes::BasicHash;
+use crate::json::JsonString;
+use crate::stats::FileTiming;
+
+/// Something that happened during the run, reported to the observer as it happens
+pub enum Event<'a> {
+    /// A path is about to be hashed. An archive is started once, and finished once for each entry
+    FileStarted { path: &'a str },
+    /// Bytes fed to a hasher, on whichever thread is doing the hashing
+    BytesHashed(u64),
+    /// A file has been hashed
+    FileFinished {
+        path: &'a str,
+        hash: &'a BasicHash,
+        timing: &'a FileTiming,
+    },
+    /// A file could not be hashed, or its hash didn't match
+    FileFailed {
+        path: &'a str,
+        reason: &'a dyn Display,
+    },
+}
+
+/// Called with every event, from any thread. Frontends can set this instead of reading stderr
+type Observer = Box<dyn Fn(&Event) + Send + Sync>;
+
+/// The observer, if one was set
+static OBSERVER: OnceLock<Observer> = OnceLock::new();
+
+/// Call a function with every event from now on. It must be quick, as it is called from the hashing loop
+pub fn set(observer: impl Fn(&Event) + Send + Sync + 'static) {
+    // only ever set once, so there is nothing to replace
+    let _ = OBSERVER.set(Box::new(observer));
+}
+
+/// Report an event to the observer, if there is one
+pub fn notify(event: Event) {
+    if let Some(observer) = OBSERVER.get() {
+        observer(&event);
+    }
+}
+
+/// Write every event to a file as a line of JSON, eg for a GUI frontend reading a named pipe
+pub fn write_events(path: &str) -> anyhow::Result<()> {
+    let writer = Mutex::new(LineWriter::new(File::create(path)?));
+
+    set(move |event| {
+        let line = match event {
+            Event::FileStarted { path } => {
+                format!(r#"{{"event":"started","path":{}}}"#, JsonString(path))
+            }
+            Event::BytesHashed(bytes) => format!(r#"{{"event":"bytes","bytes":{bytes}}}"#),
+            Event::FileFinished { path, hash, timing } => format!(
+                r#"{{"event":"finished","path":{},"hash":{},"bytes":{},"ms":{}}}"#,
+                JsonString(path),
+                JsonString(&hash.0),
+                timing.bytes,
+                timing.elapsed.as_millis()
+            ),
+            Event::FileFailed { path, reason } => format!(
+                r#"{{"event":"failed","path":{},"reason":{}}}"#,
+                JsonString(path),
+                JsonString(&format!("{reason:#}"))
+            ),
+        };
+
+        // a failed write shouldn't stop the scan, the results still go to stdout
+        let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = writeln!(writer, "{line}");
+    });
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+#[cfg(feature = "parallel")]
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::mpsc::Sender;
+#[cfg(feature = "parallel")]
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock, PoisonError, RwLock};
+#[cfg(feature = "parallel")]
+use std::thread;
+#[cfg(feature = "parallel")]
+use std::time::{Duration, Instant};
+
+use crate::classes::{BasicHash, SortOrder};
+use crate::stats::FileTiming;
+use crate::{color, progress, signing, stats};
+
+/// Batched output is written once it grows this big
+#[cfg(feature = "parallel")]
+const BATCH_SIZE: usize = 64 * 1024;
+
+/// Batched output is written at least this often, so results keep appearing on a slow run
+#[cfg(feature = "parallel")]
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set once stdout can't be written, eg when piped to `head` which has exited
+static CLOSED: AtomicBool = AtomicBool::new(false);
+
+/// The first write error other than a closed pipe, which makes the run fail
+static WRITE_ERROR: OnceLock<io::Error> = OnceLock::new();
+
+/// Lines are sent here instead of written directly, while a writer thread is running
+static WRITER: RwLock<Option<Sender<String>>> = RwLock::new(None);
+
+/// How many hashes were printed, and the first of them, for --clipboard and --qr which only take one
+static PRINTED_HASHES: Mutex<(usize, Option<String>)> = Mutex::new((0, None));
+
+thread_local! {
+    /// Results held back on this thread instead of being printed, so they can be put in order
+    static CAPTURED: RefCell<Option<Vec<HeldLine>>> = const { RefCell::new(None) };
+}
+
+/// A line of results held back to be printed later, with the result it reports so it can be sorted
+pub struct HeldLine {
+    pub path: String,
+    pub hash: String,
+    pub timing: FileTiming,
+    pub text: String,
+}
+
+/// Print a line reporting the result for a path, unless results are being held back on this thread
+pub fn print_result(path: &str, hash: &BasicHash, timing: &FileTiming, line: impl Display) {
+    let line = CAPTURED.with_borrow_mut(|captured| match captured {
+        Some(held) => {
+            held.push(HeldLine {
+                path: path.to_string(),
+                hash: hash.0.clone(),
+                timing: *timing,
+                text: line.to_string(),
+            });
+            None
+        }
+        None => Some(line),
+    });
+
+    if let Some(line) = line {
+        print_line(line);
+    }
+}
+
+/// Write a line of results to stdout. If it can't be written, the remaining work is cancelled instead of panicking
+pub fn print_line(line: impl Display) {
+    if CLOSED.load(atomic::Ordering::Relaxed) {
+        return;
+    }
+
+    if let Some(writer) = WRITER.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        // the writer only goes away once all the work is finished
+        let _ = writer.send(line.to_string());
+        return;
+    }
+
+    let line = format!("{line}\n");
+    match io::stdout().lock().write_all(line.as_bytes()) {
+        Ok(()) => signing::printed(line.as_bytes()),
+        Err(e) => write_failed(e),
+    }
+}
+
+/// Run a function with every printed line going through a writer thread, which holds stdout locked and
+/// writes in large batches. Saves worker threads queueing on the stdout lock, and the progress bar
+/// redrawing between every line
+#[cfg(feature = "parallel")]
+pub fn batched<T>(f: impl FnOnce() -> T) -> T {
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        scope.spawn(|| write_batches(rx));
+        *WRITER.write().unwrap_or_else(|e| e.into_inner()) = Some(tx);
+        let result = f();
+        // dropping the sender lets the writer finish what it has, and exit
+        *WRITER.write().unwrap_or_else(|e| e.into_inner()) = None;
+        result
+    })
+}
+
+/// Gather lines into batches, written when big enough or when the last one has waited long enough
+#[cfg(feature = "parallel")]
+fn write_batches(lines: Receiver<String>) {
+    let mut stdout = io::stdout().lock();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut last_flush = Instant::now();
+
+    loop {
+        let flush_due = match lines.recv_timeout(FLUSH_INTERVAL) {
+            Ok(line) => {
+                batch.extend_from_slice(line.as_bytes());
+                batch.push(b'\n');
+                batch.len() >= BATCH_SIZE || last_flush.elapsed() >= FLUSH_INTERVAL
+            }
+            Err(RecvTimeoutError::Timeout) => true,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        if flush_due && !batch.is_empty() {
+            flush_batch(&mut stdout, &mut batch);
+            last_flush = Instant::now();
+        }
+    }
+
+    flush_batch(&mut stdout, &mut batch);
+}
+
+/// Write out a batch with the progress bar hidden, and empty it
+#[cfg(feature = "parallel")]
+fn flush_batch(stdout: &mut impl Write, batch: &mut Vec<u8>) {
+    if !batch.is_empty() && !CLOSED.load(atomic::Ordering::Relaxed) {
+        let written = progress::suspend(|| stdout.write_all(batch).and_then(|()| stdout.flush()));
+        match written {
+            Ok(()) => signing::printed(batch),
+            Err(e) => write_failed(e),
+        }
+    }
+    batch.clear();
+}
+
+/// stdout can't be written, so cancel the remaining work
+fn write_failed(e: io::Error) {
+    CLOSED.store(true, atomic::Ordering::Relaxed);
+    stats::abort();
+
+    // the reader going away is a normal way to finish, anything else is an error
+    if e.kind() != io::ErrorKind::BrokenPipe {
+        let _ = WRITE_ERROR.set(e);
+    }
+}
+
+/// Run a function on this thread, returning the results it printed instead of printing them
+pub fn capture(f: impl FnOnce()) -> Vec<HeldLine> {
+    CAPTURED.set(Some(Vec::new()));
+    f();
+    CAPTURED.take().unwrap_or_default()
+}
+
+/// Print captured results tagged with their input position, holding back any that arrive early.
+/// Stops at a gap if the run is cancelled, as the missing lines will never arrive
+#[cfg(feature = "parallel")]
+pub fn print_in_order(results: Receiver<(usize, Vec<HeldLine>)>) {
+    let mut next = 0;
+    let mut waiting = BTreeMap::new();
+
+    for (index, lines) in results {
+        waiting.insert(index, lines);
+        while let Some(lines) = waiting.remove(&next) {
+            lines.into_iter().for_each(|line| print_line(line.text));
+            next += 1;
+        }
+    }
+}
+
+/// Print captured results in the given order. Ties are broken by path, so the output is always the same
+pub fn print_sorted(mut held: Vec<HeldLine>, order: SortOrder) {
+    held.sort_by(|a, b| {
+        let by_order = match order {
+            SortOrder::Path => Ordering::Equal,
+            SortOrder::Size => b.timing.bytes.cmp(&a.timing.bytes),
+            SortOrder::Hash => a.hash.cmp(&b.hash),
+            SortOrder::Time => b.timing.elapsed.cmp(&a.timing.elapsed),
+        };
+        by_order.then_with(|| a.path.cmp(&b.path))
+    });
+    progress::suspend(|| held.into_iter().for_each(|line| print_line(line.text)));
+}
+
+/// Note a hash as it is printed, for --clipboard and --qr
+pub fn note_hash(hash: &BasicHash) {
+    let mut printed = PRINTED_HASHES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    printed.0 += 1;
+    printed.1.get_or_insert_with(|| hash.to_string());
+}
+
+/// The hash printed, if exactly one was. Otherwise how many there were
+pub fn single_hash() -> Result<String, usize> {
+    match &*PRINTED_HASHES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+    {
+        (1, Some(hash)) => Ok(hash.clone()),
+        (count, _) => Err(*count),
+    }
+}
+
+/// true once stdout has been closed, so nothing more will be printed
+pub fn closed() -> bool {
+    CLOSED.load(atomic::Ordering::Relaxed)
+}
+
+/// The error that stopped output, unless the reader simply closed the pipe
+pub fn write_error() -> Option<&'static io::Error> {
+    WRITE_ERROR.get()
+}
+
+/// Print captured results grouped by hash, each group under a header line with the hash and number of files,
+/// and for duplicates the bytes taken by the extra copies. Ends with the total for all the duplicates
+pub fn print_grouped(mut held: Vec<HeldLine>, show_paths: bool) {
+    held.sort_by(|a, b| a.hash.cmp(&b.hash).then_with(|| a.path.cmp(&b.path)));
+
+    progress::suspend(|| {
+        let mut duplicated = 0;
+        let mut copies = 0;
+        let mut wasted = 0;
+
+        for group in held.chunk_by(|a, b| a.hash == b.hash) {
+            if group.len() == 1 {
+                print_line(format_args!("{} (1 file)", color::hash(&group[0].hash)));
+            } else {
+                // the files are identical, so every copy after the first is wasted space
+                let extra = group.len() as u64 - 1;
+                let group_wasted = group[0].timing.bytes * extra;
+                duplicated += 1;
+                copies += extra;
+                wasted += group_wasted;
+                print_line(format_args!(
+                    "{} ({} files, {group_wasted} bytes wasted)",
+                    color::hash(&group[0].hash),
+                    group.len()
+                ));
+            }
+            if show_paths {
+                for line in group {
+                    print_line(format_args!("    {}", color::path(&line.path)));
+                }
+            }
+        }
+
+        print_line(format_args!(
+            "Duplicates: {duplicated} groups, {copies} redundant copies, {wasted} bytes wasted"
+        ));
+    });
+}
@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::archive::ARCHIVE_SEPARATOR;
+use crate::classes::ConfigSettings;
+
+/// The path as it should be printed, made canonical with --absolute, or relative to the --relative-to directory
+pub fn display_path<'a>(config: &ConfigSettings, path: &'a str) -> Cow<'a, str> {
+    if config.absolute {
+        return Cow::Owned(canonical(path));
+    }
+
+    let Some(base) = &config.relative_to else {
+        return Cow::Borrowed(path);
+    };
+
+    match absolute(path) {
+        Ok(full_path) => Cow::Owned(relative_to(&full_path, base).to_string_lossy().into_owned()),
+        Err(_) => Cow::Borrowed(path),
+    }
+}
+
+/// The full path with symbolic links resolved. For an entry inside an archive, the archive's path is resolved.
+/// Falls back to a plain absolute path if the file can't be found
+fn canonical(path: &str) -> String {
+    let (file, entry) = match path.split_once(ARCHIVE_SEPARATOR) {
+        Some((archive, entry)) => (archive, Some(entry)),
+        None => (path, None),
+    };
+
+    let resolved = std::fs::canonicalize(file)
+        .or_else(|_| absolute(file))
+        .map_or_else(|_| file.to_string(), |p| p.to_string_lossy().into_owned());
+    let resolved = strip_verbatim(&resolved);
+
+    match entry {
+        Some(entry) => format!("{resolved}{ARCHIVE_SEPARATOR}{entry}"),
+        None => resolved.to_string(),
+    }
+}
+
+/// Windows canonical paths start with `\\?\`, which most tools don't expect. Drop it from plain drive paths
+fn strip_verbatim(path: &str) -> &str {
+    match path.strip_prefix(r"\\?\") {
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => rest,
+        _ => path,
+    }
+}
+
+/// The directory the scan started from: the leading directories of the pattern before any wildcard, or the
+/// directory of a single file. Paths read from stdin are taken from the current directory
+pub fn scan_root(config: &ConfigSettings) -> PathBuf {
+    let Some(pattern) = config.supplied_path.as_deref() else {
+        return PathBuf::new();
+    };
+
+    #[cfg(feature = "glob")]
+    let fixed = crate::walk::fixed_prefix(pattern);
+    #[cfg(not(feature = "glob"))]
+    let fixed = PathBuf::from(pattern);
+
+    if fixed.components().eq(Path::new(pattern).components()) {
+        fixed.parent().map(Path::to_path_buf).unwrap_or_default()
+    } else {
+        fixed
+    }
+}
+
+/// Make a path absolute without touching the filesystem, tidying away `.` and `..` components
+pub fn absolute(path: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let mut tidy = PathBuf::new();
+    for component in std::path::absolute(path)?.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                tidy.pop();
+            }
+            other => tidy.push(other),
+        }
+    }
+    Ok(tidy)
+}
+
+/// Express an absolute path relative to an absolute base directory, going up with `..` where they differ.
+/// Paths with nothing in common, eg on another drive, are left absolute
+pub fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let path_parts: Vec<Component> = path.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    let common = path_parts
+        .iter()
+        .zip(&base_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    if common == 0 {
+        return path.to_path_buf();
+    }
+
+    let mut relative: PathBuf = base_parts[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(&path_parts[common..]);
+    relative
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Named bundles of options from the config file, each option a flag followed by any values
+pub type Profiles = BTreeMap<String, Vec<Vec<String>>>;
+
+/// Where the config file is: `HASH_RUST_CONFIG` if set, otherwise in the user's config directory
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("HASH_RUST_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    let config_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    config_dir.map(|dir| dir.join("hash_rust").join("config"))
+}
+
+/// Replace `--profile name` in the arguments with the options of that profile.
+/// Options given on the command line win over the same options in the profile
+pub fn expand(mut args: Vec<OsString>) -> anyhow::Result<Vec<OsString>> {
+    let Some(position) = args.iter().position(|arg| arg == "--profile") else {
+        return Ok(args);
+    };
+    if position + 1 >= args.len() {
+        return Err(anyhow::anyhow!("--profile needs the name of a profile"));
+    }
+    let name = args.remove(position + 1).to_string_lossy().into_owned();
+    args.remove(position);
+
+    let path = config_path()
+        .ok_or_else(|| anyhow::anyhow!("Can't find the config directory for --profile"))?;
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Can't read the config file {}: {e}", path.display()))?;
+    let profiles = parse(&text)?;
+
+    let options = profiles.get(&name).ok_or_else(|| {
+        let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+        anyhow::anyhow!(
+            "No profile '{name}' in {}, the profiles are: {}",
+            path.display(),
+            names.join(", ")
+        )
+    })?;
+    Ok(apply(args, options))
+}
+
+/// Read profiles written as `[name]` followed by one option per line, eg `--algorithm sha2`.
+/// Blank lines and `#` comments are ignored
+pub fn parse(text: &str) -> anyhow::Result<Profiles> {
+    let mut profiles = Profiles::new();
+    let mut current = None;
+
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.trim().to_string();
+            profiles.entry(name.clone()).or_default();
+            current = Some(name);
+            continue;
+        }
+
+        let option: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        match (&current, option[0].starts_with('-')) {
+            (Some(name), true) => profiles.entry(name.clone()).or_default().push(option),
+            (None, _) => {
+                return Err(anyhow::anyhow!(
+                    "Config file line {}: options must follow a [profile] line",
+                    line_number + 1
+                ))
+            }
+            (_, false) => {
+                return Err(anyhow::anyhow!(
+                    "Config file line {}: '{line}' is not an option",
+                    line_number + 1
+                ))
+            }
+        }
+    }
+
+    Ok(profiles)
+}
+
+/// Add a profile's options after the command line, leaving out any the command line already has
+pub fn apply(mut args: Vec<OsString>, options: &[Vec<String>]) -> Vec<OsString> {
+    let given = args.len();
+    for option in options {
+        if !args[..given].iter().any(|arg| arg == option[0].as_str()) {
+            args.extend(option.iter().map(OsString::from));
+        }
+    }
+    args
+}
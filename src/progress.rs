@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// The overall progress bar, if one was asked for
+static PROGRESS: OnceLock<ProgressManager> = OnceLock::new();
+
+/// Overall progress of the run, drawn on stderr. Counts bytes when the total size is known, otherwise files
+pub struct ProgressManager {
+    bar: ProgressBar,
+    counts_bytes: bool,
+    /// verifying a checksum file, so files and failures are shown as well
+    verifying: bool,
+    total_files: usize,
+    /// bytes hashed so far, across all threads
+    bytes_done: AtomicU64,
+    files_done: AtomicU64,
+    /// files that didn't match or couldn't be read, when verifying
+    failures: AtomicU64,
+}
+
+impl ProgressManager {
+    fn new(total_files: usize, total_bytes: Option<u64>, verifying: bool) -> anyhow::Result<Self> {
+        // verifying has its own colour and a label, as it can run for hours next to other jobs
+        let (prefix, bar_style) = if verifying {
+            ("Verifying ", "{wide_bar:.green/blue}")
+        } else {
+            ("", "{wide_bar}")
+        };
+        let (bar, counts_bytes) = match total_bytes {
+            Some(bytes) => (
+                ProgressBar::new(bytes).with_style(ProgressStyle::with_template(&format!(
+                    "{prefix}{bar_style} {{binary_bytes}}/{{binary_total_bytes}} {{binary_bytes_per_sec}} ETA {{eta}} {{msg}}"
+                ))?),
+                true,
+            ),
+            None => (
+                ProgressBar::new(total_files as u64).with_style(ProgressStyle::with_template(
+                    &format!("{prefix}{bar_style} {{pos}}/{{len}} files {{msg}}"),
+                )?),
+                false,
+            ),
+        };
+
+        Ok(Self {
+            bar,
+            counts_bytes,
+            verifying,
+            total_files,
+            bytes_done: AtomicU64::new(0),
+            files_done: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+        })
+    }
+
+    /// What the bar can't show itself: bytes and average speed on the files bar, and files and failures
+    /// when verifying
+    fn message(&self) -> String {
+        let mut message = if self.counts_bytes {
+            String::new()
+        } else {
+            const MB: f64 = 1024.0 * 1024.0;
+            let done = self.bytes_done.load(Ordering::Relaxed) as f64 / MB;
+            let seconds = self.bar.elapsed().as_secs_f64();
+            let rate = if seconds > 0.0 { done / seconds } else { 0.0 };
+            format!("{done:.1} MB, {rate:.1} MB/s")
+        };
+
+        if self.verifying {
+            if self.counts_bytes {
+                let files = self.files_done.load(Ordering::Relaxed);
+                message = format!("{files}/{} files", self.total_files);
+            }
+            let failures = self.failures.load(Ordering::Relaxed);
+            if failures > 0 {
+                message.push_str(&format!(", {failures} FAILED"));
+            }
+        }
+        message
+    }
+}
+
+/// Show the progress bar for these files. With `prescan` every file is measured first, so the bar tracks bytes
+pub fn start<S: AsRef<str>>(paths: &[S], prescan: bool) -> anyhow::Result<()> {
+    show(paths, prescan, false)
+}
+
+/// Show the progress bar for verifying these files against a checksum file, with a count of failures
+pub fn start_verify<S: AsRef<str>>(paths: &[S], prescan: bool) -> anyhow::Result<()> {
+    show(paths, prescan, true)
+}
+
+fn show<S: AsRef<str>>(paths: &[S], prescan: bool, verifying: bool) -> anyhow::Result<()> {
+    let total_bytes = prescan.then(|| {
+        paths
+            .iter()
+            .filter_map(|path| std::fs::metadata(path.as_ref()).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    });
+
+    let manager = ProgressManager::new(paths.len(), total_bytes, verifying)?;
+    // only ever started once, so there is nothing to replace
+    let _ = PROGRESS.set(manager);
+    Ok(())
+}
+
+/// Add a file to the total, for files hashed as they are found
+#[cfg(all(feature = "parallel", feature = "glob"))]
+pub fn file_found() {
+    if let Some(progress) = PROGRESS.get() {
+        if !progress.counts_bytes {
+            progress.bar.inc_length(1);
+        }
+    }
+}
+
+/// Count bytes fed to a hasher. Called from the hashing loop, so the bar moves during large files
+pub fn add_bytes(bytes: u64) {
+    if let Some(progress) = PROGRESS.get() {
+        progress.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+        if progress.counts_bytes {
+            progress.bar.inc(bytes);
+        }
+    }
+}
+
+/// Count a finished file
+pub fn file_done() {
+    if let Some(progress) = PROGRESS.get() {
+        progress.files_done.fetch_add(1, Ordering::Relaxed);
+        progress.bar.set_message(progress.message());
+        if !progress.counts_bytes {
+            progress.bar.inc(1);
+        }
+    }
+}
+
+/// Count a file that failed verification, before it is counted as done
+pub fn file_failed() {
+    if let Some(progress) = PROGRESS.get() {
+        progress.failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Run a function that prints, with the bar hidden so the output doesn't get mixed into it
+pub fn suspend<T>(f: impl FnOnce() -> T) -> T {
+    match PROGRESS.get() {
+        Some(progress) => progress.bar.suspend(f),
+        None => f(),
+    }
+}
+
+/// Remove the bar once everything has been hashed
+pub fn finish() {
+    if let Some(progress) = PROGRESS.get() {
+        progress.bar.finish_and_clear();
+    }
+}
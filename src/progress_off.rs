@@ -0,0 +1,33 @@
+// Stand-in for the progress bar when built without the progress feature, eg for WebAssembly.
+// Every call does nothing, so the hashing code doesn't need to know
+
+/// No bar to show
+pub fn start<S: AsRef<str>>(_paths: &[S], _prescan: bool) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// No bar to show
+pub fn start_verify<S: AsRef<str>>(_paths: &[S], _prescan: bool) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// No total to add to
+#[cfg(all(feature = "parallel", feature = "glob"))]
+pub fn file_found() {}
+
+/// No bar to move
+pub fn add_bytes(_bytes: u64) {}
+
+/// No bar to move
+pub fn file_done() {}
+
+/// No failures to count
+pub fn file_failed() {}
+
+/// Nothing to hide, so just run the function
+pub fn suspend<T>(f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+/// No bar to remove
+pub fn finish() {}
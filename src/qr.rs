@@ -0,0 +1,48 @@
+use qrcode::render::unicode::Dense1x2;
+use qrcode::{EcLevel, QrCode};
+
+use crate::{color, output, progress};
+
+// QR codes for --qr, drawn with block characters so a hash can be read off the screen with a phone. The code
+// uses error correction level M, in the smallest version the hash fits
+
+/// Draw the hash as a QR code after the results, if exactly one was printed, or say why not
+pub fn print_result() {
+    let hash = match output::single_hash() {
+        Ok(hash) => hash,
+        // nothing was hashed, and the failures have been reported already
+        Err(0) => return,
+        Err(count) => {
+            tracing::warn!("Not drawing a QR code, as {count} files were hashed, not one");
+            return;
+        }
+    };
+    match render(hash.as_bytes(), color::stdout_colored()) {
+        Ok(lines) => progress::suspend(|| lines.into_iter().for_each(output::print_line)),
+        Err(e) => tracing::warn!("Could not draw a QR code: {e}"),
+    }
+}
+
+/// Lines of text showing the data as a QR code, each for two rows of modules. In color the code is black on
+/// white, otherwise the light modules are drawn, for the usual light text on a dark terminal
+pub fn render(data: &[u8], colored: bool) -> anyhow::Result<Vec<String>> {
+    let code = QrCode::with_error_correction_level(data, EcLevel::M)?;
+    let mut renderer = code.render::<Dense1x2>();
+    if !colored {
+        renderer
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark);
+    }
+
+    Ok(renderer
+        .build()
+        .lines()
+        .map(|line| {
+            if colored {
+                format!("\x1b[38;5;16;48;5;231m{line}\x1b[0m")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect())
+}
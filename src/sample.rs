@@ -0,0 +1,51 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// SplitMix64, a small and fast generator. Good enough to pick files, and the same on every platform
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `bound`. The slight bias for huge bounds doesn't matter when sampling files
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// A seed for when none was given, different on every run
+pub fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64)
+}
+
+/// Keep a random `count` of the items, in their original order. The same seed always picks the same items
+pub fn sample<T>(items: &mut Vec<T>, count: usize, seed: u64) {
+    if count >= items.len() {
+        return;
+    }
+
+    // shuffle the first `count` positions of a list of indexes, then keep those in order
+    let mut rng = SplitMix64(seed);
+    let mut indexes: Vec<usize> = (0..items.len()).collect();
+    for i in 0..count {
+        let j = i + rng.below(indexes.len() - i);
+        indexes.swap(i, j);
+    }
+    let mut chosen = vec![false; items.len()];
+    for &index in &indexes[..count] {
+        chosen[index] = true;
+    }
+
+    let mut position = 0;
+    items.retain(|_| {
+        position += 1;
+        chosen[position - 1]
+    });
+}
@@ -0,0 +1,213 @@
+use strum::IntoEnumIterator;
+
+use crate::classes::{HashAlgorithm, OutputEncoding};
+use crate::hasher::{encode_hash, Hasher};
+use crate::{call_hasher, default_encoding};
+
+/// Known answers for every algorithm, from FIPS 180-4, FIPS 202, SP 800-185, RFC 1320, RFC 1321, RFC 3174, RFC 7693, the ISO Whirlpool
+/// vectors, the BLAKE3 reference, the THEX empty tree, the rapidhash, t1ha, MetroHash, CityHash and FarmHash references, the SHA-3 submissions, and the CRC catalogue check values
+const ALGORITHM_VECTORS: &[(HashAlgorithm, &str, &str)] = &[
+    (HashAlgorithm::CRC32, "123456789", "3421780262"),
+    (HashAlgorithm::Crc, "123456789", "cbf43926"),
+    (HashAlgorithm::Crc16Ccitt, "123456789", "29b1"),
+    (HashAlgorithm::Crc16Modbus, "123456789", "4b37"),
+    (HashAlgorithm::Crc16Xmodem, "123456789", "31c3"),
+    (HashAlgorithm::Crc16Usb, "123456789", "b4c8"),
+    (HashAlgorithm::Crc8Smbus, "123456789", "f4"),
+    (HashAlgorithm::Crc8Maxim, "123456789", "a1"),
+    (
+        HashAlgorithm::MD5,
+        "abc",
+        "900150983cd24fb0d6963f7d28e17f72",
+    ),
+    (
+        HashAlgorithm::Ed2k,
+        "abc",
+        "a448017aaf21d8525fc10ae87aa6729d",
+    ),
+    (
+        HashAlgorithm::SHA1,
+        "abc",
+        "a9993e364706816aba3e25717850c26c9cd0d89d",
+    ),
+    (
+        HashAlgorithm::SHA2_224,
+        "abc",
+        "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+    ),
+    (
+        HashAlgorithm::SHA2_256,
+        "abc",
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+    ),
+    (
+        HashAlgorithm::SHA2_384,
+        "abc",
+        "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+    ),
+    (
+        HashAlgorithm::SHA2_512,
+        "abc",
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+    ),
+    (
+        HashAlgorithm::SHA3_256,
+        "abc",
+        "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+    ),
+    (
+        HashAlgorithm::SHA3_384,
+        "abc",
+        "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b298d88cea927ac7f539f1edf228376d25",
+    ),
+    (
+        HashAlgorithm::SHA3_512,
+        "abc",
+        "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+    ),
+    (
+        HashAlgorithm::Whirlpool,
+        "abc",
+        "4e2448a4c6f486bb16b6562c73b4020bf3043e3a731bce721ae1b303d97e6d4c7181eebdb6c57e277d0e34957114cbd6c797fc9d95d8b582d225292076d4eef5",
+    ),
+    (
+        HashAlgorithm::Blake2B512,
+        "abc",
+        "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+    ),
+    (
+        HashAlgorithm::Blake2S256,
+        "abc",
+        "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+    ),
+    (
+        HashAlgorithm::Blake2bp,
+        "abc",
+        "b91a6b66ae87526c400b0a8b53774dc65284ad8f6575f8148ff93dff943a6ecd8362130f22d6dae633aa0f91df4ac89aaff31d0f1b923c898e82025dedbdad6e",
+    ),
+    (
+        HashAlgorithm::Blake2sp,
+        "abc",
+        "70f75b58f1fecab821db43c88ad84edde5a52600616cd22517b7bb14d440a7d5",
+    ),
+    (
+        HashAlgorithm::ParallelHash128,
+        "abc",
+        "f07b9b1d0da389544bce61cfead55b2d599ecbb6aedc21e2850513900290fd0b",
+    ),
+    (
+        HashAlgorithm::ParallelHash256,
+        "abc",
+        "820040c1e9577df899a483b67d235c8cc25b61a99ad604d2f64c2b998f21b43e1f4952584fcad517c5993ea0c013ee6f3f9622343084894fdeef516ab0df3353",
+    ),
+    (
+        HashAlgorithm::Kmac128,
+        "abc",
+        "bad8c820c19c56d72b2dd4955b9bfab692d925dad1b6a65075ba60fd6f739e4a",
+    ),
+    (
+        HashAlgorithm::Kmac256,
+        "abc",
+        "a29daa0f7353b796ec10ccbcd3d52a0c2838a9266018aaf11ddba167e9d375e10858d9f8e6ab182136a08ee0eb0023d4832aaca807254b96927c9214934c6efc",
+    ),
+    (HashAlgorithm::Rapidhash, "abc", "cb475beafa9c0da2"),
+    (HashAlgorithm::T1ha2, "abc", "d03ef8122bc05edd"),
+    (HashAlgorithm::MetroHash64, "abc", "bbfffae624554fed"),
+    (
+        HashAlgorithm::CityHash128,
+        "abc",
+        "3980b2afd2126c04a085f09013029e45",
+    ),
+    (HashAlgorithm::FarmHash64, "hello world", "588fb7478bd6b01b"),
+    (
+        HashAlgorithm::Tth,
+        "",
+        "LWPNACQDBZRYXW3VHJVCJ64QBZNGHOHHHZWCLNQ=",
+    ),
+    #[cfg(feature = "sha3-finalists")]
+    (
+        HashAlgorithm::Groestl256,
+        "",
+        "1a52d11d550039be16107f9c58db9ebcc417f16f736adb2502567119f0083467",
+    ),
+    #[cfg(feature = "sha3-finalists")]
+    (
+        HashAlgorithm::Groestl512,
+        "",
+        "6d3ad29d279110eef3adbd66de2a0345a77baede1557f5d099fce0c03d6dc2ba8e6d4a6633dfbd66053c20faa87d1a11f39a7fbe4a6c2f009801370308fc4ad8",
+    ),
+    #[cfg(feature = "sha3-finalists")]
+    (
+        HashAlgorithm::Jh256,
+        "",
+        "46e64619c18bb0a92a5e87185a47eef83ca747b8fcc8e1412921357e326df434",
+    ),
+    #[cfg(feature = "sha3-finalists")]
+    (
+        HashAlgorithm::Skein512_256,
+        "",
+        "39ccc4554a8b31853b9de7a1fe638a24cce6b35a55f2431009e18780335d2621",
+    ),
+    (
+        HashAlgorithm::Blake3,
+        "abc",
+        "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+    ),
+];
+
+/// Known answers for every output encoding, from the RFC 4648 test vectors
+const ENCODING_VECTORS: &[(OutputEncoding, &[u8], &str)] = &[
+    (OutputEncoding::Hex, b"foobar", "666f6f626172"),
+    (OutputEncoding::Base64, b"foobar", "Zm9vYmFy"),
+    (OutputEncoding::Base32, b"foobar", "MZXW6YTBOI======"),
+    (OutputEncoding::U32, &[0xcb, 0xf4, 0x39, 0x26], "3421780262"),
+];
+
+/// Run every known-answer test, printing each result. Returns true if they all passed
+pub fn run_self_test() -> anyhow::Result<bool> {
+    let mut failures = 0;
+
+    for algo in HashAlgorithm::iter() {
+        let Some((_, input, expected)) = ALGORITHM_VECTORS.iter().find(|(a, _, _)| *a == algo)
+        else {
+            println!("FAILED {algo:?}: no test vector");
+            failures += 1;
+            continue;
+        };
+
+        let actual = call_hasher(algo, default_encoding(algo), input.as_bytes())?;
+        failures += report(&format!("{algo:?}"), &actual.0, expected);
+
+        // the same again, fed a byte at a time
+        let mut hasher = Hasher::new(algo, default_encoding(algo));
+        for byte in input.as_bytes() {
+            hasher.update(std::slice::from_ref(byte));
+        }
+        let actual = hasher.finalize_encoded()?;
+        failures += report(&format!("{algo:?} incremental"), &actual.0, expected);
+    }
+
+    for (encoding, input, expected) in ENCODING_VECTORS {
+        let actual = encode_hash(input, *encoding);
+        failures += report(&format!("{encoding:?} encoding"), &actual.0, expected);
+    }
+
+    if failures == 0 {
+        println!("All self tests passed");
+    } else {
+        println!("{failures} self test(s) FAILED");
+    }
+
+    Ok(failures == 0)
+}
+
+/// Print the outcome of a single test, returning 1 if it failed
+fn report(name: &str, actual: &str, expected: &str) -> u32 {
+    if actual == expected {
+        println!("OK     {name}");
+        0
+    } else {
+        println!("FAILED {name}: expected {expected}, got {actual}");
+        1
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use data_encoding::BASE64;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use crate::output;
+
+// Signed manifests for --sign and --verify-signature, in the format of minisign so the keys it makes can be
+// used, and the signatures checked with it. The signature goes at the end of the output as `#` comment lines,
+// which --check skips, and covers every byte printed before them
+
+/// The line starting the signature block
+pub const SIGNATURE_HEADER: &str = "# minisign signature";
+
+/// A hash of everything printed so far, once signing has started
+static PRINTED: OnceLock<Mutex<blake2b_simd::State>> = OnceLock::new();
+
+/// A minisign secret key
+pub struct SecretKey {
+    id: [u8; 8],
+    key: SigningKey,
+}
+
+/// Read a minisign secret key file. Keys protected by a password can't be read, as that needs scrypt
+pub fn read_secret_key(path: &str) -> anyhow::Result<SecretKey> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read the secret key '{path}': {e}"))?;
+    // the algorithms, the key derivation salt and limits, then the key id, the key and its checksum
+    let bytes = key_bytes(&text)
+        .filter(|bytes| bytes.len() == 158 && bytes.starts_with(b"Ed"))
+        .ok_or_else(|| anyhow::anyhow!("'{path}' is not a minisign secret key"))?;
+    if bytes[2..4] != [0, 0] {
+        return Err(anyhow::anyhow!(
+            "'{path}' is protected by a password, which isn't supported. Make a key without one with minisign -G -W"
+        ));
+    }
+
+    let (id, secret, checksum) = (&bytes[54..62], &bytes[62..126], &bytes[126..158]);
+    let expected = blake2b_simd::Params::new()
+        .hash_length(32)
+        .to_state()
+        .update(b"Ed")
+        .update(id)
+        .update(secret)
+        .finalize();
+
+    // the key is the seed followed by its public key, which must be the one the seed gives
+    let key = SigningKey::from_bytes(&secret[..32].try_into()?);
+    if expected.as_bytes() != checksum || key.verifying_key().as_bytes() != &secret[32..] {
+        return Err(anyhow::anyhow!(
+            "The secret key '{path}' is damaged, its checksum doesn't match"
+        ));
+    }
+    Ok(SecretKey {
+        id: id.try_into()?,
+        key,
+    })
+}
+
+/// A minisign public key
+pub struct PublicKey {
+    id: [u8; 8],
+    key: VerifyingKey,
+}
+
+/// Read a minisign public key file, as written by minisign -G
+pub fn read_public_key(path: &str) -> anyhow::Result<PublicKey> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read the public key '{path}': {e}"))?;
+    // the algorithm, then the key id and the key
+    let bytes = key_bytes(&text)
+        .filter(|bytes| bytes.len() == 42 && bytes.starts_with(b"Ed"))
+        .ok_or_else(|| anyhow::anyhow!("'{path}' is not a minisign public key"))?;
+    let key = VerifyingKey::from_bytes(&bytes[10..].try_into()?)
+        .map_err(|_| anyhow::anyhow!("The public key '{path}' is not a valid Ed25519 key"))?;
+    Ok(PublicKey {
+        id: bytes[2..10].try_into()?,
+        key,
+    })
+}
+
+/// The base64 line of a minisign key file, after its comment, decoded
+fn key_bytes(text: &str) -> Option<Vec<u8>> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))?;
+    BASE64.decode(line.as_bytes()).ok()
+}
+
+/// Hash everything printed from now on, so it can be signed at the end
+pub fn start() {
+    let _ = PRINTED.set(Mutex::new(
+        blake2b_simd::Params::new().hash_length(64).to_state(),
+    ));
+}
+
+/// Add bytes written to stdout to the hash, if signing
+pub fn printed(bytes: &[u8]) {
+    if let Some(state) = PRINTED.get() {
+        state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .update(bytes);
+    }
+}
+
+/// Sign everything printed, and print the signature after it
+pub fn print_signature(key: &SecretKey, description: &str) {
+    let Some(state) = PRINTED.get() else {
+        return;
+    };
+    let hash = state
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .finalize();
+
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let trusted_comment = format!("timestamp:{seconds}\t{description}");
+    for line in signature_block(key, hash.as_bytes(), &trusted_comment) {
+        output::print_line(line);
+    }
+}
+
+/// The lines of a signature of a BLAKE2b-512 hash: minisign's prehashed signature and its signature of the
+/// trusted comment, each line behind a `#`
+pub fn signature_block(key: &SecretKey, hash: &[u8], trusted_comment: &str) -> [String; 5] {
+    let signature = key.key.sign(hash).to_bytes();
+    let mut algorithm_id_signature = b"ED".to_vec();
+    algorithm_id_signature.extend_from_slice(&key.id);
+    algorithm_id_signature.extend_from_slice(&signature);
+
+    // the trusted comment is signed along with the signature, so it can't be changed either
+    let mut signed_comment = signature.to_vec();
+    signed_comment.extend_from_slice(trusted_comment.as_bytes());
+    let global_signature = key.key.sign(&signed_comment).to_bytes();
+
+    [
+        SIGNATURE_HEADER.to_string(),
+        format!(
+            "# untrusted comment: signature from hash_rust, key {:016X}",
+            u64::from_le_bytes(key.id)
+        ),
+        format!("# {}", BASE64.encode(&algorithm_id_signature)),
+        format!("# trusted comment: {trusted_comment}"),
+        format!("# {}", BASE64.encode(&global_signature)),
+    ]
+}
+
+/// Check the signature at the end of a checksum file, returning its trusted comment. Nothing but blank lines
+/// may follow the signature, as they wouldn't be covered by it
+pub fn verify_manifest(bytes: &[u8], key: &PublicKey) -> anyhow::Result<String> {
+    let header = format!("\n{SIGNATURE_HEADER}");
+    let start = if bytes.starts_with(&header.as_bytes()[1..]) {
+        Some(0)
+    } else {
+        bytes
+            .windows(header.len())
+            .rposition(|window| window == header.as_bytes())
+            .map(|position| position + 1)
+    }
+    .ok_or_else(|| anyhow::anyhow!("There is no signature to verify"))?;
+    let (signed, block) = bytes.split_at(start);
+
+    let block = String::from_utf8_lossy(block);
+    let lines: Vec<_> = block
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .skip(1)
+        .map(|line| line.strip_prefix("# "))
+        .collect();
+    let [Some(_untrusted), Some(signature), Some(trusted), Some(global)] = lines[..] else {
+        return Err(anyhow::anyhow!(
+            "The signature is not in the expected form, or has lines after it"
+        ));
+    };
+    let trusted_comment = trusted
+        .strip_prefix("trusted comment: ")
+        .ok_or_else(|| anyhow::anyhow!("The signature has no trusted comment"))?;
+
+    // the algorithm, the key id and the signature
+    let signature = BASE64
+        .decode(signature.as_bytes())
+        .ok()
+        .filter(|signature| signature.len() == 74)
+        .ok_or_else(|| anyhow::anyhow!("The signature is not in the expected form"))?;
+    let global: [u8; 64] = BASE64
+        .decode(global.as_bytes())
+        .ok()
+        .and_then(|global| global.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("The signature is not in the expected form"))?;
+    if signature[2..10] != key.id {
+        return Err(anyhow::anyhow!(
+            "It was signed with key {:016X}, not {:016X}",
+            u64::from_le_bytes(signature[2..10].try_into()?),
+            u64::from_le_bytes(key.id)
+        ));
+    }
+    let ed_signature = Signature::from_bytes(&signature[10..].try_into()?);
+
+    // minisign signs a BLAKE2b-512 hash of the data, or the data itself in its older signatures
+    let checked = match &signature[..2] {
+        b"ED" => {
+            let hash = blake2b_simd::Params::new().hash_length(64).hash(signed);
+            key.key.verify_strict(hash.as_bytes(), &ed_signature)
+        }
+        b"Ed" => key.key.verify_strict(signed, &ed_signature),
+        _ => return Err(anyhow::anyhow!("The signature algorithm is not supported")),
+    };
+    if checked.is_err() {
+        return Err(anyhow::anyhow!(
+            "The signature doesn't match, the file has been changed"
+        ));
+    }
+
+    let mut signed_comment = ed_signature.to_vec();
+    signed_comment.extend_from_slice(trusted_comment.as_bytes());
+    if key
+        .key
+        .verify_strict(&signed_comment, &Signature::from_bytes(&global))
+        .is_err()
+    {
+        return Err(anyhow::anyhow!(
+            "The trusted comment of the signature has been changed"
+        ));
+    }
+    Ok(trusted_comment.to_string())
+}
@@ -0,0 +1,255 @@
+use std::sync::OnceLock;
+
+use digest::{ExtendableOutput, Update, XofReader};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use sha3::{CShake128, CShake128Core, CShake256, CShake256Core, Shake128, Shake256};
+
+// Functions from NIST SP 800-185, built on the cSHAKE of the sha3 crate
+
+/// Default ParallelHash block size, used unless --block-size gives another
+pub const DEFAULT_BLOCK_SIZE: usize = 8 * 1024;
+
+/// Roughly how much data is collected before its blocks are hashed, together and in parallel where possible
+const BATCH_BYTES: usize = 1024 * 1024;
+
+/// The block size for ParallelHash, if --block-size was given
+static BLOCK_SIZE: OnceLock<usize> = OnceLock::new();
+
+/// The KMAC key from --key
+static KEY: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// The customization string from --customization
+static CUSTOMIZATION: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Use this block size for ParallelHash from now on
+pub fn set_block_size(size: usize) {
+    // only ever set once, so there is nothing to replace
+    let _ = BLOCK_SIZE.set(size);
+}
+
+/// The block size for ParallelHash
+pub fn block_size() -> usize {
+    BLOCK_SIZE.get().copied().unwrap_or(DEFAULT_BLOCK_SIZE)
+}
+
+/// Use this key for KMAC from now on
+pub fn set_key(key: Vec<u8>) {
+    let _ = KEY.set(key);
+}
+
+/// The key for KMAC, empty if none was given
+pub fn key() -> &'static [u8] {
+    KEY.get().map_or(&[], Vec::as_slice)
+}
+
+/// Use this customization string for KMAC and ParallelHash from now on
+pub fn set_customization(customization: Vec<u8>) {
+    let _ = CUSTOMIZATION.set(customization);
+}
+
+/// The customization string for KMAC and ParallelHash, empty by default
+pub fn customization() -> &'static [u8] {
+    CUSTOMIZATION.get().map_or(&[], Vec::as_slice)
+}
+
+/// `left_encode` from SP 800-185: the length of the big endian number, then the number
+fn left_encode(value: u64) -> Vec<u8> {
+    let mut encoded = right_encode(value);
+    encoded.rotate_right(1);
+    encoded
+}
+
+/// `right_encode` from SP 800-185: the big endian number, then its length
+fn right_encode(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(7);
+    let mut encoded = bytes[skip..].to_vec();
+    encoded.push((8 - skip) as u8);
+    encoded
+}
+
+/// `bytepad` from SP 800-185: the rate, then the data, padded with zeros to a whole number of blocks
+fn bytepad(data: &[u8], rate: usize) -> Vec<u8> {
+    let mut padded = left_encode(rate as u64);
+    padded.extend_from_slice(data);
+    padded.resize(padded.len().div_ceil(rate) * rate, 0);
+    padded
+}
+
+/// The two security strengths, which differ in the cSHAKE and SHAKE they use and the size of their output
+#[derive(Clone)]
+enum Outer {
+    Bits128(CShake128),
+    Bits256(CShake256),
+}
+
+impl Outer {
+    /// Bytes of output: twice the security strength
+    fn output_len(&self) -> usize {
+        match self {
+            Self::Bits128(_) => 32,
+            Self::Bits256(_) => 64,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Bits128(outer) => outer.update(data),
+            Self::Bits256(outer) => outer.update(data),
+        }
+    }
+
+    /// Add the output length, as every function here does, and read that much output
+    fn finalize(mut self) -> Vec<u8> {
+        let output_len = self.output_len();
+        self.update(&right_encode(output_len as u64 * 8));
+
+        let mut output = vec![0; output_len];
+        match self {
+            Self::Bits128(outer) => outer.finalize_xof().read(&mut output),
+            Self::Bits256(outer) => outer.finalize_xof().read(&mut output),
+        }
+        output
+    }
+}
+
+/// KMAC, a MAC built on cSHAKE: the padded key, then the data
+#[derive(Clone)]
+pub struct Kmac(Outer);
+
+impl Kmac {
+    /// KMAC128 with 256 bits of output
+    pub fn new_128(key: &[u8], customization: &[u8]) -> Self {
+        let core = CShake128Core::new_with_function_name(b"KMAC", customization);
+        Self::new(Outer::Bits128(CShake128::from_core(core)), 168, key)
+    }
+
+    /// KMAC256 with 512 bits of output
+    pub fn new_256(key: &[u8], customization: &[u8]) -> Self {
+        let core = CShake256Core::new_with_function_name(b"KMAC", customization);
+        Self::new(Outer::Bits256(CShake256::from_core(core)), 136, key)
+    }
+
+    fn new(mut outer: Outer, rate: usize, key: &[u8]) -> Self {
+        // encode_string of the key
+        let mut encoded = left_encode(key.len() as u64 * 8);
+        encoded.extend_from_slice(key);
+        outer.update(&bytepad(&encoded, rate));
+        Self(outer)
+    }
+
+    /// The MAC of everything added so far
+    pub fn finalize_bytes(self) -> Vec<u8> {
+        self.0.finalize()
+    }
+}
+
+impl Update for Kmac {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+/// ParallelHash: the data is cut into blocks that are each hashed on their own, then the block hashes are
+/// hashed together. The blocks of each batch are hashed in parallel when the parallel feature is on
+#[derive(Clone)]
+pub struct ParallelHash {
+    outer: Outer,
+    block_size: usize,
+    /// data not hashed yet, up to a batch of whole blocks
+    pending: Vec<u8>,
+    /// blocks hashed so far
+    blocks: u64,
+}
+
+impl ParallelHash {
+    /// ParallelHash128 with 256 bits of output
+    pub fn new_128(block_size: usize, customization: &[u8]) -> Self {
+        let core = CShake128Core::new_with_function_name(b"ParallelHash", customization);
+        Self::new(Outer::Bits128(CShake128::from_core(core)), block_size)
+    }
+
+    /// ParallelHash256 with 512 bits of output
+    pub fn new_256(block_size: usize, customization: &[u8]) -> Self {
+        let core = CShake256Core::new_with_function_name(b"ParallelHash", customization);
+        Self::new(Outer::Bits256(CShake256::from_core(core)), block_size)
+    }
+
+    fn new(outer: Outer, block_size: usize) -> Self {
+        let mut hash = Self {
+            outer,
+            block_size,
+            pending: Vec::new(),
+            blocks: 0,
+        };
+        hash.feed_outer(&left_encode(block_size as u64));
+        hash
+    }
+
+    fn feed_outer(&mut self, data: &[u8]) {
+        self.outer.update(data);
+    }
+
+    /// Hash each block of the pending data, the last one possibly short, and add them to the outer hash in order
+    fn hash_pending(&mut self) {
+        // each block hash is as long as the output
+        let output_len = self.outer.output_len();
+        let hash_block = |block: &[u8]| {
+            let mut digest = vec![0; output_len];
+            if output_len == 32 {
+                Shake128::default()
+                    .chain(block)
+                    .finalize_xof_into(&mut digest);
+            } else {
+                Shake256::default()
+                    .chain(block)
+                    .finalize_xof_into(&mut digest);
+            }
+            digest
+        };
+
+        #[cfg(feature = "parallel")]
+        let digests: Vec<Vec<u8>> = self
+            .pending
+            .par_chunks(self.block_size)
+            .map(hash_block)
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let digests: Vec<Vec<u8>> = self
+            .pending
+            .chunks(self.block_size)
+            .map(hash_block)
+            .collect();
+
+        for digest in &digests {
+            self.feed_outer(digest);
+        }
+        self.blocks += digests.len() as u64;
+        self.pending.clear();
+    }
+
+    /// The hash of everything added so far
+    pub fn finalize_bytes(mut self) -> Vec<u8> {
+        if !self.pending.is_empty() {
+            self.hash_pending();
+        }
+        self.feed_outer(&right_encode(self.blocks));
+        self.outer.finalize()
+    }
+}
+
+impl Update for ParallelHash {
+    fn update(&mut self, mut data: &[u8]) {
+        // whole blocks only, so a batch never splits one
+        let batch = (BATCH_BYTES / self.block_size).max(1) * self.block_size;
+        while !data.is_empty() {
+            if self.pending.len() == batch {
+                self.hash_pending();
+            }
+            let take = data.len().min(batch - self.pending.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+        }
+    }
+}
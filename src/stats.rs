@@ -0,0 +1,268 @@
+use std::cell::Cell;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::classes::{BasicHash, ExitStatus};
+use crate::logging::REPORT;
+use crate::observer::{self, Event};
+
+/// Files hashed successfully, across all threads
+static FILES_HASHED: AtomicU64 = AtomicU64::new(0);
+
+/// Files that could not be hashed, across all threads
+static FILES_FAILED: AtomicU64 = AtomicU64::new(0);
+
+/// Files hashed, but not matching the expected hash
+static FILES_MISMATCHED: AtomicU64 = AtomicU64::new(0);
+
+/// Files left out before hashing, eg by --skip-empty
+static FILES_SKIPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Bytes fed to the hashers, across all threads
+static BYTES_HASHED: AtomicU64 = AtomicU64::new(0);
+
+/// Path and reason for every file that failed or didn't match, across all threads
+static FAILURES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Path, hash and size of every file hashed, across all threads. Only kept when asked for, by --post-results
+static RESULTS: Mutex<Option<Vec<(String, String, u64)>>> = Mutex::new(None);
+
+/// Stop at the first failure, instead of carrying on with the other files
+static FAIL_FAST: AtomicBool = AtomicBool::new(false);
+
+/// Set once a failure has stopped the run
+static ABORTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Bytes fed to hashers on this thread, used to measure a single file
+    static THREAD_BYTES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Time taken and bytes hashed for a single file
+#[derive(Clone, Copy)]
+pub struct FileTiming {
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl Display for FileTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}ms", self.bytes, self.elapsed.as_millis())
+    }
+}
+
+/// Run a function that hashes on this thread, measuring how long it took and how many bytes it hashed
+pub fn timed<T>(f: impl FnOnce() -> T) -> (T, FileTiming) {
+    let bytes_before = THREAD_BYTES.get();
+    let start = Instant::now();
+    let result = f();
+
+    let timing = FileTiming {
+        bytes: THREAD_BYTES.get() - bytes_before,
+        elapsed: start.elapsed(),
+    };
+    (result, timing)
+}
+
+/// Count a file hashed successfully
+pub fn file_hashed(path: &str, hash: &BasicHash, timing: &FileTiming) {
+    files_hashed(1);
+    if let Some(results) = RESULTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_mut()
+    {
+        results.push((path.to_string(), hash.0.clone(), timing.bytes));
+    }
+    observer::notify(Event::FileFinished { path, hash, timing });
+}
+
+/// Count several files hashed successfully, eg as one combined stream
+pub fn files_hashed(count: usize) {
+    FILES_HASHED.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+/// Count files left out before hashing
+pub fn files_skipped(count: usize) {
+    FILES_SKIPPED.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+/// Count a file that failed to hash, keeping the reason for the summary
+pub fn file_failed(path: &str, reason: impl Display) {
+    FILES_FAILED.fetch_add(1, Ordering::Relaxed);
+    failure_seen(path, &reason);
+}
+
+/// Count a file whose hash didn't match what was expected, eg a copy that failed verification
+pub fn file_mismatched(path: &str, reason: impl Display) {
+    FILES_MISMATCHED.fetch_add(1, Ordering::Relaxed);
+    failure_seen(path, &reason);
+}
+
+/// Stop the whole run at the first failed or mismatched file
+pub fn set_fail_fast(fail_fast: bool) {
+    FAIL_FAST.store(fail_fast, Ordering::Relaxed);
+}
+
+/// true once the run has been stopped, by a failure or closed output, so no more files should be started
+pub fn aborted() -> bool {
+    ABORTED.load(Ordering::Relaxed)
+}
+
+/// Stop the run, so no more files are started
+pub fn abort() {
+    ABORTED.store(true, Ordering::Relaxed);
+}
+
+fn failure_seen(path: &str, reason: &dyn Display) {
+    observer::notify(Event::FileFailed { path, reason });
+    FAILURES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push((path.to_string(), format!("{reason:#}")));
+
+    if FAIL_FAST.load(Ordering::Relaxed) {
+        abort();
+    }
+}
+
+/// Keep the path, hash and size of every file hashed from now on
+pub fn keep_results() {
+    *RESULTS.lock().unwrap_or_else(PoisonError::into_inner) = Some(Vec::new());
+}
+
+/// The files hashed, in the order they finished, if they were kept
+#[cfg(feature = "webhook")]
+pub fn take_results() -> Vec<(String, String, u64)> {
+    RESULTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+        .unwrap_or_default()
+}
+
+/// Path and reason for every file that failed or didn't match so far, in the order they happened
+#[cfg(feature = "webhook")]
+pub fn failures() -> Vec<(String, String)> {
+    FAILURES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// The counts of files hashed, failed, mismatched and skipped, and the bytes hashed, so far
+#[cfg(feature = "webhook")]
+pub fn counts() -> [(&'static str, u64); 5] {
+    [
+        ("files_hashed", FILES_HASHED.load(Ordering::Relaxed)),
+        ("files_failed", FILES_FAILED.load(Ordering::Relaxed)),
+        ("files_mismatched", FILES_MISMATCHED.load(Ordering::Relaxed)),
+        ("files_skipped", FILES_SKIPPED.load(Ordering::Relaxed)),
+        ("bytes", bytes_hashed()),
+    ]
+}
+
+/// Report every file that failed or didn't match, sorted by path
+pub fn show_failures() {
+    let mut failures = FAILURES.lock().unwrap_or_else(PoisonError::into_inner);
+    if failures.is_empty() {
+        return;
+    }
+
+    failures.sort();
+    tracing::error!(
+        target: REPORT,
+        files = failures.len(),
+        "{} file(s) failed",
+        failures.len()
+    );
+    for (path, reason) in failures.iter() {
+        tracing::error!(target: REPORT, path = %path, reason = %reason, "Failed");
+    }
+}
+
+/// Exit status for the run so far. Unreadable files take priority over mismatches
+pub fn exit_status() -> ExitStatus {
+    if FILES_FAILED.load(Ordering::Relaxed) > 0 {
+        ExitStatus::FileErrors
+    } else if FILES_MISMATCHED.load(Ordering::Relaxed) > 0 {
+        ExitStatus::Mismatch
+    } else {
+        ExitStatus::Success
+    }
+}
+
+/// Count bytes fed to a hasher, and move the progress bar on
+pub fn add_bytes(bytes: u64) {
+    crate::progress::add_bytes(bytes);
+    observer::notify(Event::BytesHashed(bytes));
+    THREAD_BYTES.set(THREAD_BYTES.get() + bytes);
+    BYTES_HASHED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Total bytes hashed so far
+pub fn bytes_hashed() -> u64 {
+    BYTES_HASHED.load(Ordering::Relaxed)
+}
+
+/// The whole run in a line, eg "120 files hashed, 2 failed, 3.5 GB in 1m 30s"
+#[cfg(feature = "notify")]
+pub fn summary(elapsed: Duration) -> String {
+    let mut summary = format!("{} files hashed", FILES_HASHED.load(Ordering::Relaxed));
+    for (count, what) in [
+        (FILES_FAILED.load(Ordering::Relaxed), "failed"),
+        (FILES_MISMATCHED.load(Ordering::Relaxed), "didn't match"),
+    ] {
+        if count > 0 {
+            summary = format!("{summary}, {count} {what}");
+        }
+    }
+    let whole_seconds = Duration::from_secs(elapsed.as_secs());
+    format!(
+        "{summary}, {} in {}",
+        readable_bytes(bytes_hashed()),
+        humantime::format_duration(whole_seconds)
+    )
+}
+
+/// A number of bytes in the largest unit that leaves at least one, eg "3.5 GB"
+#[cfg(feature = "notify")]
+fn readable_bytes(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["bytes", "KB", "MB", "GB"] {
+        if size < 1000.0 {
+            return if unit == "bytes" {
+                format!("{bytes} bytes")
+            } else {
+                format!("{size:.1} {unit}")
+            };
+        }
+        size /= 1000.0;
+    }
+    format!("{size:.1} TB")
+}
+
+/// Report the counts, bytes, time and speed of the whole run
+pub fn show_stats(elapsed: Duration) {
+    let bytes = bytes_hashed();
+    let seconds = elapsed.as_secs_f64();
+    let rate = if seconds > 0.0 {
+        bytes as f64 / (1024.0 * 1024.0) / seconds
+    } else {
+        0.0
+    };
+
+    tracing::info!(
+        target: REPORT,
+        files_hashed = FILES_HASHED.load(Ordering::Relaxed),
+        files_failed = FILES_FAILED.load(Ordering::Relaxed),
+        files_mismatched = FILES_MISMATCHED.load(Ordering::Relaxed),
+        files_skipped = FILES_SKIPPED.load(Ordering::Relaxed),
+        bytes,
+        elapsed_secs = %format_args!("{seconds:.3}"),
+        mb_per_sec = %format_args!("{rate:.1}"),
+        "Finished"
+    );
+}
@@ -0,0 +1,500 @@
+use std::cmp::Ordering;
+use std::io::{self, IsTerminal, Stderr, Write as _};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::crossterm::{cursor, execute};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Cell, Gauge, Paragraph, Row as TableRow, Table, TableState};
+use ratatui::{Frame, Terminal};
+
+use crate::classes::HashAlgorithm;
+use crate::observer::{self, Event};
+use crate::{color, stats};
+
+// The results browser for --tui, drawn on stderr with ratatui. Results arrive through the observer as files are
+// hashed, and can be sorted, filtered and looked at while the scan goes on, and once it is finished until it is
+// closed
+
+/// How often the screen is redrawn while no key is pressed
+const REDRAW: Duration = Duration::from_millis(100);
+
+/// Lines of the screen that aren't results: title, bar, column headings, gap, two of detail and the keys
+const FIXED_LINES: u16 = 7;
+
+/// The browser, once started
+static BROWSER: OnceLock<Browser> = OnceLock::new();
+
+/// Messages for stderr, kept while the browser is open so they don't draw over it. None when it is closed
+static MESSAGES: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+
+type Screen = Terminal<CrosstermBackend<Stderr>>;
+
+struct Browser {
+    results: Arc<Mutex<Results>>,
+    screen: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Everything reported so far, shared between the hashing threads and the screen
+#[derive(Default)]
+struct Results {
+    rows: Vec<Row>,
+    total: usize,
+    failed: usize,
+    finished: bool,
+}
+
+/// One hashed or failed file
+struct Row {
+    path: String,
+    /// the hash, or the reason it failed
+    outcome: Result<String, String>,
+    bytes: u64,
+    ms: u128,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Path,
+    Size,
+    Time,
+    Hash,
+}
+
+impl Column {
+    fn next(self) -> Self {
+        match self {
+            Self::Path => Self::Size,
+            Self::Size => Self::Time,
+            Self::Time => Self::Hash,
+            Self::Hash => Self::Path,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Size => "size",
+            Self::Time => "time",
+            Self::Hash => "hash",
+        }
+    }
+
+    fn compare(self, a: &Row, b: &Row) -> Ordering {
+        match self {
+            Self::Path => a.path.cmp(&b.path),
+            // biggest and slowest first, as those are the ones worth looking at
+            Self::Size => b.bytes.cmp(&a.bytes),
+            Self::Time => b.ms.cmp(&a.ms),
+            Self::Hash => a.outcome.cmp(&b.outcome),
+        }
+    }
+}
+
+/// How the results are shown, changed by the keys pressed
+struct View {
+    sort: Column,
+    reverse: bool,
+    filter: String,
+    editing_filter: bool,
+    failures_only: bool,
+    /// position of the selected row, and of the first row on screen, among the rows shown
+    selected: usize,
+    top: usize,
+    /// the rows shown, in order, and what they were worked out from
+    shown: Vec<usize>,
+    shown_from: Option<(usize, Column, bool, String, bool)>,
+}
+
+/// Start the browser in place of the progress bar. The results are printed as usual once it is closed
+pub fn start(total: usize, algorithm: HashAlgorithm) -> anyhow::Result<()> {
+    if !io::stderr().is_terminal() {
+        return Err(anyhow::anyhow!("--tui needs a terminal to draw on"));
+    }
+    let screen = open_screen()?;
+
+    let results = Arc::new(Mutex::new(Results {
+        total,
+        ..Results::default()
+    }));
+
+    let shared = Arc::clone(&results);
+    observer::set(move |event| {
+        let row = match event {
+            Event::FileFinished { path, hash, timing } => Row {
+                path: (*path).to_string(),
+                outcome: Ok(hash.0.clone()),
+                bytes: timing.bytes,
+                ms: timing.elapsed.as_millis(),
+            },
+            Event::FileFailed { path, reason } => Row {
+                path: (*path).to_string(),
+                outcome: Err(format!("{reason:#}")),
+                bytes: 0,
+                ms: 0,
+            },
+            Event::FileStarted { .. } | Event::BytesHashed(_) => return,
+        };
+        let mut results = shared.lock().unwrap_or_else(PoisonError::into_inner);
+        results.failed += usize::from(row.outcome.is_err());
+        results.rows.push(row);
+    });
+
+    *MESSAGES.lock().unwrap_or_else(PoisonError::into_inner) = Some(Vec::new());
+    let shared = Arc::clone(&results);
+    let screen = thread::spawn(move || run(screen, &shared, algorithm));
+
+    let _ = BROWSER.set(Browser {
+        results,
+        screen: Mutex::new(Some(screen)),
+    });
+    Ok(())
+}
+
+/// The scan is over. Wait for the browser to be closed, then put the terminal back as it was
+pub fn finish() {
+    let Some(browser) = BROWSER.get() else {
+        return;
+    };
+    browser
+        .results
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .finished = true;
+
+    let screen = browser
+        .screen
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    if let Some(screen) = screen {
+        let _ = screen.join();
+    }
+}
+
+/// Switch stderr to the alternate screen in raw mode, for the browser to draw on
+fn open_screen() -> anyhow::Result<Screen> {
+    terminal::enable_raw_mode()?;
+    let screen = execute!(io::stderr(), EnterAlternateScreen, cursor::Hide)
+        .and_then(|()| Terminal::new(CrosstermBackend::new(io::stderr())));
+    if screen.is_err() {
+        close_screen();
+    }
+    Ok(screen?)
+}
+
+/// Put the terminal back as it was before the browser opened
+fn close_screen() {
+    let _ = execute!(io::stderr(), LeaveAlternateScreen, cursor::Show);
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Draw the screen and act on keys until the browser is closed
+fn run(mut screen: Screen, results: &Mutex<Results>, algorithm: HashAlgorithm) {
+    let colored = color::stderr_colored();
+    let mut view = View {
+        sort: Column::Path,
+        reverse: false,
+        filter: String::new(),
+        editing_filter: false,
+        failures_only: false,
+        selected: 0,
+        top: 0,
+        shown: Vec::new(),
+        shown_from: None,
+    };
+
+    loop {
+        let height = screen.size().map_or(FIXED_LINES + 1, |size| size.height);
+        let page = usize::from(height.saturating_sub(FIXED_LINES).max(1));
+        {
+            let results = results.lock().unwrap_or_else(PoisonError::into_inner);
+            view.update(&results.rows, page);
+            let drawn = screen.draw(|frame| draw(frame, &results, &view, algorithm, colored));
+            if drawn.is_err() {
+                break;
+            }
+        }
+
+        match event::poll(REDRAW).and_then(|ready| ready.then(event::read).transpose()) {
+            Ok(Some(TermEvent::Key(key))) if key.kind == KeyEventKind::Press => {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || !view.press(key.code, page) {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    // closed before the scan was over, so don't start any more files
+    if !results
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .finished
+    {
+        stats::abort();
+    }
+    drop(screen);
+    close_screen();
+    let messages = MESSAGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    if let Some(messages) = messages {
+        let _ = io::stderr().write_all(&messages);
+    }
+}
+
+/// Keep a message for stderr until the browser is closed. Returns false if it isn't open
+pub fn hold_message(message: &[u8]) -> bool {
+    match MESSAGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_mut()
+    {
+        Some(messages) => {
+            messages.extend_from_slice(message);
+            true
+        }
+        None => false,
+    }
+}
+
+impl View {
+    /// Work out the rows shown again if anything they depend on has changed, and keep the selection on screen
+    fn update(&mut self, rows: &[Row], page: usize) {
+        let from = (
+            rows.len(),
+            self.sort,
+            self.reverse,
+            self.filter.clone(),
+            self.failures_only,
+        );
+        if self.shown_from.as_ref() != Some(&from) {
+            let filter = self.filter.to_lowercase();
+            self.shown = (0..rows.len())
+                .filter(|&index| {
+                    let row = &rows[index];
+                    (!self.failures_only || row.outcome.is_err())
+                        && (filter.is_empty() || row.path.to_lowercase().contains(&filter))
+                })
+                .collect();
+            self.shown.sort_by(|&a, &b| {
+                let order = self.sort.compare(&rows[a], &rows[b]);
+                if self.reverse {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+            self.shown_from = Some(from);
+        }
+
+        self.selected = self.selected.min(self.shown.len().saturating_sub(1));
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + page {
+            self.top = self.selected + 1 - page;
+        }
+    }
+
+    /// Act on a key. Returns false once the browser should close
+    fn press(&mut self, key: KeyCode, page: usize) -> bool {
+        if self.editing_filter {
+            match key {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        let last = self.shown.len().saturating_sub(1);
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+            KeyCode::PageDown => self.selected = (self.selected + page).min(last),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            KeyCode::Char('s') => self.sort = self.sort.next(),
+            KeyCode::Char('r') => self.reverse = !self.reverse,
+            KeyCode::Char('e') => self.failures_only = !self.failures_only,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            _ => {}
+        }
+        true
+    }
+}
+
+/// A style with the color given, or without it when stderr isn't colored
+fn paint(colored: bool, color: Color) -> Style {
+    if colored {
+        Style::new().fg(color)
+    } else {
+        Style::new()
+    }
+}
+
+/// Draw the whole screen: title, progress bar, the rows on this page, the selected row in full and the keys
+fn draw(
+    frame: &mut Frame,
+    results: &Results,
+    view: &View,
+    algorithm: HashAlgorithm,
+    colored: bool,
+) {
+    let [title_area, bar_area, table_area, _, detail_area, keys_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Min(2),
+        Constraint::Length(1),
+        Constraint::Length(2),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let done = results.rows.len();
+    let state = if results.finished {
+        "finished, q to close"
+    } else {
+        "scanning, q to stop"
+    };
+    let failed = if results.failed > 0 {
+        Span::styled(
+            format!("{} failed", results.failed),
+            paint(colored, Color::Red),
+        )
+    } else {
+        Span::raw("0 failed")
+    };
+    let title = Line::from(vec![
+        Span::styled("hash_rust", Style::new().add_modifier(Modifier::BOLD)),
+        Span::raw(format!(
+            "  {algorithm:?}  {done}/{} files  {} bytes  ",
+            results.total,
+            stats::bytes_hashed()
+        )),
+        failed,
+        Span::raw(format!("  {state}")),
+    ]);
+    frame.render_widget(Paragraph::new(title), title_area);
+
+    let ratio = if results.total == 0 {
+        1.0
+    } else {
+        (done as f64 / results.total as f64).min(1.0)
+    };
+    frame.render_widget(
+        Gauge::default()
+            .gauge_style(paint(colored, Color::Green))
+            .use_unicode(true)
+            .label("")
+            .ratio(ratio),
+        bar_area,
+    );
+
+    let arrow = if view.reverse { "^" } else { "v" };
+    let heading = |column: Column, title: &str| {
+        if view.sort == column {
+            format!("{title}{arrow}")
+        } else {
+            title.to_string()
+        }
+    };
+    let header = TableRow::new([
+        Cell::from(heading(Column::Hash, "HASH")),
+        Cell::from(Line::from(heading(Column::Size, "BYTES")).right_aligned()),
+        Cell::from(Line::from(heading(Column::Time, "MS")).right_aligned()),
+        Cell::from(heading(Column::Path, "PATH")),
+    ])
+    .style(Style::new().add_modifier(Modifier::UNDERLINED));
+
+    let page = usize::from(table_area.height.saturating_sub(1));
+    let rows = view.shown.iter().skip(view.top).take(page).map(|&index| {
+        let row = &results.rows[index];
+        let hash = match &row.outcome {
+            Ok(hash) => Span::styled(hash.as_str(), paint(colored, Color::Green)),
+            Err(_) => Span::styled("FAILED", paint(colored, Color::Red)),
+        };
+        TableRow::new([
+            Cell::from(hash),
+            Cell::from(Line::from(row.bytes.to_string()).right_aligned()),
+            Cell::from(Line::from(row.ms.to_string()).right_aligned()),
+            Cell::from(row.path.as_str()),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(16),
+            Constraint::Length(14),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(header)
+    .column_spacing(1)
+    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let mut table_state = TableState::default()
+        .with_selected((!view.shown.is_empty()).then(|| view.selected - view.top));
+    frame.render_stateful_widget(table, table_area, &mut table_state);
+
+    // the selected row in full
+    let detail = match view
+        .shown
+        .get(view.selected)
+        .map(|&index| &results.rows[index])
+    {
+        Some(row) => vec![
+            Line::raw(format!("path:  {}", row.path)),
+            match &row.outcome {
+                Ok(hash) => Line::raw(format!("hash:  {hash}")),
+                Err(reason) => Line::from(vec![
+                    Span::raw("error: "),
+                    Span::styled(reason.as_str(), paint(colored, Color::Red)),
+                ]),
+            },
+        ],
+        None => Vec::new(),
+    };
+    frame.render_widget(Paragraph::new(detail), detail_area);
+
+    let filter = if view.editing_filter {
+        format!("filter: {}_  (enter to keep, esc to clear)", view.filter)
+    } else if view.filter.is_empty() {
+        "/ filter".to_string()
+    } else {
+        format!("/ filter: {}", view.filter)
+    };
+    let failures = if view.failures_only {
+        "e all files"
+    } else {
+        "e failures only"
+    };
+    frame.render_widget(
+        Paragraph::new(format!(
+            "up/down/pgup/pgdn move  s sort by {}  r reverse  {filter}  {failures}  q close",
+            view.sort.name()
+        ))
+        .style(Style::new().add_modifier(Modifier::DIM)),
+        keys_area,
+    );
+}
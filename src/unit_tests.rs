@@ -6,6 +6,8 @@
 
 #[cfg(test)]
 use super::*;
+#[cfg(test)]
+use ed25519_dalek::{Signature, Signer, SigningKey};
 
 #[test]
 fn unit_it_works() {
@@ -16,3 +18,814 @@ fn unit_it_works() {
 fn help_length() {
     assert!(HELP.len() > 10);
 }
+
+#[test]
+fn size_suffixes() {
+    assert_eq!(parse_size("100").unwrap(), 100);
+    assert_eq!(parse_size("64k").unwrap(), 64 * 1024);
+    assert_eq!(parse_size("1M").unwrap(), 1024 * 1024);
+    assert_eq!(parse_size("2GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+    assert!(parse_size("M").is_err());
+    assert!(parse_size("ten").is_err());
+}
+
+#[test]
+fn relative_paths() {
+    let base = paths::absolute("data/sums").unwrap();
+    let file = paths::absolute("data/./files/../files/a.txt").unwrap();
+    assert_eq!(file, paths::absolute("data/files/a.txt").unwrap());
+    assert_eq!(
+        paths::relative_to(&file, &base),
+        std::path::Path::new("../files/a.txt")
+    );
+    assert_eq!(
+        paths::relative_to(&file, &paths::absolute("data").unwrap()),
+        std::path::Path::new("files/a.txt")
+    );
+}
+
+#[test]
+fn checksum_line_formats() {
+    let entry = check::parse_line("abc123  dir/file.txt").unwrap();
+    assert_eq!(
+        (entry.expected.as_str(), entry.path.as_str()),
+        ("abc123", "dir/file.txt")
+    );
+    assert_eq!(
+        check::parse_line("abc123 *file.bin").unwrap().path,
+        "file.bin"
+    );
+    assert_eq!(
+        check::parse_line("abc123 file name").unwrap().path,
+        "file name"
+    );
+
+    let bsd = check::parse_line("SHA256 (a (1).txt) = abc123").unwrap();
+    assert_eq!(bsd.path, "a (1).txt");
+    assert_eq!(bsd.algorithm, Some(HashAlgorithm::SHA2_256));
+
+    assert_eq!(
+        check::parse_line("\\abc123  back\\\\slash").unwrap().path,
+        "back\\slash"
+    );
+    assert!(check::parse_line("not-a-hash file").is_none());
+    assert!(check::parse_line("abc123").is_none());
+}
+
+#[test]
+fn malformed_checksum_lines() {
+    let mut config = ConfigSettings::new(
+        Verbosity::Quiet,
+        false,
+        true,
+        false,
+        HashAlgorithm::SHA2_256,
+        OutputEncoding::Hex,
+        None,
+    );
+    let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let text = format!(
+        "{sha256}  good.txt\n\
+         garbage line\n\
+         {}  short.txt\n\
+         {}  not-hex.txt\n\
+         SHA256 (tagged.txt) = {}\n",
+        &sha256[1..],
+        sha256.replace('e', "z"),
+        &sha256[..40]
+    );
+
+    // with -a the algorithm is known, so only a full length hex hash fits
+    config.set_check(Some(CheckOptions::default()));
+    let checksums = check::parse_checksums(&config, "SHA256SUMS", &text);
+    assert_eq!(checksums.entries.len(), 1);
+    assert_eq!(checksums.entries[0].path, "good.txt");
+    assert_eq!(checksums.malformed, 4);
+
+    // without -a a hash of a length no algorithm makes is malformed too, but a SHA-1 sized one is fine
+    config.set_check(Some(CheckOptions {
+        detect_algorithm: true,
+        ..CheckOptions::default()
+    }));
+    let checksums = check::parse_checksums(&config, "SHA256SUMS", &text);
+    assert_eq!(checksums.malformed, 4);
+    let checksums =
+        check::parse_checksums(&config, "SUMS", &format!("{}  sha1.txt", &sha256[..40]));
+    assert_eq!((checksums.entries.len(), checksums.malformed), (1, 0));
+}
+
+#[test]
+fn checksum_base_dir() {
+    let base = Path::new("snapshot");
+    assert_eq!(
+        check::resolve("photos/a.jpg", Some(base)),
+        Path::new("snapshot").join("photos/a.jpg").to_string_lossy()
+    );
+    assert_eq!(check::resolve("photos/a.jpg", None), "photos/a.jpg");
+
+    // absolute paths are already where they should be
+    let absolute = paths::absolute("elsewhere/b.jpg").unwrap();
+    let absolute = absolute.to_string_lossy();
+    assert_eq!(check::resolve(&absolute, Some(base)), absolute);
+}
+
+#[cfg(feature = "glob")]
+#[test]
+fn walk_base_directory() {
+    assert_eq!(
+        walk::fixed_prefix("photos/2024/**/*.jpg"),
+        Path::new("photos/2024")
+    );
+    assert_eq!(walk::fixed_prefix("/data/*/raw/**"), Path::new("/data"));
+    assert_eq!(walk::fixed_prefix("**/*.rs"), Path::new(""));
+}
+
+#[cfg(all(unix, feature = "glob"))]
+#[test]
+fn walk_links_and_order() {
+    use std::os::unix::fs::symlink;
+
+    let root = std::env::temp_dir().join(format!("hash_rust_walk_{}", std::process::id()));
+    for dir in ["a", "a/b", "a-b"] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["a/z.txt", "a/b/y.txt", "a-b/x.txt", "c.txt"] {
+        std::fs::write(root.join(file), file).unwrap();
+    }
+    // one link to a directory outside the walk, and one leading back up to its start
+    let outside = root.with_extension("outside");
+    std::fs::create_dir_all(&outside).unwrap();
+    std::fs::write(outside.join("w.txt"), "w").unwrap();
+    symlink(&outside, root.join("link")).unwrap();
+    symlink(&root, root.join("a/b/loop")).unwrap();
+
+    let pattern = format!("{}/**/*.txt", root.display());
+    let mut config = ConfigSettings::new(
+        Verbosity::Quiet,
+        false,
+        true,
+        false,
+        HashAlgorithm::SHA2_256,
+        OutputEncoding::Hex,
+        None,
+    );
+    let mut found = |links| {
+        config.set_links(links);
+        walk::matching_files(&config, &pattern)
+            .unwrap()
+            .iter()
+            .map(|file| file[root.as_os_str().len() + 1..].to_string())
+            .collect::<Vec<_>>()
+    };
+    let followed = found(LinkMode::Follow);
+    let skipped = found(LinkMode::Skip);
+    let reported = found(LinkMode::Report);
+    std::fs::remove_dir_all(&root).unwrap();
+    std::fs::remove_dir_all(&outside).unwrap();
+
+    // in name order a component at a time, so a/ before a-b/, and the loop is not gone round
+    assert_eq!(
+        followed,
+        ["a/b/y.txt", "a/z.txt", "a-b/x.txt", "c.txt", "link/w.txt"]
+    );
+    assert_eq!(skipped, ["a/b/y.txt", "a/z.txt", "a-b/x.txt", "c.txt"]);
+    assert_eq!(reported, skipped);
+}
+
+#[cfg(feature = "glob")]
+#[test]
+fn brace_expansion() {
+    assert_eq!(
+        braces::expand("photos/**/*.{jpg,png,gif}").unwrap(),
+        ["photos/**/*.jpg", "photos/**/*.png", "photos/**/*.gif"]
+    );
+    assert_eq!(
+        braces::expand("{a,b{1,2}}/{x,}").unwrap(),
+        ["a/x", "a/", "b1/x", "b1/", "b2/x", "b2/"]
+    );
+
+    // left alone without a comma, without a closing brace, or inside a character class
+    assert_eq!(braces::expand("{x}/y{").unwrap(), ["{x}/y{"]);
+    assert_eq!(braces::expand("[{,}]{a,b}").unwrap(), ["[{,}]a", "[{,}]b"]);
+    assert_eq!(braces::expand("[]{,]").unwrap(), ["[]{,]"]);
+
+    assert_eq!(walk::fixed_prefix("data/{a,b}/**"), Path::new("data"));
+    assert!(braces::expand(&"{0,1,2,3}".repeat(6)).is_err());
+}
+
+#[test]
+fn dedupe_script() {
+    let held = |path: &str, hash: &str, bytes| output::HeldLine {
+        path: path.into(),
+        hash: hash.into(),
+        timing: FileTiming {
+            bytes,
+            elapsed: Duration::ZERO,
+        },
+        text: String::new(),
+    };
+    let results = vec![
+        held("b/it's.jpg", "aa", 100),
+        held("unique.txt", "bb", 5),
+        held("a.jpg", "aa", 100),
+        held("empty1", "cc", 0),
+        held("empty2", "cc", 0),
+    ];
+
+    // the first path of each group is kept, and quotes survive
+    let lines = dedupe::script_lines(results, ScriptKind::Hardlink, false);
+    let commands: Vec<_> = lines
+        .iter()
+        .filter(|line| line.starts_with("ln "))
+        .collect();
+    assert_eq!(commands, ["ln -f -- 'a.jpg' 'b/it'\\''s.jpg'"]);
+    assert_eq!(lines.last().unwrap(), "# 1 duplicates, 100 bytes");
+}
+
+#[test]
+fn canonical_manifest() {
+    let root = Path::new("scans/photos");
+    assert_eq!(
+        canonical::manifest_path("scans/photos/2024/a.jpg", Some(root)),
+        "2024/a.jpg"
+    );
+    assert_eq!(
+        canonical::manifest_path("scans/./photos/../photos/b.jpg", Some(root)),
+        "b.jpg"
+    );
+    assert_eq!(
+        canonical::manifest_line("2024/a.jpg", "ab12"),
+        "ab12  2024/a.jpg"
+    );
+    assert_eq!(
+        canonical::manifest_line("odd\\name", "ab12"),
+        "\\ab12  odd\\\\name"
+    );
+}
+
+#[test]
+fn qr_code() {
+    // a SHA-256 hash in hex needs version 5, 37 modules and the quiet zone across, two rows to a line
+    let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let lines = qr::render(sha256.as_bytes(), false).unwrap();
+    assert_eq!(lines.len(), 23);
+    assert!(lines.iter().all(|line| line.chars().count() == 45));
+
+    // in Base64 it fits version 4
+    let base64 = "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+    assert_eq!(qr::render(base64.as_bytes(), false).unwrap().len(), 21);
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn post_results_url() {
+    assert!(webhook::check_url("https://collector.example/hashes").is_ok());
+    assert!(webhook::check_url("http://10.0.0.5:8080/").is_ok());
+    assert!(webhook::check_url("collector.example/hashes").is_err());
+    assert!(webhook::check_url("file:///tmp/out.json").is_err());
+}
+
+#[test]
+fn ed25519_signatures() {
+    // tests 1 and 2 from RFC 8032, signed as --sign does
+    let seed =
+        hex::decode("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap();
+    let key = SigningKey::from_bytes(&seed.try_into().unwrap());
+    assert_eq!(
+        hex::encode(key.verifying_key().as_bytes()),
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+    );
+    assert_eq!(
+        hex::encode(key.sign(b"").to_bytes()),
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+    );
+
+    let seed =
+        hex::decode("4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb").unwrap();
+    let key = SigningKey::from_bytes(&seed.try_into().unwrap());
+    let signature = key.sign(&[0x72]);
+    assert_eq!(
+        hex::encode(signature.to_bytes()),
+        "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"
+    );
+
+    // a change to the message, the signature or the key is caught
+    let public = key.verifying_key();
+    assert!(public.verify_strict(&[0x72], &signature).is_ok());
+    assert!(public.verify_strict(&[0x73], &signature).is_err());
+    let mut changed = signature.to_bytes();
+    changed[40] ^= 1;
+    assert!(public
+        .verify_strict(&[0x72], &Signature::from_bytes(&changed))
+        .is_err());
+    let other = SigningKey::from_bytes(&[7; 32]).verifying_key();
+    assert!(other.verify_strict(&[0x72], &signature).is_err());
+}
+
+/// A file of test data, kept in tests/data
+#[cfg(test)]
+fn test_data(name: &str) -> String {
+    format!("{}/tests/data/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn minisign_signing() {
+    // the same lines as minisign -S -H -t 'hash_rust test manifest' writes for the manifest with this key
+    let key = signing::read_secret_key(&test_data("minisign.key")).unwrap();
+    let manifest = std::fs::read(test_data("manifest.txt")).unwrap();
+    let hash = blake2b_simd::Params::new().hash_length(64).hash(&manifest);
+    let block = signing::signature_block(&key, hash.as_bytes(), "hash_rust test manifest");
+    let minisig = std::fs::read_to_string(test_data("manifest.txt.minisig")).unwrap();
+    let minisig: Vec<String> = minisig.lines().map(|line| format!("# {line}")).collect();
+    assert_eq!(block[2..], minisig[1..]);
+
+    // keys that can't be used
+    let error = |name| {
+        signing::read_secret_key(&test_data(name))
+            .err()
+            .unwrap()
+            .to_string()
+    };
+    assert!(error("encrypted.key").contains("protected by a password"));
+    assert!(error("bad_checksum.key").contains("damaged"));
+    assert!(error("wrong_public.key").contains("damaged"));
+}
+
+#[test]
+fn minisign_verifying() {
+    let secret = signing::read_secret_key(&test_data("minisign.key")).unwrap();
+    let public = signing::read_public_key(&test_data("minisign.pub")).unwrap();
+    let block = |body: &[u8], comment: &str| {
+        let hash = blake2b_simd::Params::new().hash_length(64).hash(body);
+        signing::signature_block(&secret, hash.as_bytes(), comment)
+    };
+    let signed = |body: &[u8], lines: &[String]| {
+        let mut bytes = body.to_vec();
+        for line in lines {
+            bytes.extend_from_slice(line.as_bytes());
+            bytes.push(b'\n');
+        }
+        bytes
+    };
+    let verify = |bytes: &[u8]| signing::verify_manifest(bytes, &public).map_err(|e| e.to_string());
+
+    let manifest = std::fs::read(test_data("manifest.txt")).unwrap();
+    let lines = block(&manifest, "timestamp:0");
+    let good = signed(&manifest, &lines);
+    assert_eq!(verify(&good).unwrap(), "timestamp:0");
+    assert!(verify(&[good.as_slice(), b"\n\n"].concat()).is_ok());
+
+    // a signature with nothing before it, as for an empty manifest
+    assert_eq!(verify(&signed(b"", &block(b"", "empty"))).unwrap(), "empty");
+
+    let mut changed = good.clone();
+    changed[30] ^= 1;
+    assert!(verify(&changed)
+        .unwrap_err()
+        .contains("the file has been changed"));
+
+    let mut comment = lines.clone();
+    comment[3] = "# trusted comment: timestamp:1".to_string();
+    assert!(verify(&signed(&manifest, &comment))
+        .unwrap_err()
+        .contains("trusted comment of the signature has been changed"));
+
+    let mut other_id = lines.clone();
+    let mut signature = data_encoding::BASE64
+        .decode(&lines[2].as_bytes()[2..])
+        .unwrap();
+    signature[2..10].copy_from_slice(&[9; 8]);
+    other_id[2] = format!("# {}", data_encoding::BASE64.encode(&signature));
+    assert!(verify(&signed(&manifest, &other_id))
+        .unwrap_err()
+        .contains("signed with key 0909090909090909"));
+
+    assert!(verify(&[good.as_slice(), b"extra\n"].concat())
+        .unwrap_err()
+        .contains("lines after it"));
+
+    // minisign's older signatures, of the data itself rather than its hash
+    let seed =
+        hex::decode("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap();
+    let key = SigningKey::from_bytes(&seed.try_into().unwrap());
+    let signature = key.sign(&manifest).to_bytes();
+    let global = key.sign(&[&signature[..], b"legacy"].concat()).to_bytes();
+    let legacy = [
+        signing::SIGNATURE_HEADER.to_string(),
+        "# untrusted comment: legacy signature".to_string(),
+        format!(
+            "# {}",
+            data_encoding::BASE64
+                .encode(&[b"Ed".as_slice(), &[1, 2, 3, 4, 5, 6, 7, 8], &signature].concat())
+        ),
+        "# trusted comment: legacy".to_string(),
+        format!("# {}", data_encoding::BASE64.encode(&global)),
+    ];
+    assert_eq!(verify(&signed(&manifest, &legacy)).unwrap(), "legacy");
+}
+
+#[test]
+fn hdd_order() {
+    let at = |device, offset, inode| hdd::Location {
+        device,
+        offset,
+        inode,
+    };
+    let locations = [
+        at(2, 500, 1),
+        at(1, u64::MAX, 7),
+        at(1, 300, 9),
+        at(2, 100, 2),
+        at(1, u64::MAX, 3),
+    ];
+
+    // one group per device, by physical offset, then files without one in inode order
+    assert_eq!(
+        hdd::group_by_device(&locations),
+        vec![vec![2, 4, 1], vec![3, 0]]
+    );
+}
+
+#[test]
+fn checksum_algorithm_from_length() {
+    let detect =
+        |line: &str, prefer| check::detect_algorithm(&check::parse_line(line).unwrap(), prefer);
+    let sha = format!("{} f", "0".repeat(64));
+    assert_eq!(
+        detect(&sha, HashFamily::SHA3).unwrap(),
+        HashAlgorithm::SHA3_256
+    );
+    assert_eq!(
+        detect(&sha, HashFamily::SHA2).unwrap(),
+        HashAlgorithm::SHA2_256
+    );
+    assert_eq!(
+        detect("0123456789 f", HashFamily::SHA3).unwrap(),
+        HashAlgorithm::CRC32
+    );
+    assert_eq!(
+        detect("MD5 (f) = 00", HashFamily::SHA3).unwrap(),
+        HashAlgorithm::MD5
+    );
+    assert!(detect("abcd f", HashFamily::SHA3).is_err());
+}
+
+#[test]
+fn checksum_file_encodings() {
+    let text = "abc12 file\r\n";
+    let utf16le: Vec<u8> = [0xFF, 0xFE]
+        .into_iter()
+        .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+    let utf16be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+
+    assert_eq!(check::decode_text(b"\xEF\xBB\xBFabc12 file\r\n"), text);
+    assert_eq!(check::decode_text(&utf16le), text);
+    assert_eq!(check::decode_text(&utf16be), text);
+    assert_eq!(check::decode_text(text.as_bytes()), text);
+}
+
+#[test]
+fn profile_options() {
+    let profiles =
+        profiles::parse("# comment\n[quick]\n--algorithm blake3\n-n\n\n[empty]\n").unwrap();
+    assert_eq!(profiles["quick"].len(), 2);
+    assert!(profiles["empty"].is_empty());
+    assert!(profiles::parse("--stats\n").is_err());
+    assert!(profiles::parse("[p]\nstats\n").is_err());
+
+    let args = vec!["*.txt".into(), "--algorithm".into(), "md5".into()];
+    let args = profiles::apply(args, &profiles["quick"]);
+    assert_eq!(args, ["*.txt", "--algorithm", "md5", "-n"]);
+}
+
+#[test]
+fn man_page_options() {
+    let flags = man::options("FLAGS:");
+    let help = flags.iter().find(|o| o.long == "--help").unwrap();
+    assert_eq!(help.short, Some("-h"));
+    assert_eq!(help.description, "Prints help information");
+
+    let options = man::options("OPTIONS:");
+    let algorithm = options.iter().find(|o| o.long == "--algorithm").unwrap();
+    assert_eq!(algorithm.value, Some("algorithm"));
+
+    let page = man::generate();
+    assert!(page.contains(".SH OPTIONS"));
+    assert!(page.contains("\\fB\\-\\-relative\\-to\\fR \\fIdir\\fR"));
+}
+
+#[test]
+fn algorithm_names() {
+    for algorithm in HashAlgorithm::iter() {
+        for alias in algorithm.aliases() {
+            assert_eq!(HashAlgorithm::from_str(alias).unwrap(), algorithm);
+        }
+    }
+    assert_eq!(HashAlgorithm::SHA2_256.name(), "SHA2");
+    assert_eq!(HashAlgorithm::Whirlpool.digest_bits(), 512);
+}
+
+#[test]
+fn modified_times() {
+    let day = parse_time("2024-06-01").unwrap();
+    assert_eq!(
+        day,
+        humantime::parse_rfc3339("2024-06-01T00:00:00Z").unwrap()
+    );
+    assert!(parse_time("2024-06-01 12:30:00").unwrap() > day);
+    assert!(parse_time("7d").unwrap() < std::time::SystemTime::now());
+    assert!(parse_time("last week").is_err());
+}
+
+#[test]
+fn random_sample() {
+    let all: Vec<u32> = (0..100).collect();
+    let mut picked = all.clone();
+    sample::sample(&mut picked, 10, 42);
+    assert_eq!(picked.len(), 10);
+    assert!(picked.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let mut again = all.clone();
+    sample::sample(&mut again, 10, 42);
+    assert_eq!(picked, again);
+
+    let mut everything = all.clone();
+    sample::sample(&mut everything, 200, 42);
+    assert_eq!(everything, all);
+}
+
+#[test]
+fn hash_reencoding() {
+    // a hash of each width, printed in each encoding it can take, decodes to the same bytes
+    for algorithm in [
+        HashAlgorithm::Crc8Smbus,
+        HashAlgorithm::Crc16Usb,
+        HashAlgorithm::CRC32,
+        HashAlgorithm::Rapidhash,
+        HashAlgorithm::MD5,
+        HashAlgorithm::SHA1,
+        HashAlgorithm::SHA2_224,
+        HashAlgorithm::SHA2_256,
+        HashAlgorithm::SHA2_384,
+        HashAlgorithm::SHA2_512,
+    ] {
+        let hash = hash_file_encoded(
+            b"abc".as_slice(),
+            Hasher::new(algorithm, OutputEncoding::Hex),
+        )
+        .unwrap();
+        let bytes = decode_hash(&hash, OutputEncoding::Hex, algorithm).unwrap();
+        assert_eq!(bytes.len(), algorithm.digest_bits() / 8);
+
+        for encoding in [
+            OutputEncoding::Hex,
+            OutputEncoding::Base64,
+            OutputEncoding::Base32,
+            OutputEncoding::U32,
+        ] {
+            if encoding == OutputEncoding::U32 && algorithm.digest_bits() > 32 {
+                continue;
+            }
+            let encoded = encode_hash(&bytes, encoding);
+            assert_eq!(
+                decode_hash(&encoded, encoding, algorithm).unwrap(),
+                bytes,
+                "{algorithm:?} in {encoding:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn narrow_checksum_encodings() {
+    // a CRC-16 or CRC-8 printed as a number comes back as two bytes or one, as -e u32,hex prints it
+    let check = |algorithm, number: &str, hex: &str, base64: &str| {
+        let hash = hash_file_encoded(
+            b"123456789".as_slice(),
+            Hasher::new(algorithm, OutputEncoding::U32),
+        )
+        .unwrap();
+        assert_eq!(hash.0, number);
+        let bytes = decode_hash(&hash, OutputEncoding::U32, algorithm).unwrap();
+        assert_eq!(encode_hash(&bytes, OutputEncoding::Hex).0, hex);
+        assert_eq!(encode_hash(&bytes, OutputEncoding::Base64).0, base64);
+    };
+    check(HashAlgorithm::Crc16Modbus, "19255", "4b37", "Szc=");
+    check(HashAlgorithm::Crc8Smbus, "244", "f4", "9A==");
+}
+
+#[test]
+fn convert_checksum_formats() {
+    let md5 = "900150983cd24fb0d6963f7d28e17f72";
+    let gnu = format!("{md5}  dir/abc\n");
+    let records = convert::read(&gnu, None, HashFamily::SHA3).unwrap();
+    assert_eq!(records[0].algorithm, HashAlgorithm::MD5);
+
+    let bsd = convert::write(&records, ChecksumFormat::Bsd).unwrap();
+    assert_eq!(bsd, format!("MD5 (dir/abc) = {md5}\n"));
+
+    // JSON written by --convert reads back the same
+    let json = convert::write(&records, ChecksumFormat::Json).unwrap();
+    assert_eq!(
+        convert::read(&json, None, HashFamily::SHA3).unwrap(),
+        records
+    );
+
+    let hashdeep = format!("%%%% HASHDEEP-1.0\n%%%% size,md5,filename\n## \n3,{md5},a,b\n");
+    let records = convert::read(&hashdeep, None, HashFamily::SHA3).unwrap();
+    assert_eq!(records[0].path, "a,b");
+    assert_eq!(records[0].size, Some(3));
+}
+
+#[test]
+fn crc_specs() {
+    // check values from the CRC catalogue, the CRC of "123456789"
+    let check = |spec: &str| {
+        let mut crc = crc::Crc::new(crc::parse_spec(spec).unwrap());
+        digest::Update::update(&mut crc, b"123456789");
+        crc.value()
+    };
+    assert_eq!(check("width=16,poly=0x1021,init=0xffff"), 0x29B1);
+    assert_eq!(check("width=8 poly=0x07"), 0xF4);
+    assert_eq!(
+        check("width=5,poly=0x05,init=0x1f,refin=true,xorout=0x1f"),
+        0x19
+    );
+    assert_eq!(
+        check("width=64,poly=0x42f0e1eba9ea3693,init=0xffffffffffffffff,refin=true,xorout=0xffffffffffffffff"),
+        0x995D_C9BB_DF19_39FA
+    );
+    assert_eq!(crc::Crc::new(crc::CrcSpec::CRC32).finalize_bytes().len(), 4);
+    assert_eq!(
+        crc::Crc::new(crc::CrcSpec::CRC16_USB)
+            .finalize_bytes()
+            .len(),
+        2
+    );
+    assert_eq!(encode_hash(&[0x29, 0xb1], OutputEncoding::U32).0, "10673");
+
+    assert!(crc::parse_spec("poly=0x07").is_err());
+    assert!(crc::parse_spec("width=8,poly=0x107").is_err());
+}
+
+#[test]
+fn ed2k_chunks() {
+    use md4::{Digest, Md4};
+    let hash = |data: &[u8]| {
+        call_hasher(HashAlgorithm::Ed2k, OutputEncoding::Hex, data)
+            .unwrap()
+            .0
+    };
+
+    // less than a chunk is plain MD4
+    assert_eq!(hash(b"abc"), hex::encode(Md4::digest(b"abc")));
+
+    // a whole chunk is followed by the hash of an empty one
+    let chunk = vec![7u8; 9_728_000];
+    let mut chunk_hashes = Md4::digest(&chunk).to_vec();
+    chunk_hashes.extend(Md4::digest([]));
+    assert_eq!(hash(&chunk), hex::encode(Md4::digest(&chunk_hashes)));
+}
+
+#[test]
+fn merkle_tree() {
+    use sha2::{Digest, Sha256};
+    let node = |prefix: u8, parts: &[&[u8]]| {
+        let mut hasher = Sha256::new();
+        hasher.update([prefix]);
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finalize()
+    };
+    let root = |data: &[u8]| merkle::Merkle::<Sha256>::digest(data);
+
+    assert_eq!(root(b""), node(0, &[]));
+    assert_eq!(root(b"abc"), node(0, &[b"abc"]));
+
+    // three leaves: the first two are joined, and the third moves up to join them at the top
+    let data = vec![9u8; 2 * 1024 + 1];
+    let left = node(
+        1,
+        &[&node(0, &[&data[..1024]]), &node(0, &[&data[1024..2048]])],
+    );
+    let expected = node(1, &[&left, &node(0, &[&data[2048..]])]);
+    assert_eq!(root(&data), expected);
+}
+
+#[test]
+fn parallel_hash() {
+    // samples 1 and 4 of NIST SP 800-185
+    let data = hex::decode("000102030405060710111213141516172021222324252627").unwrap();
+    let mut hash = sp800_185::ParallelHash::new_128(8, b"");
+    digest::Update::update(&mut hash, &data);
+    assert_eq!(
+        hex::encode(hash.finalize_bytes()),
+        "ba8dc1d1d979331d3f813603c67f72609ab5e44b94a0b8f9af46514454a2b4f5"
+    );
+
+    let mut hash = sp800_185::ParallelHash::new_256(8, b"");
+    for byte in &data {
+        digest::Update::update(&mut hash, std::slice::from_ref(byte));
+    }
+    assert_eq!(
+        hex::encode(hash.finalize_bytes()),
+        "bc1ef124da34495e948ead207dd9842235da432d2bbc54b4c110e64c451105531b7f2a3e0ce055c02805e7c2de1fb746af97a1dd01f43b824e31b87612410429"
+    );
+}
+
+#[test]
+fn kmac() {
+    // samples 1 and 6 of NIST SP 800-185
+    let key: Vec<u8> = (0x40..0x60).collect();
+    let mut mac = sp800_185::Kmac::new_128(&key, b"");
+    digest::Update::update(&mut mac, &[0, 1, 2, 3]);
+    assert_eq!(
+        hex::encode(mac.finalize_bytes()),
+        "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e"
+    );
+
+    let data: Vec<u8> = (0..200).collect();
+    let mut mac = sp800_185::Kmac::new_256(&key, b"My Tagged Application");
+    for chunk in data.chunks(7) {
+        digest::Update::update(&mut mac, chunk);
+    }
+    assert_eq!(
+        hex::encode(mac.finalize_bytes()),
+        "b58618f71f92e1d56c1b8c55ddd7cd188b97b4ca4d99831eb2699a837da2e4d970fbacfde50033aea585f1a2708510c32d07880801bd182898fe476876fc8965"
+    );
+}
+
+#[test]
+fn rapidhash_stream() {
+    // read in pieces that cross the 112 byte blocks, it must match hashing all the data at once
+    let data: Vec<u8> = (0..5000u32).map(|i| (i * 7 + i / 13) as u8).collect();
+    let mut hash = fingerprint::Rapidhash::default();
+    for chunk in data.chunks(37) {
+        digest::Update::update(&mut hash, chunk);
+    }
+    assert_eq!(
+        digest::FixedOutput::finalize_fixed(hash).as_slice(),
+        rapidhash::v3::rapidhash_v3(&data).to_be_bytes()
+    );
+    assert_eq!(
+        <fingerprint::Rapidhash as digest::Digest>::digest(b"abc").as_slice(),
+        rapidhash::v3::rapidhash_v3(b"abc").to_be_bytes()
+    );
+}
+
+#[test]
+fn t1ha2_stream() {
+    // the first values of the t1ha2 stream reference: empty with seed 0, then the 64 byte test pattern
+    let digest = |data: &[u8]| hex::encode(<fingerprint::T1ha2 as digest::Digest>::digest(data));
+    assert_eq!(digest(b""), "3c8426e33cb41606");
+    let mut pattern = vec![
+        0, 1, 2, 3, 4, 5, 6, 7, 0xff, 0x7f, 0x3f, 0x1f, 0xf, 8, 16, 32, 64, 0x80, 0xfe, 0xfc, 0xf8,
+        0xf0, 0xe0, 0xc0, 0xfd, 0xfb, 0xf7, 0xef, 0xdf, 0xbf, 0x55, 0xaa, 11, 17, 19, 23, 29, 37,
+        42, 43,
+    ];
+    pattern.extend_from_slice(b"abcdefghijklmnopqrstuvwx");
+    assert_eq!(digest(&pattern), "f43de3cdd8a20486");
+
+    // fed in pieces that straddle the 32 byte blocks
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let mut hash = fingerprint::T1ha2::default();
+    for chunk in data.chunks(13) {
+        digest::Update::update(&mut hash, chunk);
+    }
+    assert_eq!(
+        hex::encode(digest::FixedOutput::finalize_fixed(hash)),
+        "c6bea1af37b87de3"
+    );
+    assert_eq!(digest(&data), "c6bea1af37b87de3");
+}
+
+#[test]
+fn metrohash_stream() {
+    // the MetroHash64 reference test, seed 0, fed a byte at a time and all at once
+    let key = b"012345678901234567890123456789012345678901234567890123456789012";
+    let mut hash = fingerprint::MetroHash64::default();
+    for byte in key {
+        digest::Update::update(&mut hash, std::slice::from_ref(byte));
+    }
+    assert_eq!(
+        hex::encode(digest::FixedOutput::finalize_fixed(hash)),
+        "6b753dae06704bad"
+    );
+    assert_eq!(
+        hex::encode(<fingerprint::MetroHash64 as digest::Digest>::digest(key)),
+        "6b753dae06704bad"
+    );
+
+    // longer than a block, in pieces that straddle them
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let mut hash = fingerprint::MetroHash64::default();
+    for chunk in data.chunks(45) {
+        digest::Update::update(&mut hash, chunk);
+    }
+    assert_eq!(
+        hex::encode(digest::FixedOutput::finalize_fixed(hash)),
+        "7aa5fff84ae08b5a"
+    );
+}
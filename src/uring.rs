@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+
+use digest::Update;
+use io_uring::{opcode, types, IoUring};
+
+/// Size of each read submitted to the ring
+const CHUNK_SIZE: usize = 128 * 1024;
+
+/// Number of reads kept in flight for each file
+const QUEUE_DEPTH: usize = 8;
+
+/// Read a file through io_uring, keeping several reads in flight, and feed the chunks to the hasher in order
+pub fn feed_file<U: Update>(path: &str, size: u64, hasher: &mut U) -> anyhow::Result<()> {
+    let file = File::open(path)?;
+    let mut ring = IoUring::new(QUEUE_DEPTH as u32)?;
+    let mut reader = RingReader {
+        file: &file,
+        size,
+        buffers: (0..QUEUE_DEPTH)
+            .map(|_| vec![0u8; CHUNK_SIZE].into_boxed_slice())
+            .collect(),
+        completed: vec![None; QUEUE_DEPTH],
+        next_submit: 0,
+        next_hash: 0,
+        pending: 0,
+    };
+
+    let result = reader.run(&mut ring, hasher);
+
+    // the kernel may still be writing into our buffers after an error, so wait for every read to finish
+    while reader.pending > 0 {
+        ring.submit_and_wait(1)?;
+        reader.pending -= ring.completion().count();
+    }
+
+    result
+}
+
+/// State of the reads for a single file. Chunk n always lives in buffer slot n % `QUEUE_DEPTH`
+struct RingReader<'a> {
+    file: &'a File,
+    size: u64,
+    buffers: Vec<Box<[u8]>>,
+    /// bytes returned by each finished read, waiting to be hashed
+    completed: Vec<Option<usize>>,
+    /// offset of the next read to submit
+    next_submit: u64,
+    /// offset of the next chunk to hash
+    next_hash: u64,
+    /// reads submitted but not yet completed
+    pending: usize,
+}
+
+impl RingReader<'_> {
+    fn run<U: Update>(&mut self, ring: &mut IoUring, hasher: &mut U) -> anyhow::Result<()> {
+        while self.next_hash < self.size {
+            // keep the queue full, as long as the slot has already been hashed
+            while self.next_submit < self.size
+                && self.next_submit < self.next_hash + (QUEUE_DEPTH * CHUNK_SIZE) as u64
+            {
+                let slot = slot_for(self.next_submit);
+                let entry = opcode::Read::new(
+                    types::Fd(self.file.as_raw_fd()),
+                    self.buffers[slot].as_mut_ptr(),
+                    CHUNK_SIZE as u32,
+                )
+                .offset(self.next_submit)
+                .build()
+                .user_data(slot as u64);
+
+                // SAFETY: the buffer is not touched again until this read completes, and feed_file waits for
+                // all pending reads before the buffers are dropped
+                unsafe { ring.submission().push(&entry)? };
+                self.next_submit += CHUNK_SIZE as u64;
+                self.pending += 1;
+            }
+
+            ring.submit_and_wait(1)?;
+            for cqe in ring.completion() {
+                self.pending -= 1;
+                let result = cqe.result();
+                if result < 0 {
+                    return Err(std::io::Error::from_raw_os_error(-result).into());
+                }
+                self.completed[cqe.user_data() as usize] = Some(result as usize);
+            }
+
+            // hash whatever has arrived, strictly in file order
+            while let Some(len) = self.completed[slot_for(self.next_hash)].take() {
+                self.hash_chunk(len, hasher)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hash the chunk at `next_hash`, completing it synchronously if the kernel returned a short read
+    fn hash_chunk<U: Update>(&mut self, mut len: usize, hasher: &mut U) -> anyhow::Result<()> {
+        let slot = slot_for(self.next_hash);
+        let wanted = CHUNK_SIZE.min((self.size - self.next_hash) as usize);
+
+        while len < wanted {
+            let n = self.file.read_at(
+                &mut self.buffers[slot][len..wanted],
+                self.next_hash + len as u64,
+            )?;
+            if n == 0 {
+                return Err(anyhow::anyhow!("File was truncated while hashing"));
+            }
+            len += n;
+        }
+
+        crate::limits::throttle(len);
+        hasher.update(&self.buffers[slot][..len]);
+        self.next_hash += CHUNK_SIZE as u64;
+        Ok(())
+    }
+}
+
+/// Buffer slot used for the chunk starting at this offset
+fn slot_for(offset: u64) -> usize {
+    (offset / CHUNK_SIZE as u64) as usize % QUEUE_DEPTH
+}
@@ -0,0 +1,224 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use glob::{MatchOptions, Pattern};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+
+use crate::classes::{ConfigSettings, LinkMode};
+use crate::stats;
+
+// The recursive walk behind `**` patterns. The glob crate descends into every directory it finds, so a `**`
+// from the root would wander into /proc and /sys. Here the tree is walked by the parallel walker of the ignore
+// crate, with its filters turned off, deciding which directories to enter, and each file is matched against
+// the whole pattern
+
+/// Virtual filesystems, whose files are generated as they are read
+const PSEUDO_PATHS: [&str; 3] = ["/proc", "/sys", "/dev"];
+
+/// The files matching a pattern containing `**`, found by walking everything under its fixed leading part.
+/// They come in the same order however many threads walk, each directory's entries in name order
+pub fn matching_files(config: &ConfigSettings, pattern: &str) -> anyhow::Result<Vec<String>> {
+    let files = Mutex::new(Vec::new());
+    Walker::new(config, pattern)?.run(&|path| {
+        files
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(path);
+    });
+
+    let mut files = files.into_inner().unwrap_or_else(PoisonError::into_inner);
+    // by component, so a directory's files come before those of a directory with a longer name
+    files.sort_by(|a, b| Path::new(a).components().cmp(Path::new(b).components()));
+    Ok(files)
+}
+
+/// Walk as `matching_files` does, but hand each file over as soon as it is found, in no particular order
+#[cfg(feature = "parallel")]
+pub fn stream_matching_files(
+    config: &ConfigSettings,
+    pattern: &str,
+    found: &(dyn Fn(String) + Sync),
+) -> anyhow::Result<()> {
+    Walker::new(config, pattern)?.run(found);
+    Ok(())
+}
+
+/// The leading directories of a pattern, up to the first one with a wildcard or braces. Empty for the current
+/// directory
+pub fn fixed_prefix(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '[', '{'])
+        })
+        .collect()
+}
+
+struct Walker<'a> {
+    config: &'a ConfigSettings,
+    pattern: Pattern,
+    options: MatchOptions,
+    /// where the walk starts
+    base: PathBuf,
+    /// the real path of the start, to know the virtual filesystems under it
+    real_base: Option<PathBuf>,
+}
+
+impl<'a> Walker<'a> {
+    fn new(config: &'a ConfigSettings, pattern: &str) -> anyhow::Result<Self> {
+        let base = fixed_prefix(pattern);
+        Ok(Walker {
+            config,
+            pattern: Pattern::new(pattern)?,
+            options: MatchOptions {
+                case_sensitive: config.case_sensitive,
+                // the glob crate matches a component at a time, so a wildcard never spans directories
+                require_literal_separator: true,
+                require_literal_leading_dot: false,
+            },
+            real_base: std::fs::canonicalize(listing_path(&base)).ok(),
+            base,
+        })
+    }
+
+    /// Walk from the fixed part of the pattern, if it is a directory, handing over each matching file. With
+    /// --hdd-mode a disk is read one file at a time, and listing it from many threads would make it seek just
+    /// the same
+    fn run(&self, found: &(dyn Fn(String) + Sync)) {
+        let listing = listing_path(&self.base);
+        if !listing.is_dir() {
+            return;
+        }
+
+        let threads = if self.config.single_thread || self.config.hdd_mode {
+            1
+        } else {
+            walk_threads()
+        };
+        WalkBuilder::new(listing)
+            .standard_filters(false)
+            .follow_links(self.config.links == LinkMode::Follow)
+            .threads(threads)
+            .build_parallel()
+            .run(|| {
+                Box::new(move |entry| match entry {
+                    Ok(entry) => self.visit(&entry, found),
+                    Err(e) => {
+                        self.report(&e);
+                        WalkState::Continue
+                    }
+                })
+            });
+    }
+
+    /// Hand over the entry if it is a matching file, and say whether to go into it if it is a directory
+    fn visit(&self, entry: &DirEntry, found: &(dyn Fn(String) + Sync)) -> WalkState {
+        // once the run has been stopped, nothing more is wanted
+        if stats::aborted() {
+            return WalkState::Quit;
+        }
+        // the start of the walk is walked as asked, even inside a virtual filesystem
+        if entry.depth() == 0 {
+            return WalkState::Continue;
+        }
+
+        let path = self.path(entry.path());
+        if entry.path_is_symlink() && self.config.links != LinkMode::Follow {
+            if self.config.links == LinkMode::Report {
+                tracing::warn!("Not following link '{}'", path.display());
+            }
+            return WalkState::Continue;
+        }
+
+        let Some(file_type) = entry.file_type() else {
+            return WalkState::Continue;
+        };
+        if file_type.is_dir() {
+            if !self.config.include_pseudo_fs && self.is_pseudo_fs(entry) {
+                tracing::warn!(
+                    "Skipping '{}', a virtual filesystem (--include-pseudo-fs to hash it)",
+                    path.display()
+                );
+                return WalkState::Skip;
+            }
+        } else if file_type.is_file() && self.pattern.matches_path_with(&path, self.options) {
+            found(path.to_string_lossy().into_owned());
+        }
+        WalkState::Continue
+    }
+
+    /// The path of an entry as the pattern gives it, without the `./` of a walk from the current directory
+    fn path(&self, walked: &Path) -> PathBuf {
+        match walked.strip_prefix(".") {
+            Ok(path) if self.base.as_os_str().is_empty() && !path.as_os_str().is_empty() => {
+                path.to_path_buf()
+            }
+            _ => walked.to_path_buf(),
+        }
+    }
+
+    /// true if the directory is one of the virtual filesystems. A link is known by where it leads, anything
+    /// else by where it is under the start of the walk
+    fn is_pseudo_fs(&self, entry: &DirEntry) -> bool {
+        let real = if entry.path_is_symlink() {
+            std::fs::canonicalize(entry.path()).ok()
+        } else {
+            let listing = listing_path(&self.base);
+            self.real_base.as_ref().and_then(|real_base| {
+                let relative = entry.path().strip_prefix(listing).ok()?;
+                Some(real_base.join(relative))
+            })
+        };
+        real.is_some_and(|real| {
+            PSEUDO_PATHS
+                .iter()
+                .any(|pseudo| real.components().eq(Path::new(pseudo).components()))
+        })
+    }
+
+    /// Warn about a directory that couldn't be read, or a link leading back to a directory already being walked,
+    /// which would go round in a loop forever. Broken links are left out quietly
+    fn report(&self, error: &ignore::Error) {
+        match error {
+            ignore::Error::WithDepth { err, .. } => self.report(err),
+            ignore::Error::Loop { ancestor, child } => tracing::warn!(
+                "Not going into '{}', a loop back to '{}'",
+                self.path(child).display(),
+                self.path(ancestor).display()
+            ),
+            ignore::Error::WithPath { path, err } => {
+                if err
+                    .io_error()
+                    .is_none_or(|e| e.kind() != ErrorKind::NotFound)
+                {
+                    tracing::warn!("Cannot read '{}': {err}", self.path(path).display());
+                }
+            }
+            _ => tracing::warn!("{error}"),
+        }
+    }
+}
+
+/// Threads to list directories on, as many as hash
+#[cfg(feature = "parallel")]
+fn walk_threads() -> usize {
+    rayon::current_num_threads()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn walk_threads() -> usize {
+    1
+}
+
+/// The path to list for a directory, which is empty for the current directory
+fn listing_path(dir: &Path) -> &Path {
+    if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    }
+}
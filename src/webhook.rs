@@ -0,0 +1,91 @@
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use ureq::Agent;
+
+use crate::classes::{ConfigSettings, VERSION};
+use crate::json::JsonString;
+use crate::stats;
+
+// Sending the results to a collector for --post-results, so scans on many machines can report to one place.
+// The request is made with ureq, a small blocking HTTP client, with rustls for https
+
+/// Longest wait for the collector, so a dead endpoint can't hold up the end of the run for ever
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Check the URL when the options are read, before any hashing is done
+pub fn check_url(url: &str) -> anyhow::Result<()> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "--post-results needs an http:// or https:// URL, not '{url}'"
+        ))
+    }
+}
+
+/// POST the results and summary of the run as JSON. A failure is reported, but doesn't fail the run
+pub fn post_results(config: &ConfigSettings, url: &str, elapsed: Duration) {
+    let body = results_json(config, elapsed);
+    match post(url, &body) {
+        Ok(()) => tracing::info!("Posted the results to {url}"),
+        Err(e) => tracing::warn!("Could not post the results to {url}: {e}"),
+    }
+}
+
+/// The run as a JSON object: what was hashed and how, the counts, and every result and failure. Each result
+/// is in the form read by `--convert`
+pub fn results_json(config: &ConfigSettings, elapsed: Duration) -> String {
+    let algorithm = JsonString(config.algorithm.name());
+    let mut body = format!(
+        r#"{{"version":{},"algorithm":{algorithm},"path":{},"summary":{{"#,
+        JsonString(VERSION.unwrap_or("?")),
+        config
+            .supplied_path
+            .as_deref()
+            .map_or_else(|| "null".to_string(), |path| JsonString(path).to_string()),
+    );
+    for (name, count) in stats::counts() {
+        let _ = write!(body, r#""{name}":{count},"#);
+    }
+    let _ = write!(
+        body,
+        r#""elapsed_ms":{}}},"results":["#,
+        elapsed.as_millis()
+    );
+
+    for (index, (path, hash, size)) in stats::take_results().iter().enumerate() {
+        let comma = if index == 0 { "" } else { "," };
+        let _ = write!(
+            body,
+            r#"{comma}{{"path":{},"algorithm":{algorithm},"hash":{},"size":{size}}}"#,
+            JsonString(path),
+            JsonString(hash)
+        );
+    }
+    body.push_str(r#"],"failures":["#);
+    for (index, (path, reason)) in stats::failures().iter().enumerate() {
+        let comma = if index == 0 { "" } else { "," };
+        let _ = write!(
+            body,
+            r#"{comma}{{"path":{},"reason":{}}}"#,
+            JsonString(path),
+            JsonString(reason)
+        );
+    }
+    body.push_str("]}");
+    body
+}
+
+/// Send the body to the URL. A status other than success is an error, as with any other failure
+fn post(url: &str, body: &str) -> anyhow::Result<()> {
+    let agent: Agent = Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .into();
+    agent
+        .post(url)
+        .header("Content-Type", "application/json")
+        .send(body)?;
+    Ok(())
+}
@@ -9,3 +9,52 @@
 // fn integration_it_works() {
 //     assert_eq!(2 + 2, 4);
 // }
+
+#[cfg(feature = "glob")]
+#[test]
+fn copy_to_inside_the_tree() {
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    // enough directories that the walk is still going when the first copies are made, with the copies going
+    // into one of the first it lists
+    let root = std::env::temp_dir().join(format!("hash_rust_copy_to_{}", std::process::id()));
+    for dir in 0..1000 {
+        let dir = root.join(format!("d{dir:03}"));
+        fs::create_dir_all(&dir).unwrap();
+        for file in 0..2 {
+            fs::write(dir.join(format!("f{file}.txt")), format!("{dir:?} {file}")).unwrap();
+        }
+    }
+    let dest = root.join("d000").join("out");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hash_rust"))
+        .arg(format!("{}/**/*", root.display()))
+        .arg("--copy-to")
+        .arg(&dest)
+        .args(["-a", "crc32"])
+        .output()
+        .unwrap();
+
+    fn count(dir: &Path) -> usize {
+        fs::read_dir(dir).map_or(0, |entries| {
+            entries
+                .map(|entry| entry.unwrap().path())
+                .map(|path| if path.is_dir() { count(&path) } else { 1 })
+                .sum()
+        })
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let copied = count(&dest);
+    let nested = count(&dest.join("d000").join("out"));
+    fs::remove_dir_all(&root).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout.lines().filter(|line| line.ends_with("OK")).count(),
+        2000
+    );
+    assert_eq!(copied, 2000);
+    assert_eq!(nested, 0);
+}